@@ -0,0 +1,29 @@
+// Flag parsing shared by the command line tools
+
+use crate::error_codes::RobotError;
+use std::str::FromStr;
+
+/// Parses the value given for a flag or argument
+/// # Arguments
+/// * `name` - Name of the flag or argument, used in the error
+/// * `value` - The text given for it
+pub(crate) fn parse_arg<T: FromStr>(name: &str, value: &str) -> Result<T, RobotError> {
+    value
+        .parse::<T>()
+        .map_err(|_| format!("Invalid value for {}: {}", name, value))
+}
+
+/// Parses a timeout in seconds, which has to be finite and above zero
+/// # Arguments
+/// * `name` - Name of the flag, used in the error
+/// * `value` - The text given for it
+pub(crate) fn parse_timeout(name: &str, value: &str) -> Result<f64, RobotError> {
+    let timeout: f64 = parse_arg(name, value)?;
+    if !(timeout.is_finite() && timeout > 0.0) {
+        return Err(format!(
+            "{} needs a number of seconds above zero: {}",
+            name, value
+        ));
+    }
+    Ok(timeout)
+}
@@ -0,0 +1,64 @@
+// Terminal teach pendant, e.g.
+//
+// pendant --host 10.5.2.12 --file reader.toml
+//
+// Shows the live position and status of the robot, jogs it from the keyboard and keeps a list of
+// taught locations. The keys are listed at the bottom of the screen and Space halts the robot.
+// The controller address defaults to the ROBOT_IP environment variable.
+
+use pflex_module_rs::pendant::PendantOptions;
+use ratatui::crossterm::event::{self, Event};
+use std::process::exit;
+use std::time::{Duration, Instant};
+
+// how often the position and status are read while no key is pressed
+const REFRESH_INTERVAL: Duration = Duration::from_millis(200);
+
+fn main() {
+    let mut args = std::env::args().skip(1).collect::<Vec<String>>();
+    if args.iter().any(|arg| arg == "-h" || arg == "--help") {
+        println!("{}", PendantOptions::USAGE);
+        return;
+    }
+    if let Ok(ip) = std::env::var("ROBOT_IP") {
+        args.splice(0..0, ["--host".to_string(), ip]);
+    }
+    let fail = |error: String| -> ! {
+        eprintln!("{}", error);
+        exit(1);
+    };
+    let options = PendantOptions::from_args(args).unwrap_or_else(|e| fail(e));
+    let mut pendant = options.open().unwrap_or_else(|e| fail(e));
+
+    // restores the terminal on panics as well
+    let mut terminal = ratatui::init();
+    let mut refreshed = Instant::now() - REFRESH_INTERVAL;
+    let result = loop {
+        if refreshed.elapsed() >= REFRESH_INTERVAL {
+            if let Err(e) = pendant.refresh() {
+                break Err(format!("Lost the robot: {}", e));
+            }
+            refreshed = Instant::now();
+        }
+        if let Err(e) = terminal.draw(|frame| pendant.draw(frame)) {
+            break Err(e.to_string());
+        }
+        match event::poll(REFRESH_INTERVAL.saturating_sub(refreshed.elapsed())) {
+            Ok(true) => match event::read() {
+                Ok(Event::Key(key)) => pendant.handle_key(key),
+                Ok(_) => {}
+                Err(e) => break Err(e.to_string()),
+            },
+            Ok(false) => {}
+            Err(e) => break Err(e.to_string()),
+        }
+        if pendant.is_finished() {
+            break Ok(());
+        }
+    };
+    ratatui::restore();
+    pendant.robot().disconnect();
+    if let Err(e) = result {
+        fail(e);
+    }
+}
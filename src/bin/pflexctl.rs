@@ -0,0 +1,42 @@
+// Runs everyday robot operations from the command line, e.g.
+//
+// pflexctl --host 10.5.2.12 power on
+// pflexctl --json where joints
+//
+// Run with `--help` to list every command. The controller address defaults to the ROBOT_IP
+// environment variable. Reports are printed to stdout, as JSON with `--json`, and the exit code
+// is 1 when the robot returns an error and 2 when the command line is invalid.
+
+use pflex_module_rs::cli::CtlOptions;
+use serde_json::json;
+use std::process::exit;
+
+fn main() {
+    let mut args = std::env::args().skip(1).collect::<Vec<String>>();
+    if args.is_empty() || args.iter().any(|arg| arg == "-h" || arg == "--help") {
+        println!("{}", CtlOptions::USAGE);
+        return;
+    }
+    if let Ok(ip) = std::env::var("ROBOT_IP") {
+        args.splice(0..0, ["--host".to_string(), ip]);
+    }
+    let json = args.iter().any(|arg| arg == "--json");
+    let fail = |error: String, code: i32| -> ! {
+        if json {
+            println!("{}", json!({ "error": error }));
+        } else {
+            eprintln!("{}", error);
+        }
+        exit(code);
+    };
+    let options = CtlOptions::from_args(args).unwrap_or_else(|e| fail(e, 2));
+    let mut robot = options.connect().unwrap_or_else(|e| fail(e, 1));
+    match options.command.run(&mut robot) {
+        Ok(report) if options.json => println!("{}", report.to_json()),
+        Ok(report) => println!("{}", report),
+        Err(e) => {
+            robot.disconnect();
+            fail(e, 1)
+        }
+    }
+}
@@ -0,0 +1,88 @@
+// Interactive TCS shell for commissioning, e.g.
+//
+// tcs_repl --host 10.5.2.12 --macros cell.macros
+//
+// Tab completes commands, `:` meta commands and `@` macros, and the arguments of a command are
+// hinted after it. Type `:help` for every command. The controller address defaults to the
+// ROBOT_IP environment variable and the line history is kept in ~/.pflex_tcs_history.
+
+use pflex_module_rs::repl::{Repl, ReplHelper, ReplOptions, ReplOutcome};
+use rustyline::error::ReadlineError;
+use rustyline::history::DefaultHistory;
+use rustyline::Editor;
+use std::process::exit;
+
+fn main() {
+    let mut args = std::env::args().skip(1).collect::<Vec<String>>();
+    if args.iter().any(|arg| arg == "-h" || arg == "--help") {
+        println!("{}", ReplOptions::USAGE);
+        return;
+    }
+    if let Ok(ip) = std::env::var("ROBOT_IP") {
+        args.splice(0..0, ["--host".to_string(), ip]);
+    }
+    let fail = |error: String| -> ! {
+        eprintln!("{}", error);
+        exit(1);
+    };
+    let options = ReplOptions::from_args(args).unwrap_or_else(|e| fail(e));
+    let mut repl = Repl::new(options.connect().unwrap_or_else(|e| fail(e)));
+    if let Some(path) = &options.macros {
+        repl.load_macros(path).unwrap_or_else(|e| fail(e));
+    }
+
+    let mut editor: Editor<ReplHelper, DefaultHistory> =
+        Editor::new().unwrap_or_else(|e| fail(format!("Failed to start the line editor: {}", e)));
+    let mut helper = ReplHelper::default();
+    helper.update_macros(&repl);
+    editor.set_helper(Some(helper));
+    if let Some(path) = &options.history {
+        // there is no history the first time
+        editor.load_history(path).ok();
+    }
+    println!(
+        "Connected to {}:{}, type :help for the commands",
+        options.host, options.port
+    );
+
+    loop {
+        match editor.readline(Repl::PROMPT) {
+            Ok(line) => {
+                if !line.trim().is_empty() {
+                    editor.add_history_entry(line.as_str()).ok();
+                }
+                let outcome = repl.eval(&line);
+                if let Some(helper) = editor.helper_mut() {
+                    helper.update_macros(&repl);
+                }
+                match outcome {
+                    ReplOutcome::Continue(text) => {
+                        if !text.is_empty() {
+                            println!("{}", text);
+                        }
+                    }
+                    ReplOutcome::Quit(text) => {
+                        if !text.is_empty() {
+                            println!("{}", text);
+                        }
+                        break;
+                    }
+                }
+            }
+            // Ctrl-C abandons the line, Ctrl-D leaves
+            Err(ReadlineError::Interrupted) => continue,
+            Err(ReadlineError::Eof) => break,
+            Err(e) => {
+                eprintln!("{}", e);
+                break;
+            }
+        }
+    }
+
+    if let Some(path) = &options.history {
+        if let Err(e) = editor.save_history(path) {
+            eprintln!("Failed to save the history to {}: {}", path.display(), e);
+        }
+    }
+    repl.disconnect();
+}
@@ -0,0 +1,487 @@
+// Everyday robot operations from the command line
+//
+// The `pflexctl` binary parses its flags and subcommand into CtlOptions and runs the CtlCommand
+// on a robot, printing the CtlReport as text or, with `--json`, as one JSON object for scripts:
+//
+// pflexctl --host 10.5.2.12 status
+// pflexctl power on
+// pflexctl --json where joints
+// pflexctl move pose 350 0 200 90 --profile 2
+// pflexctl gripper close 127 --force 30
+// pflexctl raw pd 2800 1 0 1
+
+use crate::args::{parse_arg, parse_timeout};
+use crate::connection::SharedConnection;
+use crate::enums::Joint;
+use crate::error_codes::RobotError;
+use crate::pflex::PFlexRobot;
+use crate::structs::{EndEffectorPosition, JointPosition};
+use crate::tcs_client::TCSClient;
+use crate::units::{Degrees, Millimetres, Percent, Seconds};
+use log::info;
+use serde_json::json;
+use std::fmt;
+
+/// Connection settings and the subcommand to run
+#[derive(Debug, Clone, PartialEq)]
+pub struct CtlOptions {
+    /// IP address of the controller
+    pub host: String,
+    /// Port the TCS server is listening on
+    pub port: u16,
+    /// Index of the robot on the controller, starting at 1
+    pub robot: i32,
+    /// Whether the robot is mounted on a rail
+    pub rail: bool,
+    /// Timeout for every socket read and write in seconds
+    pub timeout: Option<f64>,
+    /// Whether the report is printed as JSON
+    pub json: bool,
+    pub command: CtlCommand,
+}
+
+/// A subcommand of `pflexctl`
+#[derive(Debug, Clone, PartialEq)]
+pub enum CtlCommand {
+    /// Reports power, attachment, homing, motion state and system speed
+    Status,
+    Power(bool),
+    Attach,
+    Home,
+    /// Reports the current tool pose
+    WhereCartesian,
+    /// Reports the current joint positions
+    WhereJoints,
+    /// Moves to a location stored on the controller and waits for the move to finish
+    MoveToLocation {
+        location_id: i32,
+        motion_profile_id: i32,
+    },
+    /// Moves to a pose and waits for the move to finish
+    MoveToPose {
+        pose: EndEffectorPosition,
+        motion_profile_id: i32,
+    },
+    /// Opens the fingers to a width
+    GripperOpen {
+        width: Millimetres,
+        finger_speed: Percent,
+    },
+    /// Closes the fingers on a plate of a width
+    GripperClose {
+        width: Millimetres,
+        finger_speed: Percent,
+        grasp_force: Percent,
+    },
+    /// Switches free mode, releasing only the horizontal joints unless Z is asked for
+    FreeMode {
+        free_on: bool,
+        with_z: bool,
+    },
+    GetSpeed,
+    SetSpeed(Percent),
+    Halt,
+    /// Sends a TCS request written out in full
+    Raw(String),
+}
+
+/// What a subcommand reports back
+#[derive(Debug, Clone, PartialEq)]
+pub enum CtlReport {
+    Done,
+    Status(RobotStatus),
+    Pose(EndEffectorPosition),
+    Joints(JointPosition),
+    Speed(Percent),
+    Grasped(bool),
+    /// Reply to a raw request, after the response code
+    Reply(Vec<String>),
+}
+
+/// State of the robot reported by `status`
+#[derive(Debug, Clone, PartialEq, serde::Serialize)]
+pub struct RobotStatus {
+    pub powered: bool,
+    pub attached: bool,
+    pub homed: bool,
+    pub motion_state: String,
+    pub system_speed: Percent,
+}
+
+impl CtlOptions {
+    /// Flags and subcommands accepted by `from_args`
+    pub const USAGE: &'static str = "\
+usage: pflexctl [options] <command>
+options:
+  --host <ip>           controller address, defaults to the ROBOT_IP environment variable
+  --port <port>         TCS server port
+  --robot <index>       robot on the controller, starting at 1
+  --rail / --no-rail    whether the robot is mounted on a rail, off by default
+  --timeout <seconds>   timeout for every socket read and write
+  --json                print the result as JSON
+commands:
+  status                             power, attachment, homing, motion state and speed
+  connect                            same as status
+  power on|off
+  attach                             only lasts until pflexctl exits, commands that need
+                                     the robot attached attach it themselves
+  home
+  where [cartesian|joints]
+  move loc <id> [--profile <id>]
+  move pose <x> <y> <z> <yaw> [--profile <id>]
+  gripper open [mm] [--speed <percent>]
+  gripper close <mm> [--speed <percent>] [--force <percent>]
+  free on [--with-z]|off             release the horizontal joints, and Z with --with-z
+  speed [percent]                    read or set the system speed
+  halt
+  raw <request...>                   send a TCS request, e.g. raw pd 2800 1 0 1";
+
+    /// Builds the options from command line flags, see `USAGE`
+    /// # Arguments
+    /// * `args` - The flags and subcommand, not including the program name
+    pub fn from_args<I: IntoIterator<Item = String>>(args: I) -> Result<Self, RobotError> {
+        let mut host = "127.0.0.1".to_string();
+        let mut port = TCSClient::TCS_SERVER_PORT;
+        let mut robot = SharedConnection::DEFAULT_ROBOT_INDEX;
+        let mut rail = false;
+        let mut timeout = None;
+        let mut json = false;
+        let mut words: Vec<String> = Vec::new();
+        let mut args = args.into_iter();
+        while let Some(arg) = args.next() {
+            // everything after `raw` belongs to the request
+            if words.first().is_some_and(|word| word == "raw") {
+                words.push(arg);
+                continue;
+            }
+            let mut value = || args.next().ok_or(format!("{} needs a value", arg));
+            match arg.as_str() {
+                "--host" => host = value()?,
+                "--port" => port = parse_arg(&arg, &value()?)?,
+                "--robot" => robot = parse_arg(&arg, &value()?)?,
+                "--rail" => rail = true,
+                "--no-rail" => rail = false,
+                "--timeout" => timeout = Some(parse_timeout(&arg, &value()?)?),
+                "--json" => json = true,
+                _ => words.push(arg),
+            }
+        }
+        let words = words.iter().map(String::as_str).collect::<Vec<&str>>();
+        Ok(CtlOptions {
+            host,
+            port,
+            robot,
+            rail,
+            timeout,
+            json,
+            command: CtlCommand::parse(&words)?,
+        })
+    }
+
+    /// Connects to the robot the options point at
+    pub fn connect(&self) -> Result<PFlexRobot, RobotError> {
+        info!("CtlOptions::connect called");
+        let connection = SharedConnection::connect(&self.host, self.port, self.timeout)
+            .map_err(|e| format!("Failed to connect to {}:{}: {}", self.host, self.port, e))?;
+        Ok(connection.robot(self.robot, self.rail))
+    }
+}
+
+impl CtlCommand {
+    /// Width the fingers open to when `gripper open` isn't given one
+    pub const DEFAULT_OPEN_MM: Millimetres = Millimetres::from_mm(130.0);
+    /// Motion profile used by moves when `--profile` isn't given
+    pub const DEFAULT_MOTION_PROFILE: i32 = 1;
+    const DEFAULT_FINGER_SPEED_PERCENT: f64 = 50.0;
+    const DEFAULT_GRASP_FORCE_PERCENT: f64 = 50.0;
+    /// How long a move is waited for
+    const MOVE_TIMEOUT_SECONDS: f64 = 60.0;
+    // flags that are given on their own, without a value
+    const SWITCHES: [&'static str; 1] = ["--with-z"];
+
+    /// Parses a subcommand and its arguments
+    /// # Arguments
+    /// * `words` - The subcommand followed by its arguments, e.g. `["power", "on"]`
+    pub fn parse(words: &[&str]) -> Result<Self, RobotError> {
+        let (name, rest) = words
+            .split_first()
+            .ok_or(format!("Missing command\n{}", CtlOptions::USAGE))?;
+        if *name == "raw" {
+            if rest.is_empty() {
+                return Err("raw needs a request, e.g. raw nop".to_string());
+            }
+            return Ok(CtlCommand::Raw(rest.join(" ")));
+        }
+        let (args, flags) = split_flags(rest)?;
+        let flag = |name: &str| flags.iter().find(|(flag, _)| *flag == name).map(|f| f.1);
+        for (flag, _) in &flags {
+            let known = match *name {
+                "move" => ["--profile"].contains(flag),
+                "gripper" => ["--speed", "--force"].contains(flag),
+                "free" => Self::SWITCHES.contains(flag),
+                _ => false,
+            };
+            if !known {
+                return Err(format!("Unrecognised flag for {}: {}", name, flag));
+            }
+        }
+        let motion_profile_id = flag("--profile")
+            .map_or(Ok(Self::DEFAULT_MOTION_PROFILE), |id| {
+                parse_arg::<i32>("--profile", id)
+            })?;
+        let finger_speed = flag("--speed").map_or(Ok(Self::DEFAULT_FINGER_SPEED_PERCENT), |p| {
+            parse_arg::<f64>("--speed", p)
+        })?;
+        let finger_speed = Percent::new(finger_speed)?;
+        let grasp_force = flag("--force").map_or(Ok(Self::DEFAULT_GRASP_FORCE_PERCENT), |p| {
+            parse_arg::<f64>("--force", p)
+        })?;
+        let grasp_force = Percent::new(grasp_force)?;
+
+        let command = match (*name, args.as_slice()) {
+            ("status" | "connect", []) => CtlCommand::Status,
+            ("power", [state]) => CtlCommand::Power(parse_switch(state)?),
+            ("attach", []) => CtlCommand::Attach,
+            ("home", []) => CtlCommand::Home,
+            ("where", [] | ["cartesian"]) => CtlCommand::WhereCartesian,
+            ("where", ["joints"]) => CtlCommand::WhereJoints,
+            ("move", ["loc", id]) => CtlCommand::MoveToLocation {
+                location_id: parse_arg("location", id)?,
+                motion_profile_id,
+            },
+            ("move", ["pose", x, y, z, yaw]) => CtlCommand::MoveToPose {
+                pose: EndEffectorPosition {
+                    x_mm: Millimetres::from_mm(parse_arg("x", x)?),
+                    y_mm: Millimetres::from_mm(parse_arg("y", y)?),
+                    z_mm: Millimetres::from_mm(parse_arg("z", z)?),
+                    yaw_deg: Degrees::from_deg(parse_arg("yaw", yaw)?),
+                    pitch_deg: PFlexRobot::DEFAULT_EE_PITCH,
+                    roll_deg: PFlexRobot::DEFAULT_EE_ROLL,
+                },
+                motion_profile_id,
+            },
+            ("gripper", ["open"]) => CtlCommand::GripperOpen {
+                width: Self::DEFAULT_OPEN_MM,
+                finger_speed,
+            },
+            ("gripper", ["open", width]) => CtlCommand::GripperOpen {
+                width: Millimetres::from_mm(parse_arg("width", width)?),
+                finger_speed,
+            },
+            ("gripper", ["close", width]) => CtlCommand::GripperClose {
+                width: Millimetres::from_mm(parse_arg("width", width)?),
+                finger_speed,
+                grasp_force,
+            },
+            ("free", [state]) => CtlCommand::FreeMode {
+                free_on: parse_switch(state)?,
+                with_z: flag("--with-z").is_some(),
+            },
+            ("speed", []) => CtlCommand::GetSpeed,
+            ("speed", [percent]) => {
+                CtlCommand::SetSpeed(Percent::new(parse_arg("speed", percent)?)?)
+            }
+            ("halt", []) => CtlCommand::Halt,
+            _ => {
+                return Err(format!(
+                    "Unrecognised command: {}\n{}",
+                    words.join(" "),
+                    CtlOptions::USAGE
+                ))
+            }
+        };
+        Ok(command)
+    }
+
+    /// Runs the subcommand on a robot
+    /// # Arguments
+    /// * `robot` - The connected robot
+    pub fn run(&self, robot: &mut PFlexRobot) -> Result<CtlReport, RobotError> {
+        info!("CtlCommand::run called");
+        // the controller detaches the robot once the connection that attached it closes, so an
+        // earlier `attach` doesn't last until the next run
+        if self.needs_attached_robot() {
+            robot.attach_robot()?;
+        }
+        let report = match self {
+            CtlCommand::Status => CtlReport::Status(RobotStatus {
+                powered: robot.is_robot_powered()?,
+                attached: robot.is_robot_attached()?,
+                homed: robot.is_robot_home()?,
+                motion_state: robot.get_motion_state()?.join(" "),
+                system_speed: robot.get_system_speed()?,
+            }),
+            CtlCommand::Power(power) => {
+                robot.send_power(*power)?;
+                CtlReport::Done
+            }
+            CtlCommand::Attach => {
+                robot.attach_robot()?;
+                CtlReport::Done
+            }
+            CtlCommand::Home => {
+                robot.home_robot()?;
+                CtlReport::Done
+            }
+            CtlCommand::WhereCartesian => CtlReport::Pose(robot.get_endeffector_position()?),
+            CtlCommand::WhereJoints => CtlReport::Joints(robot.get_joint_positions()?),
+            CtlCommand::MoveToLocation {
+                location_id,
+                motion_profile_id,
+            } => {
+                robot.move_to_waypoint(*location_id, *motion_profile_id)?;
+                Self::wait_for_move(robot)?;
+                CtlReport::Done
+            }
+            CtlCommand::MoveToPose {
+                pose,
+                motion_profile_id,
+            } => {
+                robot.move_to_cartesian(pose.clone(), *motion_profile_id)?;
+                Self::wait_for_move(robot)?;
+                CtlReport::Done
+            }
+            CtlCommand::GripperOpen {
+                width,
+                finger_speed,
+            } => {
+                robot.release_plate(*width, *finger_speed)?;
+                CtlReport::Done
+            }
+            CtlCommand::GripperClose {
+                width,
+                finger_speed,
+                grasp_force,
+            } => CtlReport::Grasped(robot.grasp_plate(*width, *finger_speed, *grasp_force)?),
+            CtlCommand::FreeMode {
+                free_on: true,
+                with_z,
+            } => {
+                // an empty list releases every joint, Z included
+                let joints: &[Joint] = match with_z {
+                    true => &[],
+                    false => &Joint::HORIZONTAL,
+                };
+                robot.set_free_mode_joints(joints, *with_z)?;
+                CtlReport::Done
+            }
+            CtlCommand::FreeMode { free_on: false, .. } => {
+                robot.set_free_mode(false)?;
+                CtlReport::Done
+            }
+            CtlCommand::GetSpeed => CtlReport::Speed(robot.get_system_speed()?),
+            CtlCommand::SetSpeed(speed) => {
+                robot.set_system_speed(*speed)?;
+                CtlReport::Done
+            }
+            CtlCommand::Halt => {
+                robot.halt()?;
+                CtlReport::Done
+            }
+            CtlCommand::Raw(request) => CtlReport::Reply(robot.send_raw(request)?),
+        };
+        Ok(report)
+    }
+
+    // whether the controller refuses the command unless the robot is attached
+    fn needs_attached_robot(&self) -> bool {
+        matches!(
+            self,
+            CtlCommand::Home
+                | CtlCommand::MoveToLocation { .. }
+                | CtlCommand::MoveToPose { .. }
+                | CtlCommand::GripperOpen { .. }
+                | CtlCommand::GripperClose { .. }
+                | CtlCommand::FreeMode { free_on: true, .. }
+        )
+    }
+
+    fn wait_for_move(robot: &mut PFlexRobot) -> Result<(), RobotError> {
+        robot.wait_until_static(Seconds::new(Self::MOVE_TIMEOUT_SECONDS)?)
+    }
+}
+
+impl CtlReport {
+    /// The report as a JSON object
+    pub fn to_json(&self) -> serde_json::Value {
+        match self {
+            CtlReport::Done => json!({ "ok": true }),
+            CtlReport::Status(status) => json!(status),
+            CtlReport::Pose(pose) => json!(pose),
+            CtlReport::Joints(joints) => json!(joints),
+            CtlReport::Speed(speed) => json!({ "system_speed": speed }),
+            CtlReport::Grasped(grasped) => json!({ "grasped": grasped }),
+            CtlReport::Reply(reply) => json!({ "reply": reply }),
+        }
+    }
+}
+
+impl fmt::Display for CtlReport {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let yes_no = |value: bool| if value { "yes" } else { "no" };
+        match self {
+            CtlReport::Done => write!(f, "ok"),
+            CtlReport::Status(status) => write!(
+                f,
+                "powered: {}\nattached: {}\nhomed: {}\nmotion state: {}\nsystem speed: {}",
+                yes_no(status.powered),
+                yes_no(status.attached),
+                yes_no(status.homed),
+                status.motion_state,
+                status.system_speed
+            ),
+            CtlReport::Pose(pose) => write!(
+                f,
+                "x: {}\ny: {}\nz: {}\nyaw: {}\npitch: {}\nroll: {}",
+                pose.x_mm, pose.y_mm, pose.z_mm, pose.yaw_deg, pose.pitch_deg, pose.roll_deg
+            ),
+            CtlReport::Joints(joints) => {
+                write!(
+                    f,
+                    "z: {}\nshoulder: {}\nelbow: {}\nwrist: {}\ngripper: {}",
+                    joints.z_mm,
+                    joints.shoulder_deg,
+                    joints.elbow_deg,
+                    joints.wrist_deg,
+                    joints.gripper_mm
+                )?;
+                match joints.rail_mm {
+                    Some(rail_mm) => write!(f, "\nrail: {}", rail_mm),
+                    None => Ok(()),
+                }
+            }
+            CtlReport::Speed(speed) => write!(f, "{}", speed),
+            CtlReport::Grasped(grasped) => write!(f, "grasped: {}", yes_no(*grasped)),
+            CtlReport::Reply(reply) => write!(f, "{}", reply.join(" ")),
+        }
+    }
+}
+
+// `--flag value` pairs given after a subcommand
+type Flags<'a> = Vec<(&'a str, &'a str)>;
+
+// splits the flags from the positional arguments
+fn split_flags<'a>(words: &[&'a str]) -> Result<(Vec<&'a str>, Flags<'a>), RobotError> {
+    let mut args = Vec::new();
+    let mut flags = Vec::new();
+    let mut words = words.iter();
+    while let Some(word) = words.next() {
+        if CtlCommand::SWITCHES.contains(word) {
+            flags.push((*word, ""));
+        } else if word.starts_with("--") {
+            let value = words.next().ok_or(format!("{} needs a value", word))?;
+            flags.push((*word, *value));
+        } else {
+            args.push(*word);
+        }
+    }
+    Ok((args, flags))
+}
+
+fn parse_switch(state: &str) -> Result<bool, RobotError> {
+    match state {
+        "on" => Ok(true),
+        "off" => Ok(false),
+        _ => Err(format!("Expected on or off, got {}", state)),
+    }
+}
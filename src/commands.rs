@@ -1,7 +1,9 @@
 use std::fmt;
+use strum::IntoEnumIterator;
+use strum_macros::EnumIter;
 
 /// Commands enumerator for the robot API
-#[derive(Debug, PartialEq, Clone)]
+#[derive(Debug, PartialEq, Clone, EnumIter)]
 pub enum TCSCommand {
     Mode,
     Exit,
@@ -23,12 +25,96 @@ pub enum TCSCommand {
     GetLocJoints,
     GetLocCart,
     FreeMode,
+    ZeroTorque,
     NoOp,
     SystemSpeed,
     Payload,
     WaitForEOM,
     Rail,
     Signal,
+    GraspPlate,
+    ReleasePlate,
+}
+
+impl TCSCommand {
+    /// Finds the command sent with a mnemonic, e.g. `wherec`
+    /// # Arguments
+    /// * `mnemonic` - The name of the command on the wire, in any case like the controller takes it
+    pub fn from_mnemonic(mnemonic: &str) -> Option<Self> {
+        TCSCommand::iter().find(|command| command.to_string().eq_ignore_ascii_case(mnemonic))
+    }
+
+    /// Arguments the command takes, optional ones in square brackets
+    pub fn arguments(&self) -> &'static str {
+        match self {
+            TCSCommand::Mode => "[0|1]",
+            TCSCommand::Exit => "",
+            TCSCommand::Power => "[0|1]",
+            TCSCommand::Select => "[robot]",
+            TCSCommand::Attach => "[0|1]",
+            TCSCommand::Home => "",
+            TCSCommand::Halt => "",
+            TCSCommand::Loc => "[location]",
+            TCSCommand::LocXyz => "<location> <x> <y> <z> <yaw> <pitch> <roll>",
+            TCSCommand::Profile => {
+                "<profile> [speed speed2 accel decel accel_ramp decel_ramp in_range straight]"
+            }
+            TCSCommand::Move => "<location> <profile>",
+            TCSCommand::MoveToCart => "<profile> <x> <y> <z> <yaw> <pitch> <roll>",
+            TCSCommand::MoveToJoints => "<profile> <z> <shoulder> <elbow> <wrist> <gripper> [rail]",
+            TCSCommand::MotionState => "",
+            TCSCommand::MoveOneAxis => "<axis> <position> <profile>",
+            TCSCommand::MoveRail => "<station> <mode> <position>",
+            TCSCommand::GetParam => "<data id> [args...]",
+            TCSCommand::GetLocJoints => "",
+            TCSCommand::GetLocCart => "",
+            TCSCommand::FreeMode => "<axis|0 for all|-1 for off>",
+            TCSCommand::ZeroTorque => "<0|1> <axis mask>",
+            TCSCommand::NoOp => "",
+            TCSCommand::SystemSpeed => "[percent]",
+            TCSCommand::Payload => "[percent]",
+            TCSCommand::WaitForEOM => "",
+            TCSCommand::Rail => "[position]",
+            TCSCommand::Signal => "<signal> [0|1]",
+            TCSCommand::GraspPlate => "<width> <finger speed> <force>",
+            TCSCommand::ReleasePlate => "<width> <finger speed>",
+        }
+    }
+
+    /// What the command does
+    pub fn description(&self) -> &'static str {
+        match self {
+            TCSCommand::Mode => "Reads or sets whether replies are verbose",
+            TCSCommand::Exit => "Closes the connection",
+            TCSCommand::Power => "Reads or switches the robot power",
+            TCSCommand::Select => "Reads or selects the robot commands are carried out on",
+            TCSCommand::Attach => "Reads or sets whether the robot is attached",
+            TCSCommand::Home => "Homes the robot",
+            TCSCommand::Halt => "Stops the robot",
+            TCSCommand::Loc => "Reads a stored location, or the current one",
+            TCSCommand::LocXyz => "Stores a Cartesian location",
+            TCSCommand::Profile => "Reads or stores a motion profile",
+            TCSCommand::Move => "Moves to a stored location",
+            TCSCommand::MoveToCart => "Moves to a Cartesian pose",
+            TCSCommand::MoveToJoints => "Moves every joint to a position",
+            TCSCommand::MotionState => "Reads whether the robot is moving",
+            TCSCommand::MoveOneAxis => "Moves a single joint",
+            TCSCommand::MoveRail => "Moves the rail",
+            TCSCommand::GetParam => "Reads a value from the parameter database",
+            TCSCommand::GetLocJoints => "Reads the joint positions",
+            TCSCommand::GetLocCart => "Reads the Cartesian position",
+            TCSCommand::FreeMode => "Frees joints so the arm can be moved by hand",
+            TCSCommand::ZeroTorque => "Switches zero-torque mode on the joints in the mask",
+            TCSCommand::NoOp => "Does nothing, checks the connection",
+            TCSCommand::SystemSpeed => "Reads or sets the speed every profile is scaled by",
+            TCSCommand::Payload => "Reads or sets the payload as a percentage of rated payload",
+            TCSCommand::WaitForEOM => "Waits for the last move to finish",
+            TCSCommand::Rail => "Reads or sets the rail position",
+            TCSCommand::Signal => "Reads or switches a digital I/O signal",
+            TCSCommand::GraspPlate => "Closes the gripper on a plate",
+            TCSCommand::ReleasePlate => "Opens the gripper to release a plate",
+        }
+    }
 }
 
 impl fmt::Display for TCSCommand {
@@ -42,7 +128,7 @@ impl fmt::Display for TCSCommand {
             TCSCommand::Home => "home",
             TCSCommand::Halt => "halt",
             TCSCommand::Loc => "loc",
-            TCSCommand::LocXyz => "locXYZ",
+            TCSCommand::LocXyz => "locXyz",
             TCSCommand::Profile => "profile",
             TCSCommand::Move => "move",
             TCSCommand::MoveToCart => "movec",
@@ -54,12 +140,15 @@ impl fmt::Display for TCSCommand {
             TCSCommand::GetLocJoints => "wherej",
             TCSCommand::GetLocCart => "wherec",
             TCSCommand::FreeMode => "freemode",
+            TCSCommand::ZeroTorque => "zeroTorque",
             TCSCommand::NoOp => "nop",
             TCSCommand::SystemSpeed => "mspeed",
             TCSCommand::Payload => "payload",
             TCSCommand::WaitForEOM => "waitForEOM",
             TCSCommand::Rail => "rail",
-            TCSCommand::Signal => "sig"
+            TCSCommand::Signal => "sig",
+            TCSCommand::GraspPlate => "graspplate",
+            TCSCommand::ReleasePlate => "releaseplate",
         };
         write!(f, "{}", s)
     }
@@ -0,0 +1,277 @@
+// Connection to a controller shared by the robots it drives
+//
+// A controller driving more than one arm carries out each command on whichever robot was last
+// selected with `selectRobot` on that connection, starting with robot 1. Every robot sharing a
+// connection sends its commands through one lock, selecting itself first when another robot was
+// selected since, so a command can never reach the wrong robot or interleave with another robot's
+// command and reply. The controller also forgets which robots were attached once a connection
+// closes, so a connection that's opened again to discard a late reply attaches them again.
+
+use crate::commands::TCSCommand;
+use crate::error_codes::RobotError;
+use crate::pflex::PFlexRobot;
+#[cfg(feature = "record")]
+use crate::session::SessionRecorder;
+use crate::tcs_client::TCSClient;
+use log::{debug, info};
+use std::collections::BTreeSet;
+use std::sync::{Arc, Mutex, MutexGuard};
+
+struct ConnectionState {
+    tcs_client: TCSClient,
+    selected_robot: i32,
+    // robots attached over the connection, attached again after a reconnect
+    attached: BTreeSet<i32>,
+    // verbose mode last set over the connection, set again after a reconnect
+    mode: Option<String>,
+}
+
+impl ConnectionState {
+    fn select(&mut self, robot_index: i32) -> Result<(), RobotError> {
+        self.restore_if_reconnected()?;
+        if self.selected_robot != robot_index {
+            debug!("selecting robot {}", robot_index);
+            self.tcs_client.send_command(
+                TCSCommand::Select,
+                Some(vec![&robot_index.to_string()]),
+                true,
+                None,
+            )?;
+            self.selected_robot = robot_index;
+        }
+        Ok(())
+    }
+
+    // a new connection starts out with the first robot selected, nothing attached and replies
+    // that aren't verbose, so whatever was set up over the old connection is set up again
+    fn restore_if_reconnected(&mut self) -> Result<(), RobotError> {
+        if !self.tcs_client.take_reconnected() {
+            return Ok(());
+        }
+        self.selected_robot = SharedConnection::DEFAULT_ROBOT_INDEX;
+        if let Some(mode) = self.mode.clone() {
+            debug!("setting mode {} again after a reconnect", mode);
+            self.tcs_client
+                .send_command(TCSCommand::Mode, Some(vec![&mode]), true, None)?;
+        }
+        for robot_index in self.attached.clone() {
+            debug!("attaching robot {} again after a reconnect", robot_index);
+            self.select(robot_index)?;
+            self.tcs_client.send_command(
+                TCSCommand::Attach,
+                Some(vec![&robot_index.to_string()]),
+                true,
+                None,
+            )?;
+        }
+        Ok(())
+    }
+
+    // keeps track of what a request that went through set up on the controller, or sets the
+    // connection up again when the client reconnected to discard the reply
+    fn settle(
+        &mut self,
+        robot_index: i32,
+        command: &str,
+        argument: Option<&str>,
+        res: Result<Vec<String>, RobotError>,
+    ) -> Result<Vec<String>, RobotError> {
+        let Err(e) = res else {
+            self.remember(robot_index, command, argument);
+            return res;
+        };
+        match self.restore_if_reconnected() {
+            Ok(_) => Err(e),
+            Err(restore_error) => Err(format!(
+                "{}, failed to set the connection up again: {}",
+                e, restore_error
+            )),
+        }
+    }
+
+    fn remember(&mut self, robot_index: i32, command: &str, argument: Option<&str>) {
+        let is = |expected: TCSCommand| command.eq_ignore_ascii_case(&expected.to_string());
+        match argument {
+            Some(index) if is(TCSCommand::Select) => {
+                if let Ok(selected) = index.parse::<i32>() {
+                    self.selected_robot = selected;
+                }
+            }
+            Some("0") if is(TCSCommand::Attach) => {
+                self.attached.remove(&robot_index);
+            }
+            Some(_) if is(TCSCommand::Attach) => {
+                self.attached.insert(robot_index);
+            }
+            Some(mode) if is(TCSCommand::Mode) => self.mode = Some(mode.to_string()),
+            _ => {}
+        }
+    }
+
+    fn close(&mut self) -> Result<(), std::io::Error> {
+        if self.tcs_client.socket.is_some() {
+            let _res = self
+                .tcs_client
+                .send_command(TCSCommand::Exit, None, false, None);
+        }
+        self.tcs_client.disconnect()
+    }
+}
+
+impl Drop for ConnectionState {
+    fn drop(&mut self) {
+        if self.tcs_client.socket.is_some() {
+            let _kill = self.close();
+        }
+    }
+}
+
+/// Connection to a controller that can be shared by several robots
+///
+/// Cloning the connection shares it. The connection is closed once it's explicitly
+/// disconnected or every clone, including the ones held by robots, has been dropped.
+#[derive(Clone)]
+pub struct SharedConnection {
+    state: Arc<Mutex<ConnectionState>>,
+}
+
+impl SharedConnection {
+    /// Robot the controller selects when a client connects
+    pub const DEFAULT_ROBOT_INDEX: i32 = 1;
+
+    /// Connects to a TCS server
+    /// # Arguments
+    /// * `ip` - IP address of the controller
+    /// * `port` - Port the TCS server is listening on
+    /// * `timeout` - Optional timeout setting for all socket read/write attempts
+    pub fn connect(ip: &str, port: u16, timeout: Option<f64>) -> Result<Self, std::io::Error> {
+        info!("SharedConnection::connect called");
+        let mut tcs_client = TCSClient::new();
+        tcs_client.connect_with_port(ip, port, timeout)?;
+        Ok(Self::from_client(tcs_client))
+    }
+
+    /// Shares a TCSClient that's already connected
+    /// # Arguments
+    /// * `tcs_client` - The connected client, the controller must still have its first robot
+    ///   selected
+    pub fn from_client(tcs_client: TCSClient) -> Self {
+        SharedConnection {
+            state: Arc::new(Mutex::new(ConnectionState {
+                tcs_client,
+                selected_robot: Self::DEFAULT_ROBOT_INDEX,
+                attached: BTreeSet::new(),
+                mode: None,
+            })),
+        }
+    }
+
+    /// Creates a robot that sends its commands over this connection
+    /// # Arguments
+    /// * `robot_index` - Index of the robot on the controller, starting at 1
+    /// * `has_rail` - A boolean that indicates whether the robot has a rail
+    pub fn robot(&self, robot_index: i32, has_rail: bool) -> PFlexRobot {
+        PFlexRobot::with_connection(self.clone(), robot_index, has_rail)
+    }
+
+    /// Robot the controller is carrying out commands for
+    pub fn selected_robot(&self) -> i32 {
+        let state = self.lock();
+        // a new connection starts out with the first robot selected
+        if state.tcs_client.has_reconnected() {
+            Self::DEFAULT_ROBOT_INDEX
+        } else {
+            state.selected_robot
+        }
+    }
+
+    /// Whether the connection is still open
+    pub fn is_connected(&self) -> bool {
+        self.lock().tcs_client.socket.is_some()
+    }
+
+    /// Whether anything else, e.g. another robot, holds a clone of the connection
+    pub fn is_shared(&self) -> bool {
+        Arc::strong_count(&self.state) > 1
+    }
+
+    /// Sends a command for a robot, selecting the robot first if needed
+    /// # Arguments
+    /// * `robot_index` - Robot the command is for
+    /// * `command` - Selected command to run from the TCSCommand enum
+    /// * `command_args` - Optional command arguments
+    /// * `wait_for_response` - A boolean option should any commands not require waiting for a response
+    /// * `read_timeout` - Optional argument to set the read timeout on the socket
+    pub fn send_command(
+        &self,
+        robot_index: i32,
+        command: TCSCommand,
+        command_args: Option<Vec<&str>>,
+        wait_for_response: bool,
+        read_timeout: Option<f64>,
+    ) -> Result<Vec<String>, RobotError> {
+        let mut state = self.lock();
+        let name = command.to_string();
+        let argument = command_args
+            .as_ref()
+            .and_then(|args| args.first())
+            .map(|arg| arg.to_string());
+        // a selectRobot sent by a robot switches the connection to the robot it names
+        if command != TCSCommand::Select {
+            state.select(robot_index)?;
+        }
+        let res =
+            state
+                .tcs_client
+                .send_command(command, command_args, wait_for_response, read_timeout);
+        state.settle(robot_index, &name, argument.as_deref(), res)
+    }
+
+    /// Sends a request written out in full for a robot, selecting the robot first if needed
+    /// # Arguments
+    /// * `robot_index` - Robot the request is for
+    /// * `request` - The command and its arguments, e.g. `pd 2800 1 0 1`
+    /// * `read_timeout` - Optional argument to set the read timeout on the socket
+    pub fn send_raw(
+        &self,
+        robot_index: i32,
+        request: &str,
+        read_timeout: Option<f64>,
+    ) -> Result<Vec<String>, RobotError> {
+        let mut state = self.lock();
+        let mut parts = request.split_whitespace();
+        let name = parts.next().unwrap_or_default();
+        let argument = parts.next();
+        // a raw selectRobot switches the connection to the robot it names
+        if !name.eq_ignore_ascii_case(&TCSCommand::Select.to_string()) {
+            state.select(robot_index)?;
+        }
+        let res = state.tcs_client.send_raw(request, read_timeout);
+        state.settle(robot_index, name, argument, res)
+    }
+
+    /// Records every request and reply sent over the connection, by any robot, from now on
+    /// # Arguments
+    /// * `recorder` - Where the exchanges are written
+    #[cfg(feature = "record")]
+    pub fn start_recording(&self, recorder: SessionRecorder) {
+        self.lock().tcs_client.start_recording(recorder);
+    }
+
+    /// Stops recording, handing back the recorder
+    #[cfg(feature = "record")]
+    pub fn stop_recording(&self) -> Option<SessionRecorder> {
+        self.lock().tcs_client.stop_recording()
+    }
+
+    /// Tells the controller the client is leaving and closes the connection for every robot
+    /// sharing it
+    pub fn disconnect(&self) -> Result<(), std::io::Error> {
+        info!("SharedConnection::disconnect called");
+        self.lock().close()
+    }
+
+    fn lock(&self) -> MutexGuard<'_, ConnectionState> {
+        self.state.lock().unwrap()
+    }
+}
@@ -1,3 +1,8 @@
+use crate::error_codes::RobotError;
+use crate::units::Mass;
+use std::fmt;
+use strum_macros::EnumString;
+
 /// Parameter IDs that are required when getting or setting parameters on the robot
 #[derive(Debug, Clone, PartialEq, Copy)]
 pub enum ParamIDs {
@@ -10,9 +15,137 @@ impl ParamIDs {
     pub fn value(&self) -> i32 {
         *self as i32
     }
+}
+
+impl fmt::Display for ParamIDs {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.value())
+    }
+}
+
+/// Joint numbers on the PreciseFlex arm, as used by joint based TCS commands
+#[derive(Debug, Clone, PartialEq, Eq, Copy, Hash, PartialOrd, Ord, EnumString)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[strum(serialize_all = "lowercase", ascii_case_insensitive)]
+#[cfg_attr(feature = "serde", serde(rename_all = "lowercase"))]
+pub enum Joint {
+    Z = 1,
+    Shoulder = 2,
+    Elbow = 3,
+    Wrist = 4,
+    Gripper = 5,
+    Rail = 6,
+}
+
+impl Joint {
+    /// Joints that move in the horizontal plane and won't drop under gravity when released
+    pub const HORIZONTAL: [Joint; 3] = [Joint::Shoulder, Joint::Elbow, Joint::Wrist];
+
+    /// Get the joint number as a 32-bit integer type
+    pub fn value(&self) -> i32 {
+        *self as i32
+    }
+
+    /// Whether the joint moves in millimetres rather than degrees
+    pub fn is_linear(&self) -> bool {
+        matches!(self, Joint::Z | Joint::Gripper | Joint::Rail)
+    }
+
+    /// Whether the joint carries the weight of the arm when it isn't being driven
+    pub fn is_gravity_loaded(&self) -> bool {
+        *self == Joint::Z
+    }
+}
+
+impl fmt::Display for Joint {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.value())
+    }
+}
+
+/// Cartesian axes the tool can be jogged along or about
+#[derive(Debug, Clone, PartialEq, Eq, Copy)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(feature = "serde", serde(rename_all = "lowercase"))]
+pub enum CartesianAxis {
+    X,
+    Y,
+    Z,
+    Yaw,
+}
+
+impl CartesianAxis {
+    /// Whether jogging the axis rotates the tool rather than translating it
+    pub fn is_rotation(&self) -> bool {
+        *self == CartesianAxis::Yaw
+    }
+}
+
+/// Frame a Cartesian jog is expressed in
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(feature = "serde", serde(rename_all = "lowercase"))]
+pub enum JogFrame {
+    /// Robot world frame
+    World,
+    /// Frame attached to the tool centre point
+    Tool,
+    /// Named user frame
+    User(String),
+}
+
+/// PreciseFlex models supported by the crate
+#[derive(Debug, Clone, PartialEq, Eq, Copy, Default, EnumString)]
+#[strum(ascii_case_insensitive)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum RobotModel {
+    #[default]
+    PF400,
+    PF3400,
+}
+
+impl RobotModel {
+    /// Rated payload of the arm, including the gripper fingers but not the gripper itself
+    pub fn rated_payload(&self) -> Mass {
+        match self {
+            RobotModel::PF400 => Mass::from_kg(0.5),
+            RobotModel::PF3400 => Mass::from_kg(2.0),
+        }
+    }
+
+    /// Converts a payload into the percentage of rated payload used by the `payload` command
+    /// # Arguments
+    /// * `payload` - Mass held by the gripper
+    /// # Returns
+    /// * A RobotError if the payload is negative or above the rated payload of the model
+    pub fn payload_percent(&self, payload: Mass) -> Result<f64, RobotError> {
+        let rated = self.rated_payload();
+        if payload.kg() < 0.0 || !payload.kg().is_finite() {
+            return Err(format!("Invalid payload: {}", payload));
+        }
+        if payload > rated {
+            return Err(format!(
+                "Payload of {} exceeds the {} rated payload of {}",
+                payload, self, rated
+            ));
+        }
+        Ok(payload.kg() / rated.kg() * 100.0)
+    }
+
+    /// Converts the percentage of rated payload reported by the `payload` command into a mass
+    /// # Arguments
+    /// * `percent` - Percentage of the rated payload
+    pub fn payload_from_percent(&self, percent: f64) -> Mass {
+        Mass::from_kg(self.rated_payload().kg() * percent / 100.0)
+    }
+}
 
-    /// Get the value of the parameter ID as a String type
-    pub fn to_string(&self) -> String {
-        self.value().to_string()
+impl fmt::Display for RobotModel {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let s = match self {
+            RobotModel::PF400 => "PF400",
+            RobotModel::PF3400 => "PF3400",
+        };
+        write!(f, "{}", s)
     }
 }
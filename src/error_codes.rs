@@ -1,7 +1,10 @@
 // yeah i'll add all the precise codes when I can be bothered
 // watch this, imma do something heckin lazy instead
 
-use crate::error_codes::ResponseCodes::{RobotPowerNotEnabled, Success, Warning};
+use crate::error_codes::ResponseCodes::{
+    HardEStop, JointOutOfRange, RobotNotAttached, RobotNotHomed, RobotPowerNotEnabled, Success,
+    Warning,
+};
 use std::fmt;
 
 pub type RobotError = String;
@@ -12,16 +15,39 @@ pub type RobotOK = String;
 pub enum ResponseCodes {
     Success = 0,
     Warning = 1,
+    RobotNotAttached = -1009,
+    JointOutOfRange = -1012,
+    RobotNotHomed = -1021,
     RobotPowerNotEnabled = -1046,
+    HardEStop = -1603,
 }
 
 impl ResponseCodes {
     pub fn check_code(code: String) -> Result<RobotOK, RobotError> {
-        match code.parse().unwrap() {
-            0 => Ok(Success.to_string()),
-            1 => Ok(Warning.to_string()),
-            -1046 => Err(RobotPowerNotEnabled.to_string()),
-            _ => Err(code),
+        let value = code
+            .parse::<i32>()
+            .map_err(|_| format!("Invalid response code: {}", code))?;
+        match Self::from_value(value) {
+            Some(Success) => Ok(Success.to_string()),
+            Some(Warning) => Ok(Warning.to_string()),
+            Some(known) => Err(known.to_string()),
+            None => Err(code),
+        }
+    }
+
+    /// Finds the response code with a value, None if the code isn't a known one
+    /// # Arguments
+    /// * `value` - The code at the start of a reply
+    pub fn from_value(value: i32) -> Option<Self> {
+        match value {
+            0 => Some(Success),
+            1 => Some(Warning),
+            -1009 => Some(RobotNotAttached),
+            -1012 => Some(JointOutOfRange),
+            -1021 => Some(RobotNotHomed),
+            -1046 => Some(RobotPowerNotEnabled),
+            -1603 => Some(HardEStop),
+            _ => None,
         }
     }
 
@@ -29,15 +55,23 @@ impl ResponseCodes {
     pub fn value(&self) -> i32 {
         *self as i32
     }
+
+    /// Get the description of the response code
+    pub fn description(&self) -> &'static str {
+        match self {
+            Success => "Operation completed successfully without an error.",
+            Warning => "Operation completed with a warning.",
+            RobotNotAttached => "No robot attached.",
+            JointOutOfRange => "Joint out-of-range.",
+            RobotNotHomed => "Robot not homed.",
+            RobotPowerNotEnabled => "Robot power not enabled.",
+            HardEStop => "Hard E-STOP.",
+        }
+    }
 }
 
 impl fmt::Display for ResponseCodes {
     fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
-        let code_description = match self {
-            Success => "Operation completed successfully without an error.",
-            Warning => "Operation completed with a warning.",
-            _ => "An error occurred during the operation.",
-        };
-        write!(f, "PFError {}: {}", self.value(), code_description)
+        write!(f, "PFError {}: {}", self.value(), self.description())
     }
 }
@@ -0,0 +1,183 @@
+use crate::enums::RobotModel;
+use crate::error_codes::RobotError;
+use crate::pflex::PFlexRobot;
+use crate::structs::{EndEffectorPosition, JointPosition};
+use crate::units::{Degrees, Millimetres};
+
+/// Which way the elbow bends, viewed from above
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(feature = "serde", serde(rename_all = "lowercase"))]
+pub enum Handedness {
+    /// Elbow bends anticlockwise, positive elbow angles
+    Lefty,
+    /// Elbow bends clockwise, negative elbow angles
+    #[default]
+    Righty,
+}
+
+impl Handedness {
+    /// Handedness of an existing joint position
+    pub fn of(joints: &JointPosition) -> Handedness {
+        if joints.elbow_deg >= Degrees::ZERO {
+            Handedness::Lefty
+        } else {
+            Handedness::Righty
+        }
+    }
+}
+
+/// Geometry of the arm
+///
+/// The Z column carries a shoulder, elbow and wrist that all rotate about vertical axes, and an
+/// optional rail moves the whole robot along a straight line in the world XY plane.
+/// # Fields
+/// * `shoulder_to_elbow_mm` - Length of the inner link
+/// * `elbow_to_wrist_mm` - Length of the outer link
+/// * `wrist_to_tool_mm` - Distance from the wrist axis to the centre of the gripper fingers
+/// * `z_offset_mm` - World Z of the tool when the Z joint reads zero
+/// * `rail_direction_deg` - Direction the rail moves the robot in, measured from the world X axis
+#[derive(Debug, Clone, Copy, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct KinematicParams {
+    pub shoulder_to_elbow_mm: f64,
+    pub elbow_to_wrist_mm: f64,
+    pub wrist_to_tool_mm: f64,
+    pub z_offset_mm: f64,
+    pub rail_direction_deg: f64,
+}
+
+impl KinematicParams {
+    /// Nominal geometry of a model, measure your own arm where accuracy matters
+    /// # Arguments
+    /// * `model` - The PreciseFlex model
+    pub fn for_model(model: RobotModel) -> Self {
+        match model {
+            RobotModel::PF400 => KinematicParams {
+                shoulder_to_elbow_mm: 302.0,
+                elbow_to_wrist_mm: 289.0,
+                wrist_to_tool_mm: 162.0,
+                z_offset_mm: 0.0,
+                rail_direction_deg: 0.0,
+            },
+            RobotModel::PF3400 => KinematicParams {
+                shoulder_to_elbow_mm: 350.0,
+                elbow_to_wrist_mm: 350.0,
+                wrist_to_tool_mm: 162.0,
+                z_offset_mm: 0.0,
+                rail_direction_deg: 0.0,
+            },
+        }
+    }
+}
+
+/// Offline forward and inverse kinematics for the PreciseFlex arm
+#[derive(Debug, Clone, Copy, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct Kinematics {
+    pub params: KinematicParams,
+    pub handedness: Handedness,
+}
+
+impl Kinematics {
+    /// Creates kinematics with the nominal geometry of a model
+    /// # Arguments
+    /// * `model` - The PreciseFlex model
+    /// * `handedness` - Elbow configuration used by inverse kinematics
+    pub fn new(model: RobotModel, handedness: Handedness) -> Self {
+        Kinematics {
+            params: KinematicParams::for_model(model),
+            handedness,
+        }
+    }
+
+    /// Calculates the end effector position of a joint position
+    /// # Arguments
+    /// * `joints` - The joint position
+    pub fn forward(&self, joints: &JointPosition) -> EndEffectorPosition {
+        let p = &self.params;
+        let [base_x, base_y] = self.rail_offset(joints.rail_mm);
+        let inner = joints.shoulder_deg.radians();
+        let outer = (joints.shoulder_deg + joints.elbow_deg).radians();
+        let yaw_deg = normalise_deg(joints.shoulder_deg + joints.elbow_deg + joints.wrist_deg);
+        let yaw = yaw_deg.radians();
+        let x_mm = base_x
+            + p.shoulder_to_elbow_mm * inner.cos()
+            + p.elbow_to_wrist_mm * outer.cos()
+            + p.wrist_to_tool_mm * yaw.cos();
+        let y_mm = base_y
+            + p.shoulder_to_elbow_mm * inner.sin()
+            + p.elbow_to_wrist_mm * outer.sin()
+            + p.wrist_to_tool_mm * yaw.sin();
+        EndEffectorPosition {
+            x_mm: x_mm.into(),
+            y_mm: y_mm.into(),
+            z_mm: joints.z_mm + p.z_offset_mm.into(),
+            yaw_deg,
+            pitch_deg: PFlexRobot::DEFAULT_EE_PITCH,
+            roll_deg: PFlexRobot::DEFAULT_EE_ROLL,
+        }
+    }
+
+    /// Calculates the joint position that reaches an end effector position
+    /// # Arguments
+    /// * `pose` - The end effector position, pitch and roll are ignored
+    /// * `rail_mm` - Rail position the pose is reached from, if the robot has a rail
+    /// * `gripper_mm` - Gripper opening to include in the joint position
+    /// # Returns
+    /// * A RobotError if the pose is out of reach
+    pub fn inverse(
+        &self,
+        pose: &EndEffectorPosition,
+        rail_mm: Option<Millimetres>,
+        gripper_mm: Millimetres,
+    ) -> Result<JointPosition, RobotError> {
+        let p = &self.params;
+        let [base_x, base_y] = self.rail_offset(rail_mm);
+        let yaw = pose.yaw_deg.radians();
+        let wrist_x = pose.x_mm.mm() - base_x - p.wrist_to_tool_mm * yaw.cos();
+        let wrist_y = pose.y_mm.mm() - base_y - p.wrist_to_tool_mm * yaw.sin();
+        let (l1, l2) = (p.shoulder_to_elbow_mm, p.elbow_to_wrist_mm);
+        let cos_elbow =
+            (wrist_x * wrist_x + wrist_y * wrist_y - l1 * l1 - l2 * l2) / (2.0 * l1 * l2);
+        if !(-1.0..=1.0).contains(&cos_elbow) {
+            return Err(format!(
+                "Pose ({}, {}, {}) is out of reach",
+                pose.x_mm.mm(),
+                pose.y_mm.mm(),
+                pose.z_mm.mm()
+            ));
+        }
+        let elbow = match self.handedness {
+            Handedness::Lefty => cos_elbow.acos(),
+            Handedness::Righty => -cos_elbow.acos(),
+        };
+        let shoulder = wrist_y.atan2(wrist_x) - (l2 * elbow.sin()).atan2(l1 + l2 * elbow.cos());
+        let shoulder_deg = normalise_deg(Degrees::from_radians(shoulder));
+        let elbow_deg = Degrees::from_radians(elbow);
+        Ok(JointPosition {
+            z_mm: pose.z_mm - p.z_offset_mm.into(),
+            shoulder_deg,
+            elbow_deg,
+            wrist_deg: normalise_deg(pose.yaw_deg - shoulder_deg - elbow_deg),
+            gripper_mm,
+            rail_mm,
+        })
+    }
+
+    fn rail_offset(&self, rail_mm: Option<Millimetres>) -> [f64; 2] {
+        let rail_mm = rail_mm.map_or(0.0, |rail_mm| rail_mm.mm());
+        let direction = self.params.rail_direction_deg.to_radians();
+        [rail_mm * direction.cos(), rail_mm * direction.sin()]
+    }
+}
+
+/// Wraps an angle into the range -180 to 180 degrees
+fn normalise_deg(angle: Degrees) -> Degrees {
+    let wrapped = (angle.deg() + 180.0).rem_euclid(360.0) - 180.0;
+    if wrapped == -180.0 {
+        Degrees::from_deg(180.0)
+    } else {
+        Degrees::from_deg(wrapped)
+    }
+}
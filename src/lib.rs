@@ -1,12 +1,47 @@
+/// Flag parsing shared by the command line tools
+#[cfg(any(feature = "cli", feature = "pendant", feature = "repl"))]
+mod args;
+/// Everyday robot operations from the command line
+#[cfg(feature = "cli")]
+pub mod cli;
 /// API commands
 pub mod commands;
+/// Connections shared by the robots a controller drives
+pub mod connection;
 /// Internal enums
 pub mod enums;
 /// Error response codes
 pub mod error_codes;
+/// Offline forward and inverse kinematics
+pub mod kinematics;
+/// Simulated robot for testing without hardware
+#[cfg(feature = "mock")]
+pub mod mock;
+/// Terminal teach pendant for jogging and teaching locations
+#[cfg(feature = "pendant")]
+pub mod pendant;
 /// Top level module for the PreciseFlex
 pub mod pflex;
+/// Declarative recipe files for robot tasks
+#[cfg(feature = "recipe")]
+pub mod recipe;
+/// Interactive TCS shell for commissioning
+#[cfg(feature = "repl")]
+pub mod repl;
+/// Motion sequences executed as one unit
+pub mod sequence;
+/// Recording TCS conversations to replay them later
+#[cfg(feature = "record")]
+pub mod session;
 /// Internal structs
 pub mod structs;
 /// TCS Client module used to communicate with the robot
 pub mod tcs_client;
+/// Free-mode teaching sessions
+pub mod teach;
+/// Pose transforms, tool frames and user frames
+pub mod transforms;
+/// Physical units used by the API
+pub mod units;
+/// Workspace limits and safety zones checked before motion
+pub mod workspace;
@@ -0,0 +1,1502 @@
+// Simulated PreciseFlex robot speaking the TCS protocol
+//
+// The simulator:
+// 1. Listens on a local port, an ephemeral one when started with `MockRobot::start`
+// 2. Maintains a shared robot state using an Arc<Mutex<>> to allow concurrent client connections
+// 3. Implements responses for all the commands defined in the TCSCommand enum
+// 4. Simulates basic behavior, like power requirements for movement commands
+// 5. Returns formatted responses in the "code data\r\n" format expected by the TCSClient
+//
+// Notable features:
+// - Commands like `hp` (power) will modify the internal state
+// - The robot starts unpowered, detached and not homed, like a controller that was just booted
+// - Movement commands check the robot is out of E-STOP, powered, attached, homed and not in free
+//   mode, returning the controller's error code if not
+// - Locations and profiles are stored in tables by index, can be read back with `loc` and
+//   `profile`, and are checked when a move uses them. The tables can be saved to and loaded from
+//   JSON through `MockRobot::save_tables` and `MockRobot::load_tables`
+// - The gripper and rail are held to their travel limits, and grasping succeeds only when a plate
+//   of about the requested width has been placed between the fingers with `RobotState::plate_mm`
+// - Digital I/O is kept in a signal bank, outputs are switched with `sig` and inputs are set from
+//   tests with `RobotState::set_input`
+// - Moves take as long as they would on a robot, following the motion profile and system
+//   speed, and `waitForEOM` returns once the last queued move has finished
+// - The state can be inspected and changed from tests through `MockRobot::state`
+// - A controller driving several robots can be simulated with `MockRobot::start_with_robots`,
+//   each connection carries out commands for the robot it last selected with `selectRobot`
+// - A robot stays attached to the connection that attached it, and is detached when that
+//   connection closes
+// - Faults like slow, dropped or garbled replies can be injected into chosen commands through
+//   `MockRobot::inject_fault` or a scenario file, see the `faults` module
+// - A session recorded from a real robot can be served back in order with
+//   `MockRobot::start_replay`, flagging any request that differs from the recording, see the
+//   `replay` module
+
+use crate::connection::SharedConnection;
+use crate::enums::RobotModel;
+use crate::error_codes::{ResponseCodes, RobotError};
+use crate::kinematics::{Handedness, Kinematics};
+use crate::pflex::PFlexRobot;
+use crate::session::Session;
+use crate::structs::{EndEffectorPosition, JointPosition};
+use crate::units::Millimetres;
+use log::{debug, info, warn};
+use motion::{Motion, MotionQueue, QueuedMove};
+use std::collections::{BTreeMap, BTreeSet, HashMap};
+use std::fmt::Debug;
+use std::fs;
+use std::io::{BufRead, BufReader, ErrorKind, Write};
+use std::net::{Shutdown, SocketAddr, TcpListener, TcpStream};
+use std::path::Path;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{Arc, Mutex, MutexGuard};
+use std::thread::{self, JoinHandle};
+use std::time::{Duration, Instant};
+
+mod config;
+mod faults;
+mod logger;
+mod motion;
+mod replay;
+
+pub use config::MockConfig;
+pub use faults::{Fault, FaultRule};
+pub use logger::LogfmtLogger;
+pub use motion::{Joints, ProfileSettings};
+pub use replay::{Divergence, Replay};
+
+/// Snapshot of the location and profile tables stored on the simulated controller
+#[derive(Debug, Clone, Default, PartialEq, serde::Serialize, serde::Deserialize)]
+pub struct MockTables {
+    #[serde(default)]
+    pub locations: BTreeMap<i32, [f64; 6]>,
+    #[serde(default)]
+    pub profiles: BTreeMap<i32, ProfileSettings>,
+}
+
+impl MockTables {
+    pub fn to_json(&self) -> Result<String, RobotError> {
+        serde_json::to_string_pretty(self).map_err(|e| e.to_string())
+    }
+
+    pub fn from_json(json: &str) -> Result<Self, RobotError> {
+        serde_json::from_str(json).map_err(|e| format!("Invalid tables: {}", e))
+    }
+}
+
+/// State of the simulated robot
+#[derive(Debug, Clone)]
+pub struct RobotState {
+    pub estop: bool,
+    pub power: bool,
+    pub attached: bool,
+    /// Connection that attached the robot, which detaches it when it closes
+    pub attached_by: Option<SocketAddr>,
+    pub homed: bool,
+    pub position: [f64; 6], // x, y, z, yaw, pitch, roll
+    pub joint_positions: [f64; 6],
+    pub free_mode: bool,
+    /// Joints released in free mode, by joint number
+    pub free_joints: BTreeSet<i32>,
+    pub zero_torque: bool,
+    pub payload_percent: f64,
+    pub system_speed: i32,
+    /// How long moves take compared to a real robot
+    pub time_scale: f64,
+    pub rail_position: Option<f64>,
+    pub kinematics: Kinematics,
+    pub profiles: HashMap<i32, ProfileSettings>,
+    pub locations: HashMap<i32, [f64; 6]>,
+    /// Travel limits of the gripper fingers in mm
+    pub gripper_limits: [f64; 2],
+    /// Travel limits of the rail in mm
+    pub rail_limits: [f64; 2],
+    /// Width of the plate between the fingers, None when there's nothing to grasp
+    pub plate_mm: Option<f64>,
+    pub holding_plate: bool,
+    pub signals: HashMap<i32, bool>,
+    motion: MotionQueue,
+    faults: Vec<FaultRule>,
+    // when an injected E-STOP is pressed
+    estop_at: Option<Instant>,
+}
+
+impl RobotState {
+    const MOTION_STATE_IDLE: i32 = 1;
+    const MOTION_STATE_MOVING: i32 = 2;
+    /// Highest motion profile index the simulated controller stores
+    pub const MAX_PROFILE_ID: i32 = 20;
+    /// Highest location index the simulated controller stores
+    pub const MAX_LOCATION_ID: i32 = 1000;
+    /// Signals from this number up are digital inputs, which can't be switched with `sig`
+    pub const FIRST_INPUT_SIGNAL: i32 = 10001;
+    /// How far the requested plate width can be from the plate for a grasp to succeed
+    pub const GRASP_TOLERANCE_MM: f64 = 5.0;
+    /// How fast joints released in free mode drift, in mm or degrees per second
+    pub const FREE_MODE_DRIFT_PER_SECOND: f64 = 0.5;
+    const GRIPPER: usize = 4;
+    const RAIL: usize = 5;
+
+    pub fn new() -> Self {
+        let mut state = RobotState {
+            estop: false,
+            power: false,
+            attached: false,
+            attached_by: None,
+            homed: false,
+            position: [300.0, 0.0, 150.0, 0.0, 90.0, -180.0],
+            joint_positions: [0.0, 0.0, 0.0, 0.0, 0.0, 0.0],
+            free_mode: false,
+            free_joints: BTreeSet::new(),
+            zero_torque: false,
+            payload_percent: 0.0,
+            system_speed: 50,
+            time_scale: 1.0,
+            rail_position: Some(0.0),
+            kinematics: Kinematics::new(RobotModel::PF400, Handedness::Righty),
+            profiles: HashMap::new(),
+            locations: HashMap::new(),
+            gripper_limits: [0.0, 140.0],
+            rail_limits: [0.0, 2000.0],
+            plate_mm: None,
+            holding_plate: false,
+            signals: HashMap::new(),
+            motion: MotionQueue::default(),
+            faults: Vec::new(),
+            estop_at: None,
+        };
+        // start with joints that agree with the starting position
+        let position = state.position;
+        state
+            .set_position(position)
+            .expect("Starting position is out of reach");
+        state
+    }
+
+    /// Sets up the robot as described by the simulator settings
+    /// # Arguments
+    /// * `config` - Settings of the simulated cell
+    pub fn configure(&mut self, config: &MockConfig) -> Result<(), String> {
+        self.kinematics = Kinematics::new(config.model, Handedness::Righty);
+        self.rail_position = config.rail.then_some(0.0);
+        self.power = config.power;
+        self.system_speed = config.system_speed;
+        self.time_scale = config.time_scale;
+        match config.joints {
+            Some(joints) => {
+                let joints = self.joint_position(joints);
+                self.set_joints(&joints);
+                Ok(())
+            }
+            // work the joints out again for the model
+            None => self.set_position(self.position),
+        }
+    }
+
+    /// Current joint position of the simulated robot
+    pub fn joints(&self) -> JointPosition {
+        JointPosition {
+            z_mm: self.joint_positions[0].into(),
+            shoulder_deg: self.joint_positions[1].into(),
+            elbow_deg: self.joint_positions[2].into(),
+            wrist_deg: self.joint_positions[3].into(),
+            gripper_mm: self.joint_positions[4].into(),
+            rail_mm: self.rail_position.map(Millimetres::from_mm),
+        }
+    }
+
+    /// Moves to a joint position, updating the Cartesian position to match
+    pub fn set_joints(&mut self, joints: &JointPosition) {
+        let pose = self.kinematics.forward(joints);
+        self.joint_positions = [
+            joints.z_mm.mm(),
+            joints.shoulder_deg.deg(),
+            joints.elbow_deg.deg(),
+            joints.wrist_deg.deg(),
+            joints.gripper_mm.mm(),
+            joints
+                .rail_mm
+                .map(|rail_mm| rail_mm.mm())
+                .or(self.rail_position)
+                .unwrap_or(0.0),
+        ];
+        if let (Some(_), Some(rail_mm)) = (self.rail_position, joints.rail_mm) {
+            self.rail_position = Some(rail_mm.mm());
+        }
+        self.position = [
+            pose.x_mm.mm(),
+            pose.y_mm.mm(),
+            pose.z_mm.mm(),
+            pose.yaw_deg.deg(),
+            self.position[4],
+            self.position[5],
+        ];
+    }
+
+    /// Moves to a Cartesian position, keeping the current handedness
+    pub fn set_position(&mut self, position: [f64; 6]) -> Result<(), String> {
+        let joints = self.joints_for_position(position, self.joint_positions)?;
+        self.set_joint_values(joints);
+        self.position = position;
+        Ok(())
+    }
+
+    /// Moves the joints released in free mode on by how far they drift in a while, as if the arm
+    /// were being guided by hand, with the Cartesian position following them
+    /// # Arguments
+    /// * `elapsed` - Time since the joints last drifted
+    pub fn drift(&mut self, elapsed: Duration) {
+        if !self.free_mode {
+            return;
+        }
+        let mut joints = self.joint_positions;
+        for joint in &self.free_joints {
+            // joint numbers start at 1
+            joints[*joint as usize - 1] += Self::FREE_MODE_DRIFT_PER_SECOND * elapsed.as_secs_f64();
+        }
+        self.set_joint_values(joints);
+    }
+
+    /// Presses the E-STOP, stopping any move and disabling power
+    pub fn press_estop(&mut self) {
+        self.halt();
+        self.estop = true;
+        self.power = false;
+    }
+
+    /// Releases the E-STOP, power has to be enabled again before the robot can move
+    pub fn release_estop(&mut self) {
+        self.estop = false;
+    }
+
+    /// Injects a fault into the replies to matching commands
+    pub fn inject_fault(&mut self, rule: FaultRule) {
+        self.faults.push(rule);
+    }
+
+    /// Removes every injected fault, including an E-STOP waiting to be pressed
+    pub fn clear_faults(&mut self) {
+        self.faults.clear();
+        self.estop_at = None;
+    }
+
+    /// Faults waiting to be injected, in the order they were added
+    pub fn faults(&self) -> &[FaultRule] {
+        &self.faults
+    }
+
+    /// Takes the first fault that applies to a command, counting it against its rule
+    fn take_fault(&mut self, command: &str) -> Option<Fault> {
+        let rule = self.faults.iter_mut().find(|rule| rule.matches(command))?;
+        if let Some(times) = rule.times.as_mut() {
+            *times -= 1;
+        }
+        let fault = rule.fault.clone();
+        self.faults.retain(|rule| rule.times != Some(0));
+        Some(fault)
+    }
+
+    /// Checks the robot is able to move, the way the controller does before accepting a move
+    /// # Returns
+    /// * The error reply the controller sends if the robot can't move
+    pub fn check_can_move(&self) -> Result<(), String> {
+        if self.estop {
+            Err(error_reply(ResponseCodes::HardEStop))
+        } else if !self.power {
+            Err(error_reply(ResponseCodes::RobotPowerNotEnabled))
+        } else if !self.attached {
+            Err(error_reply(ResponseCodes::RobotNotAttached))
+        } else if !self.homed {
+            Err(error_reply(ResponseCodes::RobotNotHomed))
+        } else if self.free_mode {
+            Err("-1 Robot is in free mode\r\n".to_string())
+        } else {
+            Ok(())
+        }
+    }
+
+    /// Copies the location and profile tables
+    pub fn tables(&self) -> MockTables {
+        MockTables {
+            locations: self.locations.clone().into_iter().collect(),
+            profiles: self.profiles.clone().into_iter().collect(),
+        }
+    }
+
+    /// Replaces the location and profile tables
+    /// # Returns
+    /// * An error, leaving the tables as they were, if an index is outside the tables
+    pub fn set_tables(&mut self, tables: MockTables) -> Result<(), String> {
+        if let Some(id) = tables
+            .locations
+            .keys()
+            .find(|id| !(1..=Self::MAX_LOCATION_ID).contains(*id))
+        {
+            return Err(format!("Invalid location index {}", id));
+        }
+        if let Some(id) = tables
+            .profiles
+            .keys()
+            .find(|id| !(1..=Self::MAX_PROFILE_ID).contains(*id))
+        {
+            return Err(format!("Invalid profile index {}", id));
+        }
+        self.locations = tables.locations.into_iter().collect();
+        self.profiles = tables.profiles.into_iter().collect();
+        Ok(())
+    }
+
+    /// Checks a joint target against the gripper and rail travel limits
+    /// # Returns
+    /// * The error reply the controller sends if a joint is out of range
+    pub fn check_limits(&self, target: &Joints) -> Result<(), String> {
+        let in_range = |value: f64, limits: [f64; 2]| (limits[0]..=limits[1]).contains(&value);
+        let rail_in_range =
+            self.rail_position.is_none() || in_range(target[Self::RAIL], self.rail_limits);
+        if in_range(target[Self::GRIPPER], self.gripper_limits) && rail_in_range {
+            Ok(())
+        } else {
+            Err(error_reply(ResponseCodes::JointOutOfRange))
+        }
+    }
+
+    /// Closes the fingers to grasp a plate
+    /// # Arguments
+    /// * `plate_mm` - Width of the plate to grasp
+    /// # Returns
+    /// * Whether a plate was grasped, the fingers close fully when there isn't one
+    pub fn grasp(&mut self, plate_mm: f64) -> Result<bool, String> {
+        let grasped = self
+            .plate_mm
+            .filter(|plate| (plate - plate_mm).abs() <= Self::GRASP_TOLERANCE_MM);
+        let mut target = self.final_joints();
+        target[Self::GRIPPER] = grasped.unwrap_or(self.gripper_limits[0]);
+        self.check_limits(&target)?;
+        self.queue_move(target, None, ProfileSettings::default());
+        self.holding_plate = grasped.is_some();
+        Ok(self.holding_plate)
+    }
+
+    /// Opens the fingers, letting go of any plate
+    /// # Arguments
+    /// * `open_mm` - Width to open the fingers to
+    pub fn release(&mut self, open_mm: f64) -> Result<(), String> {
+        let mut target = self.final_joints();
+        target[Self::GRIPPER] = open_mm;
+        self.check_limits(&target)?;
+        self.queue_move(target, None, ProfileSettings::default());
+        self.holding_plate = false;
+        Ok(())
+    }
+
+    /// Reads a digital input or output, signals that were never set are off
+    pub fn signal(&self, signal: i32) -> bool {
+        self.signals.get(&signal).copied().unwrap_or_default()
+    }
+
+    /// Sets a digital input, e.g. a sensor the station code waits for
+    pub fn set_input(&mut self, signal: i32, state: bool) {
+        self.signals.insert(signal, state);
+    }
+
+    /// Looks up a motion profile, profiles that were never set use the controller defaults
+    pub fn profile(&self, profile_id: i32) -> Result<ProfileSettings, String> {
+        if !(1..=Self::MAX_PROFILE_ID).contains(&profile_id) {
+            return Err(format!("Invalid profile index {}", profile_id));
+        }
+        Ok(self.profiles.get(&profile_id).copied().unwrap_or_default())
+    }
+
+    /// Looks up a stored location
+    pub fn location(&self, location_id: i32) -> Result<[f64; 6], String> {
+        if !(1..=Self::MAX_LOCATION_ID).contains(&location_id) {
+            return Err(format!("Invalid location index {}", location_id));
+        }
+        self.locations
+            .get(&location_id)
+            .copied()
+            .ok_or_else(|| format!("Location {} has not been taught", location_id))
+    }
+
+    /// Whether a move is being carried out or waiting to be carried out
+    pub fn is_moving(&self) -> bool {
+        self.motion.is_moving()
+    }
+
+    /// Motion state reported by the `state` command
+    pub fn motion_state(&self) -> i32 {
+        if self.is_moving() {
+            Self::MOTION_STATE_MOVING
+        } else {
+            Self::MOTION_STATE_IDLE
+        }
+    }
+
+    /// Queues a move to a joint position using a motion profile
+    /// # Arguments
+    /// * `target` - Joints to move to
+    /// * `position` - Cartesian position to report at the end of the move, None to work it out
+    /// * `profile_id` - Motion profile the move is carried out with
+    pub fn queue_joints(
+        &mut self,
+        target: Joints,
+        position: Option<[f64; 6]>,
+        profile_id: i32,
+    ) -> Result<(), String> {
+        let profile = self.profile(profile_id)?;
+        self.queue_move(target, position, profile);
+        Ok(())
+    }
+
+    fn queue_move(&mut self, target: Joints, position: Option<[f64; 6]>, profile: ProfileSettings) {
+        let now = Instant::now();
+        self.update(now);
+        self.motion.queued.push_back(QueuedMove {
+            target,
+            position,
+            profile,
+        });
+        self.update(now);
+    }
+
+    /// Queues a move to a Cartesian position using a motion profile
+    /// # Arguments
+    /// * `position` - x, y, z, yaw, pitch and roll to move to
+    /// * `profile_id` - Motion profile the move is carried out with
+    pub fn queue_position(&mut self, position: [f64; 6], profile_id: i32) -> Result<(), String> {
+        let from = self.final_joints();
+        let target = self.joints_for_position(position, from)?;
+        self.queue_joints(target, Some(position), profile_id)
+    }
+
+    /// Joints the robot will be at once every queued move has finished
+    pub fn final_joints(&self) -> Joints {
+        self.motion.final_joints().unwrap_or(self.joint_positions)
+    }
+
+    /// Stops every move, leaving the robot wherever it is now
+    pub fn halt(&mut self) {
+        self.update(Instant::now());
+        self.motion.clear();
+    }
+
+    /// Moves the robot along the queued moves up to a point in time, pressing an injected E-STOP
+    /// on the way if it's due
+    pub fn update(&mut self, now: Instant) {
+        if let Some(estop_at) = self.estop_at.filter(|estop_at| *estop_at <= now) {
+            self.advance(estop_at);
+            self.estop_at = None;
+            self.motion.clear();
+            self.estop = true;
+            self.power = false;
+        }
+        self.advance(now);
+    }
+
+    fn advance(&mut self, now: Instant) {
+        loop {
+            let next_start = match &self.motion.active {
+                Some(motion) if now < motion.finishes() => {
+                    let joints = motion.joints_at(now);
+                    self.set_joint_values(joints);
+                    return;
+                }
+                Some(motion) => {
+                    let finished = motion.finishes();
+                    let (joints, position) = (motion.target(), motion.final_position());
+                    self.set_joint_values(joints);
+                    if let Some(position) = position {
+                        self.position = position;
+                    }
+                    self.motion.active = None;
+                    finished
+                }
+                None => now,
+            };
+            match self.motion.queued.pop_front() {
+                Some(planned) => {
+                    self.motion.active = Some(Motion::plan(
+                        self.joint_positions,
+                        &planned,
+                        self.system_speed as f64,
+                        self.time_scale,
+                        next_start,
+                    ));
+                }
+                None => return,
+            }
+        }
+    }
+
+    fn joint_position(&self, joints: Joints) -> JointPosition {
+        JointPosition {
+            z_mm: joints[0].into(),
+            shoulder_deg: joints[1].into(),
+            elbow_deg: joints[2].into(),
+            wrist_deg: joints[3].into(),
+            gripper_mm: joints[4].into(),
+            rail_mm: self.rail_position.map(|_| Millimetres::from_mm(joints[5])),
+        }
+    }
+
+    fn set_joint_values(&mut self, joints: Joints) {
+        self.set_joints(&self.joint_position(joints));
+    }
+
+    fn joints_for_position(&self, position: [f64; 6], from: Joints) -> Result<Joints, String> {
+        let from_joints = self.joint_position(from);
+        let kinematics = Kinematics {
+            handedness: Handedness::of(&from_joints),
+            ..self.kinematics
+        };
+        let pose = EndEffectorPosition {
+            x_mm: position[0].into(),
+            y_mm: position[1].into(),
+            z_mm: position[2].into(),
+            yaw_deg: position[3].into(),
+            pitch_deg: position[4].into(),
+            roll_deg: position[5].into(),
+        };
+        let joints = kinematics.inverse(&pose, from_joints.rail_mm, from_joints.gripper_mm)?;
+        Ok([
+            joints.z_mm.mm(),
+            joints.shoulder_deg.deg(),
+            joints.elbow_deg.deg(),
+            joints.wrist_deg.deg(),
+            joints.gripper_mm.mm(),
+            from[5],
+        ])
+    }
+}
+
+impl Default for RobotState {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+fn error_reply(code: ResponseCodes) -> String {
+    format!("{} {}\r\n", code.value(), code.description())
+}
+
+/// How often `waitForEOM` checks whether the last move has finished
+const MOTION_POLL_INTERVAL: Duration = Duration::from_millis(10);
+
+fn handle_client(
+    mut stream: TcpStream,
+    robots: Vec<Arc<Mutex<RobotState>>>,
+    replay: Option<Arc<Mutex<Replay>>>,
+) {
+    let peer = stream.peer_addr().unwrap();
+    info!(peer:% = peer; "client connected");
+
+    let mut reader = BufReader::new(stream.try_clone().unwrap());
+    let mut line = String::new();
+    // every connection starts out with the first robot selected
+    let mut selected_robot = 1;
+
+    loop {
+        line.clear();
+        match reader.read_line(&mut line) {
+            Ok(0) => {
+                info!("client disconnected");
+                break;
+            }
+            Ok(_) if replay.is_some() => {
+                let response = replay.as_ref().unwrap().lock().unwrap().answer(&line);
+                if let Some(response) = response {
+                    if let Err(e) = stream.write_all(response.as_bytes()) {
+                        warn!(error:% = e; "failed to send response");
+                        break;
+                    }
+                    debug!(reply = response.as_str(); "response sent");
+                }
+                if line.trim().eq_ignore_ascii_case("exit") {
+                    info!("client requested exit");
+                    break;
+                }
+            }
+            Ok(_) => {
+                let cmd = line.split_whitespace().next().unwrap_or_default();
+                let robot_state = robots[selected_robot - 1].clone();
+                let fault = robot_state.lock().unwrap().take_fault(cmd);
+                let response = match &fault {
+                    Some(Fault::CloseSocket) => {
+                        info!("injected fault: closing connection");
+                        let _ = stream.shutdown(Shutdown::Both);
+                        break;
+                    }
+                    Some(Fault::ErrorCode(code)) => format!("{} Injected error\r\n", code),
+                    _ if cmd.eq_ignore_ascii_case("selectRobot") => {
+                        select_robot(&line, &mut selected_robot, robots.len())
+                    }
+                    _ => process_command(&line, robot_state.clone(), peer),
+                };
+                if let Err(e) = send_response(&mut stream, &response, fault, &robot_state) {
+                    warn!(error:% = e; "failed to send response");
+                    break;
+                } else {
+                    debug!(reply = response.as_str(); "response sent");
+                }
+                if line.trim().eq_ignore_ascii_case("exit") {
+                    info!("client requested exit");
+                    break;
+                }
+            }
+            Err(e) => {
+                warn!(error:% = e; "error reading from client");
+                break;
+            }
+        }
+    }
+    // like the controller, a connection that closes detaches the robots it attached
+    for robot_state in &robots {
+        let mut state = robot_state.lock().unwrap();
+        if state.attached_by == Some(peer) {
+            info!(peer:% = peer; "detaching robot attached by the closed connection");
+            state.halt();
+            state.attached = false;
+            state.attached_by = None;
+        }
+    }
+}
+
+fn select_robot(command: &str, selected_robot: &mut usize, robot_count: usize) -> String {
+    match command.split_whitespace().nth(1) {
+        Some(index) => match index.parse::<usize>() {
+            Ok(index) if (1..=robot_count).contains(&index) => {
+                *selected_robot = index;
+                "0 \r\n".to_string()
+            }
+            _ => "-1 Invalid robot index\r\n".to_string(),
+        },
+        None => format!("0 {}\r\n", selected_robot),
+    }
+}
+
+fn send_response(
+    stream: &mut TcpStream,
+    response: &str,
+    fault: Option<Fault>,
+    robot_state: &Arc<Mutex<RobotState>>,
+) -> std::io::Result<()> {
+    match fault {
+        Some(Fault::Latency(delay)) => {
+            thread::sleep(delay);
+            stream.write_all(response.as_bytes())
+        }
+        Some(Fault::DropReply) => Ok(()),
+        Some(Fault::Fragment { bytes, delay }) => {
+            for chunk in response.as_bytes().chunks(bytes.get()) {
+                stream.write_all(chunk)?;
+                stream.flush()?;
+                thread::sleep(delay);
+            }
+            Ok(())
+        }
+        Some(Fault::Garble) => stream.write_all(&[0xff, 0xfe, 0x00, 0xfd, b'\r', b'\n']),
+        Some(Fault::EStopAfter(delay)) => {
+            robot_state.lock().unwrap().estop_at = Some(Instant::now() + delay);
+            stream.write_all(response.as_bytes())
+        }
+        _ => stream.write_all(response.as_bytes()),
+    }
+}
+
+fn process_command(command: &str, robot_state: Arc<Mutex<RobotState>>, peer: SocketAddr) -> String {
+    let parts: Vec<&str> = command.split_whitespace().collect();
+    if parts.is_empty() {
+        return "0 Invalid command\r\n".to_string();
+    }
+
+    let cmd = parts[0];
+    debug!(parts:? = parts; "processing command");
+    robot_state.lock().unwrap().update(Instant::now());
+
+    // the controller doesn't care about the case of a command
+    match cmd.to_lowercase().as_str() {
+        "nop" => "0 \r\n".to_string(),
+
+        "mode" => {
+            if parts.len() > 1 {
+                // Set verbose mode (not actually implemented in mock)
+                "0 \r\n".to_string()
+            } else {
+                "0 0\r\n".to_string() // Return current mode
+            }
+        }
+
+        "hp" => {
+            let mut state = robot_state.lock().unwrap();
+            if parts.len() > 1 {
+                let power = parts[1] == "1";
+                if power && state.estop {
+                    return error_reply(ResponseCodes::HardEStop);
+                }
+                if !power {
+                    state.halt();
+                }
+                state.power = power;
+                "0 \r\n".to_string()
+            } else {
+                format!("0 {}\r\n", if state.power { "1" } else { "0" })
+            }
+        }
+
+        "attach" => {
+            let mut state = robot_state.lock().unwrap();
+            if parts.len() > 1 {
+                let attach = parts[1] != "0";
+                if attach && !state.power {
+                    return error_reply(ResponseCodes::RobotPowerNotEnabled);
+                }
+                if !attach {
+                    state.halt();
+                }
+                state.attached = attach;
+                state.attached_by = attach.then_some(peer);
+                "0 \r\n".to_string()
+            } else {
+                format!("0 {}\r\n", if state.attached { "1" } else { "0" })
+            }
+        }
+
+        "home" => {
+            let mut state = robot_state.lock().unwrap();
+            if state.estop {
+                error_reply(ResponseCodes::HardEStop)
+            } else if !state.power {
+                error_reply(ResponseCodes::RobotPowerNotEnabled)
+            } else if !state.attached {
+                error_reply(ResponseCodes::RobotNotAttached)
+            } else {
+                state.homed = true;
+                "0 \r\n".to_string()
+            }
+        }
+
+        "halt" => {
+            robot_state.lock().unwrap().halt();
+            "0 \r\n".to_string()
+        }
+
+        "loc" => {
+            let state = &mut robot_state.lock().unwrap();
+            let location = match parts.get(1) {
+                Some(id) => id
+                    .parse::<i32>()
+                    .map_err(|e| e.to_string())
+                    .and_then(|id| state.location(id)),
+                None => Ok(state.position),
+            };
+            match location {
+                Ok(location) => format!(
+                    "0 0 0 {} {} {} {} {} {}\r\n",
+                    location[0], location[1], location[2], location[3], location[4], location[5]
+                ),
+                Err(e) => format!("-1 {}\r\n", e),
+            }
+        }
+
+        "locxyz" => {
+            if parts.len() < 8 {
+                "-1 Insufficient parameters\r\n".to_string()
+            } else {
+                let id = parts[1]
+                    .parse::<i32>()
+                    .ok()
+                    .filter(|id| (1..=RobotState::MAX_LOCATION_ID).contains(id));
+                match (id, parse_values::<6>(&parts[2..8])) {
+                    (Some(id), Ok(location)) => {
+                        robot_state.lock().unwrap().locations.insert(id, location);
+                        "0 \r\n".to_string()
+                    }
+                    _ => "-1 Invalid location\r\n".to_string(),
+                }
+            }
+        }
+
+        "profile" if parts.len() == 2 => {
+            let profile = parts[1]
+                .parse::<i32>()
+                .map_err(|e| e.to_string())
+                .and_then(|id| robot_state.lock().unwrap().profile(id));
+            match profile {
+                Ok(profile) => format!("0 {}\r\n", profile.to_args()),
+                Err(e) => format!("-1 {}\r\n", e),
+            }
+        }
+
+        "profile" => {
+            if parts.len() < 10 {
+                "-1 Insufficient parameters\r\n".to_string()
+            } else {
+                let id = parts[1]
+                    .parse::<i32>()
+                    .ok()
+                    .filter(|id| (1..=RobotState::MAX_PROFILE_ID).contains(id));
+                match (id, ProfileSettings::from_args(&parts[2..])) {
+                    (Some(id), Ok(profile)) => {
+                        robot_state.lock().unwrap().profiles.insert(id, profile);
+                        "0 \r\n".to_string()
+                    }
+                    _ => "-1 Invalid profile\r\n".to_string(),
+                }
+            }
+        }
+
+        "move" => {
+            let state = &mut robot_state.lock().unwrap();
+            if let Err(reply) = state.check_can_move() {
+                reply
+            } else if parts.len() < 3 {
+                "-1 Insufficient parameters\r\n".to_string()
+            } else {
+                let location_id = parts[1].parse::<i32>().unwrap_or_default();
+                let profile_id = parts[2].parse::<i32>().unwrap_or_default();
+                let queued = state
+                    .location(location_id)
+                    .and_then(|location| state.queue_position(location, profile_id));
+                match queued {
+                    Ok(_) => "0 \r\n".to_string(),
+                    Err(e) => format!("-1 {}\r\n", e),
+                }
+            }
+        }
+
+        "movec" => {
+            let state = &mut robot_state.lock().unwrap();
+            if let Err(reply) = state.check_can_move() {
+                reply
+            } else if parts.len() < 8 {
+                "-1 Insufficient parameters\r\n".to_string()
+            } else {
+                let position = [
+                    parts[2].parse().unwrap_or(0.0),
+                    parts[3].parse().unwrap_or(0.0),
+                    parts[4].parse().unwrap_or(0.0),
+                    parts[5].parse().unwrap_or(0.0),
+                    parts[6].parse().unwrap_or(0.0),
+                    parts[7].parse().unwrap_or(0.0),
+                ];
+                let profile_id = parts[1].parse::<i32>().unwrap_or_default();
+                match state.queue_position(position, profile_id) {
+                    Ok(_) => "0 \r\n".to_string(),
+                    Err(e) => format!("-1 {}\r\n", e),
+                }
+            }
+        }
+
+        "movej" => {
+            let state = &mut robot_state.lock().unwrap();
+            if let Err(reply) = state.check_can_move() {
+                reply
+            } else {
+                let joints = parts
+                    .get(2..)
+                    .unwrap_or_default()
+                    .iter()
+                    .map(|joint| joint.to_string())
+                    .collect::<Vec<String>>();
+                let profile_id = parts
+                    .get(1)
+                    .and_then(|id| id.parse::<i32>().ok())
+                    .unwrap_or_default();
+                match JointPosition::from_response(&joints) {
+                    Ok(joints) => {
+                        let mut target = state.final_joints();
+                        target[..5].copy_from_slice(&[
+                            joints.z_mm.mm(),
+                            joints.shoulder_deg.deg(),
+                            joints.elbow_deg.deg(),
+                            joints.wrist_deg.deg(),
+                            joints.gripper_mm.mm(),
+                        ]);
+                        if let Some(rail_mm) = joints.rail_mm {
+                            target[5] = rail_mm.mm();
+                        }
+                        if let Err(reply) = state.check_limits(&target) {
+                            return reply;
+                        }
+                        match state.queue_joints(target, None, profile_id) {
+                            Ok(_) => "0 \r\n".to_string(),
+                            Err(e) => format!("-1 {}\r\n", e),
+                        }
+                    }
+                    Err(e) => format!("-1 {}\r\n", e),
+                }
+            }
+        }
+
+        "state" => {
+            let state = robot_state.lock().unwrap();
+            format!("0 {}\r\n", state.motion_state())
+        }
+
+        "moveoneaxis" => {
+            let state = &mut robot_state.lock().unwrap();
+            if let Err(reply) = state.check_can_move() {
+                reply
+            } else if parts.len() < 4 {
+                "-1 Insufficient parameters\r\n".to_string()
+            } else {
+                let axis = parts[1]
+                    .parse::<usize>()
+                    .ok()
+                    .filter(|axis| (1..=6).contains(axis));
+                match (axis, parts[2].parse::<f64>(), parts[3].parse::<i32>()) {
+                    (Some(axis), Ok(value), Ok(profile_id)) => {
+                        let mut target = state.final_joints();
+                        target[axis - 1] = value;
+                        if let Err(reply) = state.check_limits(&target) {
+                            return reply;
+                        }
+                        match state.queue_joints(target, None, profile_id) {
+                            Ok(_) => "0 \r\n".to_string(),
+                            Err(e) => format!("-1 {}\r\n", e),
+                        }
+                    }
+                    _ => "-1 Invalid parameters\r\n".to_string(),
+                }
+            }
+        }
+
+        "moverail" => {
+            let state = &mut robot_state.lock().unwrap();
+            if state.rail_position.is_none() {
+                "-1 No rail available\r\n".to_string()
+            } else if let Err(reply) = state.check_can_move() {
+                reply
+            } else {
+                match parts
+                    .last()
+                    .and_then(|position| position.parse::<f64>().ok())
+                {
+                    Some(position) if parts.len() > 1 => {
+                        let mut target = state.final_joints();
+                        target[5] = position;
+                        match state.check_limits(&target) {
+                            Ok(_) => {
+                                state.queue_move(target, None, ProfileSettings::default());
+                                "0 \r\n".to_string()
+                            }
+                            Err(reply) => reply,
+                        }
+                    }
+                    _ => "-1 Insufficient parameters\r\n".to_string(),
+                }
+            }
+        }
+
+        "pd" => {
+            if parts.len() < 2 {
+                "-1 Insufficient parameters\r\n".to_string()
+            } else {
+                let state = robot_state.lock().unwrap();
+                match parts[1] {
+                    "2800" => if state.homed { "0 1\r\n" } else { "0 0\r\n" }.to_string(), // HomingStatus
+                    "2003" => if state.rail_position.is_some() {
+                        "0 111\r\n"
+                    } else {
+                        "0 15\r\n"
+                    }
+                    .to_string(), // Axis config
+                    "320" => "0 0\r\n".to_string(), // LastError
+                    _ => "-1 Unknown parameter\r\n".to_string(),
+                }
+            }
+        }
+
+        "wherej" => {
+            let state = robot_state.lock().unwrap();
+            format!(
+                "0 {} {} {} {} {} {}\r\n",
+                state.joint_positions[0],
+                state.joint_positions[1],
+                state.joint_positions[2],
+                state.joint_positions[3],
+                state.joint_positions[4],
+                state.joint_positions[5]
+            )
+        }
+
+        "wherec" => {
+            let state = robot_state.lock().unwrap();
+            format!(
+                "0 {} {} {} {} {} {}\r\n",
+                state.position[0],
+                state.position[1],
+                state.position[2],
+                state.position[3],
+                state.position[4],
+                state.position[5]
+            )
+        }
+
+        "freemode" => {
+            let mut state = robot_state.lock().unwrap();
+            if parts.len() > 1 {
+                if parts[1] == "-1" {
+                    state.free_mode = false;
+                    state.free_joints.clear();
+                    return "0 \r\n".to_string();
+                }
+                // 0 releases every joint, any other joint number releases just that joint
+                let joint_count = state.joint_positions.len() as i32;
+                let joints: Vec<i32> = match parts[1].parse::<i32>() {
+                    Ok(0) => (1..=joint_count).collect(),
+                    Ok(joint) if (1..=joint_count).contains(&joint) => vec![joint],
+                    _ => return "-1 Invalid joint\r\n".to_string(),
+                };
+                // like the controller, nothing is sent back when free mode is switched on
+                let refused = if state.estop {
+                    Some(ResponseCodes::HardEStop)
+                } else if !state.power {
+                    Some(ResponseCodes::RobotPowerNotEnabled)
+                } else if !state.attached {
+                    Some(ResponseCodes::RobotNotAttached)
+                } else {
+                    None
+                };
+                match refused {
+                    Some(code) => warn!(error = code.description(); "free mode refused"),
+                    None => {
+                        state.halt();
+                        state.free_mode = true;
+                        state.free_joints.extend(joints);
+                    }
+                }
+                String::new()
+            } else {
+                "-1 Insufficient parameters\r\n".to_string()
+            }
+        }
+
+        "zerotorque" => {
+            let mut state = robot_state.lock().unwrap();
+            if parts.len() > 1 {
+                state.zero_torque = parts[1] == "1";
+                "0 \r\n".to_string()
+            } else {
+                "-1 Insufficient parameters\r\n".to_string()
+            }
+        }
+
+        "mspeed" => {
+            let mut state = robot_state.lock().unwrap();
+            if parts.len() > 1 {
+                if let Ok(speed) = parts[1].parse::<i32>() {
+                    state.system_speed = speed;
+                }
+                "0 \r\n".to_string()
+            } else {
+                format!("0 {}\r\n", state.system_speed)
+            }
+        }
+
+        "payload" => {
+            let mut state = robot_state.lock().unwrap();
+            if !state.power {
+                error_reply(ResponseCodes::RobotPowerNotEnabled)
+            } else if parts.len() > 1 {
+                match parts[1].parse::<f64>() {
+                    Ok(percent) if (0.0..=100.0).contains(&percent) => {
+                        state.payload_percent = percent;
+                        "0 \r\n".to_string()
+                    }
+                    _ => "-1 Invalid payload\r\n".to_string(),
+                }
+            } else {
+                format!("0 {}\r\n", state.payload_percent)
+            }
+        }
+
+        "graspplate" => {
+            let state = &mut robot_state.lock().unwrap();
+            if let Err(reply) = state.check_can_move() {
+                reply
+            } else if parts.len() < 4 {
+                "-1 Insufficient parameters\r\n".to_string()
+            } else {
+                // the controller replies with -1 when a plate is detected between the fingers
+                match parts[1].parse::<f64>().map(|width| state.grasp(width)) {
+                    Ok(Ok(true)) => "0 -1\r\n".to_string(),
+                    Ok(Ok(false)) => "0 0\r\n".to_string(),
+                    Ok(Err(reply)) => reply,
+                    Err(_) => "-1 Invalid parameters\r\n".to_string(),
+                }
+            }
+        }
+
+        "releaseplate" => {
+            let state = &mut robot_state.lock().unwrap();
+            if let Err(reply) = state.check_can_move() {
+                reply
+            } else if parts.len() < 3 {
+                "-1 Insufficient parameters\r\n".to_string()
+            } else {
+                match parts[1].parse::<f64>().map(|width| state.release(width)) {
+                    Ok(Ok(_)) => "0 \r\n".to_string(),
+                    Ok(Err(reply)) => reply,
+                    Err(_) => "-1 Invalid parameters\r\n".to_string(),
+                }
+            }
+        }
+
+        "waitforeom" => {
+            // poll without holding the lock so other clients can query or halt the robot
+            while robot_state.lock().unwrap().is_moving() {
+                thread::sleep(MOTION_POLL_INTERVAL);
+                robot_state.lock().unwrap().update(Instant::now());
+            }
+            if robot_state.lock().unwrap().estop {
+                error_reply(ResponseCodes::HardEStop)
+            } else {
+                "0 \r\n".to_string()
+            }
+        }
+
+        "rail" => "0 \r\n".to_string(),
+
+        "sig" => {
+            let state = &mut robot_state.lock().unwrap();
+            let signal = parts.get(1).and_then(|signal| signal.parse::<i32>().ok());
+            match (signal, parts.get(2)) {
+                (Some(signal), None) => format!("0 {} {}\r\n", signal, state.signal(signal) as i32),
+                (Some(signal), Some(_)) if signal >= RobotState::FIRST_INPUT_SIGNAL => {
+                    format!("-1 Signal {} is an input\r\n", signal)
+                }
+                (Some(signal), Some(value)) => match value.parse::<i32>() {
+                    Ok(value) => {
+                        state.signals.insert(signal, value != 0);
+                        "0 \r\n".to_string()
+                    }
+                    Err(_) => "-1 Invalid parameters\r\n".to_string(),
+                },
+                (None, _) => "-1 Insufficient parameters\r\n".to_string(),
+            }
+        }
+
+        "exit" => "0 \r\n".to_string(),
+
+        _ => format!("-1 Unknown command: {}\r\n", cmd),
+    }
+}
+
+fn parse_values<const N: usize>(values: &[&str]) -> Result<[f64; N], String> {
+    let mut parsed = [0.0; N];
+    for (value, text) in parsed.iter_mut().zip(values) {
+        *value = text.parse::<f64>().map_err(|e| e.to_string())?;
+    }
+    Ok(parsed)
+}
+
+fn robot_loop(robots: Vec<Arc<Mutex<RobotState>>>, running: Arc<AtomicBool>) {
+    let mut last_tick = Instant::now();
+    while running.load(Ordering::SeqCst) {
+        let now = Instant::now();
+        let elapsed = now.duration_since(last_tick);
+        last_tick = now;
+        for robot_state in &robots {
+            let mut state = robot_state.lock().unwrap();
+            state.update(now);
+            state.drift(elapsed);
+        }
+        thread::sleep(Duration::from_millis(100));
+    }
+}
+
+fn accept_loop(
+    listener: TcpListener,
+    robots: Vec<Arc<Mutex<RobotState>>>,
+    replay: Option<Arc<Mutex<Replay>>>,
+    running: Arc<AtomicBool>,
+) {
+    let mut clients: Vec<(TcpStream, JoinHandle<()>)> = Vec::new();
+    while running.load(Ordering::SeqCst) {
+        match listener.accept() {
+            Ok((stream, _)) => {
+                let client = stream
+                    .set_nonblocking(false)
+                    .and_then(|_| stream.try_clone());
+                match client {
+                    Ok(client) => {
+                        let client_robots = robots.clone();
+                        let client_replay = replay.clone();
+                        let handle = thread::spawn(move || {
+                            handle_client(stream, client_robots, client_replay);
+                        });
+                        clients.push((client, handle));
+                    }
+                    Err(e) => warn!(error:% = e; "error accepting connection"),
+                }
+            }
+            Err(e) if e.kind() == ErrorKind::WouldBlock => {
+                thread::sleep(MockRobot::ACCEPT_POLL_INTERVAL)
+            }
+            Err(e) => {
+                warn!(error:% = e; "error accepting connection");
+            }
+        }
+    }
+    for (client, handle) in clients {
+        let _ = client.shutdown(Shutdown::Both);
+        let _ = handle.join();
+    }
+}
+
+/// Simulated robot served over TCP on a local port
+///
+/// The simulator runs on background threads until `shutdown` is called or the MockRobot is
+/// dropped, which also disconnects any connected clients. It can simulate a controller driving
+/// several robots, each with its own state, or serve back a recorded session.
+pub struct MockRobot {
+    address: SocketAddr,
+    robots: Vec<Arc<Mutex<RobotState>>>,
+    replay: Option<Arc<Mutex<Replay>>>,
+    running: Arc<AtomicBool>,
+    threads: Vec<JoinHandle<()>>,
+}
+
+impl MockRobot {
+    const LOCALHOST: &'static str = "127.0.0.1";
+    const ACCEPT_POLL_INTERVAL: Duration = Duration::from_millis(10);
+
+    /// Starts a simulator on an ephemeral port on localhost
+    pub fn start() -> Result<Self, RobotError> {
+        Self::start_with_robots(1)
+    }
+
+    /// Starts a simulator of a controller driving several robots on an ephemeral port on localhost
+    /// # Arguments
+    /// * `robot_count` - Number of robots the controller drives
+    pub fn start_with_robots(robot_count: usize) -> Result<Self, RobotError> {
+        Self::bind_with_robots(&format!("{}:0", Self::LOCALHOST), robot_count)
+    }
+
+    /// Starts a simulator on a fixed address
+    /// # Arguments
+    /// * `address` - Address to listen on, e.g. `127.0.0.1:10100`
+    pub fn bind(address: &str) -> Result<Self, RobotError> {
+        Self::bind_with_robots(address, 1)
+    }
+
+    /// Starts a simulator of a controller driving several robots on a fixed address
+    /// # Arguments
+    /// * `address` - Address to listen on, e.g. `127.0.0.1:10100`
+    /// * `robot_count` - Number of robots the controller drives
+    pub fn bind_with_robots(address: &str, robot_count: usize) -> Result<Self, RobotError> {
+        info!("MockRobot::bind called");
+        Self::launch(address, robot_count, None)
+    }
+
+    /// Starts a simulator on an ephemeral port on localhost that replays a recorded session
+    /// # Arguments
+    /// * `session` - The recording to serve, see the `session` module
+    pub fn start_replay(session: Session) -> Result<Self, RobotError> {
+        Self::bind_replay(&format!("{}:0", Self::LOCALHOST), session)
+    }
+
+    /// Starts a simulator on a fixed address that replays a recorded session
+    /// # Arguments
+    /// * `address` - Address to listen on, e.g. `127.0.0.1:10100`
+    /// * `session` - The recording to serve, see the `session` module
+    pub fn bind_replay(address: &str, session: Session) -> Result<Self, RobotError> {
+        info!("MockRobot::bind_replay called");
+        let replay = Arc::new(Mutex::new(Replay::new(session)));
+        Self::launch(address, 1, Some(replay))
+    }
+
+    fn launch(
+        address: &str,
+        robot_count: usize,
+        replay: Option<Arc<Mutex<Replay>>>,
+    ) -> Result<Self, RobotError> {
+        if robot_count == 0 {
+            return Err("The simulator needs at least one robot".to_string());
+        }
+        let listener = TcpListener::bind(address)
+            .map_err(|e| format!("Failed to bind to {}: {}", address, e))?;
+        let local_address = listener.local_addr().map_err(|e| e.to_string())?;
+        listener.set_nonblocking(true).map_err(|e| e.to_string())?;
+
+        let robots = (0..robot_count)
+            .map(|_| Arc::new(Mutex::new(RobotState::new())))
+            .collect::<Vec<_>>();
+        let running = Arc::new(AtomicBool::new(true));
+        let accept_robots = robots.clone();
+        let accept_replay = replay.clone();
+        let accept_running = running.clone();
+        let loop_robots = robots.clone();
+        let loop_running = running.clone();
+        let threads = vec![
+            thread::spawn(move || {
+                accept_loop(listener, accept_robots, accept_replay, accept_running)
+            }),
+            thread::spawn(move || robot_loop(loop_robots, loop_running)),
+        ];
+        Ok(MockRobot {
+            address: local_address,
+            robots,
+            replay,
+            running,
+            threads,
+        })
+    }
+
+    /// Starts a simulator of the cell described by the settings
+    /// # Arguments
+    /// * `config` - Settings of the simulated cell
+    pub fn with_config(config: &MockConfig) -> Result<Self, RobotError> {
+        info!("MockRobot::with_config called");
+        config.validate()?;
+        if let Some(replay) = &config.replay {
+            return Self::bind_replay(&config.address, Session::load(replay)?);
+        }
+        let mock = Self::bind_with_robots(&config.address, config.robots)?;
+        for robot_index in 1..=mock.robot_count() {
+            mock.robot_state(robot_index).configure(config)?;
+        }
+        if let Some(scenario) = &config.scenario {
+            mock.load_scenario(scenario)?;
+        }
+        if let Some(tables) = &config.tables {
+            mock.load_tables(tables)?;
+        }
+        Ok(mock)
+    }
+
+    /// Address the simulator is listening on
+    pub fn address(&self) -> SocketAddr {
+        self.address
+    }
+
+    /// Port the simulator is listening on
+    pub fn port(&self) -> u16 {
+        self.address.port()
+    }
+
+    /// Number of robots the simulated controller drives
+    pub fn robot_count(&self) -> usize {
+        self.robots.len()
+    }
+
+    /// Locks the state of the first simulated robot for inspecting or changing it, any move being
+    /// carried out is brought up to date first
+    pub fn state(&self) -> MutexGuard<'_, RobotState> {
+        self.robot_state(1)
+    }
+
+    /// Locks the state of one of the simulated robots, any move being carried out is brought up
+    /// to date first
+    /// # Arguments
+    /// * `robot_index` - Index of the robot, starting at 1
+    /// # Panics
+    /// * If the controller doesn't drive a robot with that index
+    pub fn robot_state(&self, robot_index: usize) -> MutexGuard<'_, RobotState> {
+        let robot = robot_index
+            .checked_sub(1)
+            .and_then(|index| self.robots.get(index))
+            .unwrap_or_else(|| panic!("The simulator has no robot {}", robot_index));
+        let mut state = robot.lock().unwrap();
+        state.update(Instant::now());
+        state
+    }
+
+    /// Injects a fault into the replies to matching commands
+    /// # Arguments
+    /// * `rule` - The fault and the commands it applies to
+    pub fn inject_fault(&self, rule: FaultRule) {
+        info!("MockRobot::inject_fault called");
+        self.state().inject_fault(rule);
+    }
+
+    /// Injects every fault in a scenario file
+    /// # Arguments
+    /// * `path` - Path to the scenario file
+    pub fn load_scenario<P: AsRef<Path>>(&self, path: P) -> Result<(), RobotError> {
+        info!("MockRobot::load_scenario called");
+        let rules = FaultRule::load_scenario(path)?;
+        let mut state = self.state();
+        for rule in rules {
+            state.inject_fault(rule);
+        }
+        Ok(())
+    }
+
+    /// Saves the location and profile tables to a JSON file
+    /// # Arguments
+    /// * `path` - Path to write the tables to
+    pub fn save_tables<P: AsRef<Path>>(&self, path: P) -> Result<(), RobotError> {
+        info!("MockRobot::save_tables called");
+        let json = self.state().tables().to_json()?;
+        fs::write(path.as_ref(), json)
+            .map_err(|e| format!("Failed to write {}: {}", path.as_ref().display(), e))
+    }
+
+    /// Replaces the location and profile tables with the ones saved in a JSON file
+    /// # Arguments
+    /// * `path` - Path to read the tables from
+    pub fn load_tables<P: AsRef<Path>>(&self, path: P) -> Result<(), RobotError> {
+        info!("MockRobot::load_tables called");
+        let json = fs::read_to_string(path.as_ref())
+            .map_err(|e| format!("Failed to read {}: {}", path.as_ref().display(), e))?;
+        self.state().set_tables(MockTables::from_json(&json)?)
+    }
+
+    /// Requests that didn't match the recording being replayed, empty when not replaying
+    pub fn divergences(&self) -> Vec<Divergence> {
+        self.replay.as_ref().map_or_else(Vec::new, |replay| {
+            replay.lock().unwrap().divergences().to_vec()
+        })
+    }
+
+    /// Whether every exchange in the recording being replayed has been served, true when not
+    /// replaying
+    pub fn replay_finished(&self) -> bool {
+        self.replay
+            .as_ref()
+            .is_none_or(|replay| replay.lock().unwrap().is_finished())
+    }
+
+    /// Connects a PFlexRobot to the simulator
+    /// # Arguments
+    /// * `has_rail` - Whether the robot should expect a linear rail
+    pub fn connect(&self, has_rail: bool) -> PFlexRobot {
+        PFlexRobot::new_with_port(&self.address.ip().to_string(), self.port(), has_rail)
+    }
+
+    /// Opens a connection to the simulator that several robots can share
+    pub fn connect_shared(&self) -> Result<SharedConnection, RobotError> {
+        SharedConnection::connect(&self.address.ip().to_string(), self.port(), None)
+            .map_err(|e| e.to_string())
+    }
+
+    /// Blocks until the simulator is shut down
+    pub fn wait(mut self) {
+        self.join();
+    }
+
+    /// Stops the simulator and disconnects every client
+    pub fn shutdown(mut self) {
+        info!("MockRobot::shutdown called");
+        self.stop();
+    }
+
+    fn stop(&mut self) {
+        self.running.store(false, Ordering::SeqCst);
+        self.join();
+    }
+
+    fn join(&mut self) {
+        for handle in self.threads.drain(..) {
+            let _ = handle.join();
+        }
+    }
+}
+
+impl Drop for MockRobot {
+    fn drop(&mut self) {
+        self.stop();
+    }
+}
@@ -0,0 +1,198 @@
+// Settings for a simulated cell
+//
+// The settings can be read from a TOML file and overridden by command line flags, so every
+// developer can simulate their own cell layout, e.g.
+//
+// address = "127.0.0.1:10100"
+// robots = 1
+// model = "PF3400"
+// rail = true
+// power = false
+// system_speed = 50
+// joints = [150.0, 0.0, 90.0, 0.0, 80.0, 0.0]
+// time_scale = 1.0
+// log_level = "info"
+// scenario = "faults.txt"
+// tables = "tables.json"
+// replay = "session.jsonl"
+
+use crate::enums::RobotModel;
+use crate::error_codes::RobotError;
+use crate::tcs_client::TCSClient;
+use log::LevelFilter;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::str::FromStr;
+
+/// Settings for a simulated cell
+#[derive(Debug, Clone, PartialEq, serde::Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct MockConfig {
+    /// Address to listen on
+    pub address: String,
+    /// Number of robots the controller drives
+    pub robots: usize,
+    pub model: RobotModel,
+    /// Whether the robots are mounted on a rail
+    pub rail: bool,
+    /// Whether the robots start with power enabled
+    pub power: bool,
+    /// System speed percentage the robots start with
+    pub system_speed: i32,
+    /// Joints the robots start at: z, shoulder, elbow, wrist, gripper and rail
+    pub joints: Option<[f64; 6]>,
+    /// How long moves take compared to a real robot, e.g. 0.5 for moves twice as fast
+    pub time_scale: f64,
+    /// Most detailed log messages to write: off, error, warn, info, debug or trace
+    pub log_level: String,
+    /// Fault scenario file to load, see the `faults` module
+    pub scenario: Option<PathBuf>,
+    /// Location and profile tables to load, as saved by `MockRobot::save_tables`
+    pub tables: Option<PathBuf>,
+    /// Session to replay instead of simulating robots, as recorded by a `SessionRecorder`
+    pub replay: Option<PathBuf>,
+}
+
+impl MockConfig {
+    /// Flags accepted by `from_args`
+    pub const USAGE: &'static str = "\
+usage: mock_server [options]
+  --config <path>       read settings from a TOML file, the other flags override it
+  --address <ip:port>   address to listen on
+  --port <port>         port to listen on, keeping the address
+  --robots <count>      number of robots the controller drives
+  --model <model>       PF400 or PF3400
+  --rail / --no-rail    whether the robots are mounted on a rail
+  --power               start with power enabled
+  --speed <percent>     starting system speed
+  --joints <z,s,e,w,g,r> starting joints
+  --time-scale <scale>  how long moves take compared to a real robot
+  --scenario <path>     fault scenario file to load
+  --tables <path>       location and profile tables to load
+  --replay <path>       replay a recorded session instead of simulating robots
+  --log-level <level>   off, error, warn, info, debug or trace
+  -v / -q               log everything / only errors";
+
+    /// Reads the settings from a TOML file
+    /// # Arguments
+    /// * `path` - Path to the config file
+    pub fn load<P: AsRef<Path>>(path: P) -> Result<Self, RobotError> {
+        let contents = fs::read_to_string(path.as_ref())
+            .map_err(|e| format!("Failed to read {}: {}", path.as_ref().display(), e))?;
+        Self::parse(&contents)
+    }
+
+    /// Parses the contents of a TOML config file
+    /// # Arguments
+    /// * `contents` - The settings, any that are left out keep their defaults
+    pub fn parse(contents: &str) -> Result<Self, RobotError> {
+        let config: MockConfig =
+            toml::from_str(contents).map_err(|e| format!("Invalid config: {}", e))?;
+        config.validate()?;
+        Ok(config)
+    }
+
+    /// Builds the settings from command line flags, see `USAGE`
+    /// # Arguments
+    /// * `args` - The flags, not including the program name
+    pub fn from_args<I: IntoIterator<Item = String>>(args: I) -> Result<Self, RobotError> {
+        let args: Vec<String> = args.into_iter().collect();
+        // the file is the base the other flags apply to, wherever it is given
+        let file = args.iter().rposition(|flag| flag == "--config");
+        let mut config = match file {
+            Some(i) => Self::load(args.get(i + 1).ok_or("--config needs a value")?)?,
+            None => MockConfig::default(),
+        };
+        let mut args = args.into_iter();
+        while let Some(flag) = args.next() {
+            let mut value = || args.next().ok_or(format!("{} needs a value", flag));
+            match flag.as_str() {
+                "--config" => {
+                    value()?;
+                }
+                "--address" => config.address = value()?,
+                "--port" => {
+                    let port = parse_flag::<u16>(&flag, &value()?)?;
+                    let host = config.address.rsplit_once(':').map_or("127.0.0.1", |a| a.0);
+                    config.address = format!("{}:{}", host, port);
+                }
+                "--robots" => config.robots = parse_flag(&flag, &value()?)?,
+                "--model" => config.model = parse_flag(&flag, &value()?)?,
+                "--rail" => config.rail = true,
+                "--no-rail" => config.rail = false,
+                "--power" => config.power = true,
+                "--speed" => config.system_speed = parse_flag(&flag, &value()?)?,
+                "--joints" => {
+                    let joints = value()?
+                        .split(',')
+                        .map(|joint| parse_flag::<f64>(&flag, joint.trim()))
+                        .collect::<Result<Vec<f64>, RobotError>>()?;
+                    let joints = <[f64; 6]>::try_from(joints)
+                        .map_err(|_| "--joints needs six comma separated values".to_string())?;
+                    config.joints = Some(joints);
+                }
+                "--time-scale" => config.time_scale = parse_flag(&flag, &value()?)?,
+                "--scenario" => config.scenario = Some(PathBuf::from(value()?)),
+                "--tables" => config.tables = Some(PathBuf::from(value()?)),
+                "--replay" => config.replay = Some(PathBuf::from(value()?)),
+                "--log-level" => config.log_level = value()?,
+                "-v" | "--verbose" => config.log_level = "trace".to_string(),
+                "-q" | "--quiet" => config.log_level = "error".to_string(),
+                _ => return Err(format!("Unrecognised flag: {}\n{}", flag, Self::USAGE)),
+            }
+        }
+        config.validate()?;
+        Ok(config)
+    }
+
+    /// Checks every setting is one the simulator can use
+    pub fn validate(&self) -> Result<(), RobotError> {
+        if self.robots == 0 {
+            return Err("The simulator needs at least one robot".to_string());
+        }
+        if !(1..=100).contains(&self.system_speed) {
+            return Err(format!(
+                "System speed of {}% is not between 1 and 100",
+                self.system_speed
+            ));
+        }
+        if !(self.time_scale.is_finite() && self.time_scale > 0.0) {
+            return Err(format!(
+                "Time scale of {} is not above zero",
+                self.time_scale
+            ));
+        }
+        self.level().map(|_| ())
+    }
+
+    /// Most detailed log messages to write
+    pub fn level(&self) -> Result<LevelFilter, RobotError> {
+        LevelFilter::from_str(&self.log_level)
+            .map_err(|_| format!("Invalid log level: {}", self.log_level))
+    }
+}
+
+impl Default for MockConfig {
+    fn default() -> Self {
+        MockConfig {
+            address: format!("127.0.0.1:{}", TCSClient::TCS_SERVER_PORT),
+            robots: 1,
+            model: RobotModel::PF400,
+            rail: true,
+            power: false,
+            system_speed: 50,
+            joints: None,
+            time_scale: 1.0,
+            log_level: "info".to_string(),
+            scenario: None,
+            tables: None,
+            replay: None,
+        }
+    }
+}
+
+fn parse_flag<T: FromStr>(flag: &str, value: &str) -> Result<T, RobotError> {
+    value
+        .parse::<T>()
+        .map_err(|_| format!("Invalid value for {}: {}", flag, value))
+}
@@ -0,0 +1,144 @@
+// Faults the simulator can inject into its replies
+//
+// Faults are attached to a command, or to every command, and fire a set number of times or until
+// they are cleared. They can be injected from tests with `MockRobot::inject_fault` or loaded from
+// a scenario file with one fault per line, blank lines and anything after a `#` are ignored:
+//
+// <command|*> latency <ms> [times <n>]
+// <command|*> drop [times <n>]
+// <command|*> close [times <n>]
+// <command|*> fragment <bytes> <ms> [times <n>]
+// <command|*> garble [times <n>]
+// <command|*> error <code> [times <n>]
+// <command|*> estop <ms> [times <n>]
+
+use crate::error_codes::RobotError;
+use std::fs;
+use std::num::NonZeroUsize;
+use std::path::Path;
+use std::time::Duration;
+
+/// Misbehaviour injected into the reply to a command
+#[derive(Debug, Clone, PartialEq)]
+pub enum Fault {
+    /// Waits before replying
+    Latency(Duration),
+    /// Carries out the command without replying
+    DropReply,
+    /// Closes the connection instead of carrying out the command
+    CloseSocket,
+    /// Sends the reply a few bytes at a time with a pause between each piece
+    Fragment {
+        bytes: NonZeroUsize,
+        delay: Duration,
+    },
+    /// Carries out the command and replies with bytes that aren't a valid reply
+    Garble,
+    /// Replies with an error code instead of carrying out the command
+    ErrorCode(i32),
+    /// Carries out the command and presses the E-STOP after a delay, e.g. partway through a move
+    EStopAfter(Duration),
+}
+
+/// A fault and the commands it is injected into
+#[derive(Debug, Clone, PartialEq)]
+pub struct FaultRule {
+    /// Command the fault applies to, e.g. `movec`, or None for every command
+    pub command: Option<String>,
+    pub fault: Fault,
+    /// How many more times the fault fires, None to keep firing until cleared
+    pub times: Option<usize>,
+}
+
+impl FaultRule {
+    /// Creates a rule that fires on every matching command until cleared
+    /// # Arguments
+    /// * `command` - Command the fault applies to, or None for every command
+    /// * `fault` - The fault to inject
+    pub fn new(command: Option<&str>, fault: Fault) -> Self {
+        FaultRule {
+            command: command.map(str::to_string),
+            fault,
+            times: None,
+        }
+    }
+
+    /// Limits how many times the fault fires
+    pub fn times(mut self, times: usize) -> Self {
+        self.times = Some(times);
+        self
+    }
+
+    /// Whether the rule applies to a command
+    pub fn matches(&self, command: &str) -> bool {
+        self.times != Some(0)
+            && self
+                .command
+                .as_deref()
+                .is_none_or(|c| c.eq_ignore_ascii_case(command))
+    }
+
+    /// Loads a scenario file
+    /// # Arguments
+    /// * `path` - Path to the scenario file
+    pub fn load_scenario<P: AsRef<Path>>(path: P) -> Result<Vec<FaultRule>, RobotError> {
+        let contents = fs::read_to_string(path.as_ref())
+            .map_err(|e| format!("Failed to read {}: {}", path.as_ref().display(), e))?;
+        Self::parse_scenario(&contents)
+    }
+
+    /// Parses the contents of a scenario file
+    /// # Arguments
+    /// * `contents` - One fault per line
+    pub fn parse_scenario(contents: &str) -> Result<Vec<FaultRule>, RobotError> {
+        let mut rules = Vec::new();
+        for (index, line) in contents.lines().enumerate() {
+            let line = line.split('#').next().unwrap_or_default();
+            let parts = line.split_whitespace().collect::<Vec<&str>>();
+            if parts.is_empty() {
+                continue;
+            }
+            let rule =
+                Self::parse_rule(&parts).map_err(|e| format!("line {}: {}", index + 1, e))?;
+            rules.push(rule);
+        }
+        Ok(rules)
+    }
+
+    fn parse_rule(parts: &[&str]) -> Result<FaultRule, RobotError> {
+        let (parts, times) = match parts {
+            [rest @ .., "times", times] => (rest, Some(parse_number::<usize>(times)?)),
+            _ => (parts, None),
+        };
+        let (command, fault) = match parts {
+            [command, "latency", ms] => (command, Fault::Latency(parse_millis(ms)?)),
+            [command, "drop"] => (command, Fault::DropReply),
+            [command, "close"] => (command, Fault::CloseSocket),
+            [command, "fragment", bytes, ms] => {
+                let bytes = NonZeroUsize::new(parse_number::<usize>(bytes)?)
+                    .ok_or("Fragments need at least one byte")?;
+                let delay = parse_millis(ms)?;
+                (command, Fault::Fragment { bytes, delay })
+            }
+            [command, "garble"] => (command, Fault::Garble),
+            [command, "error", code] => (command, Fault::ErrorCode(parse_number::<i32>(code)?)),
+            [command, "estop", ms] => (command, Fault::EStopAfter(parse_millis(ms)?)),
+            _ => return Err(format!("Unrecognised fault: {}", parts.join(" "))),
+        };
+        let command = (*command != "*").then_some(*command);
+        Ok(FaultRule {
+            times,
+            ..FaultRule::new(command, fault)
+        })
+    }
+}
+
+fn parse_number<T: std::str::FromStr>(value: &str) -> Result<T, RobotError> {
+    value
+        .parse::<T>()
+        .map_err(|_| format!("Invalid number: {}", value))
+}
+
+fn parse_millis(value: &str) -> Result<Duration, RobotError> {
+    parse_number::<u64>(value).map(Duration::from_millis)
+}
@@ -0,0 +1,103 @@
+// Structured logging for the simulator
+//
+// Every record is written to stderr as one logfmt line, so logs can be filtered and parsed by
+// standard tools. The key-values of a record, e.g. `info!(peer:% = addr; "client connected")`,
+// follow the message as pairs of their own:
+//
+// ts=1718000000.125 level=INFO target=pflex_module_rs::mock msg="client connected" peer=127.0.0.1:51234
+
+use crate::error_codes::RobotError;
+use log::kv::{self, Key, Value, VisitSource};
+use log::{LevelFilter, Log, Metadata, Record};
+use std::fmt::Write as _;
+use std::io::Write;
+use std::time::{SystemTime, UNIX_EPOCH};
+
+/// Logger writing logfmt lines to stderr
+pub struct LogfmtLogger {
+    level: LevelFilter,
+}
+
+impl LogfmtLogger {
+    /// Installs the logger for the whole process, which can only be done once
+    /// # Arguments
+    /// * `level` - Most detailed log messages to write
+    pub fn init(level: LevelFilter) -> Result<(), RobotError> {
+        let logger = Box::leak(Box::new(LogfmtLogger { level }));
+        log::set_logger(logger).map_err(|e| e.to_string())?;
+        log::set_max_level(level);
+        Ok(())
+    }
+
+    /// Formats a record as a logfmt line
+    pub fn format(record: &Record) -> String {
+        let timestamp = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .unwrap_or_default()
+            .as_secs_f64();
+        let mut line = format!(
+            "ts={:.3} level={} target={} msg={}",
+            timestamp,
+            record.level(),
+            quote(record.target()),
+            quote(&record.args().to_string())
+        );
+        // writing to a String can't fail
+        let _ = record.key_values().visit(&mut Pairs(&mut line));
+        line
+    }
+}
+
+// appends each key-value of a record as a logfmt pair
+struct Pairs<'a>(&'a mut String);
+
+impl<'kvs> VisitSource<'kvs> for Pairs<'_> {
+    fn visit_pair(&mut self, key: Key<'kvs>, value: Value<'kvs>) -> Result<(), kv::Error> {
+        let _ = write!(self.0, " {}={}", key, quote(&value.to_string()));
+        Ok(())
+    }
+}
+
+// values with spaces, quotes, equals signs or control characters are quoted and escaped
+fn quote(value: &str) -> String {
+    let bare = !value.is_empty()
+        && value
+            .chars()
+            .all(|c| !c.is_whitespace() && !c.is_control() && !matches!(c, '"' | '=' | '\\'));
+    if bare {
+        return value.to_string();
+    }
+    let mut quoted = String::with_capacity(value.len() + 2);
+    quoted.push('"');
+    for c in value.chars() {
+        match c {
+            '"' => quoted.push_str("\\\""),
+            '\\' => quoted.push_str("\\\\"),
+            '\n' => quoted.push_str("\\n"),
+            '\r' => quoted.push_str("\\r"),
+            '\t' => quoted.push_str("\\t"),
+            c if c.is_control() => {
+                let _ = write!(quoted, "\\u{:04x}", c as u32);
+            }
+            c => quoted.push(c),
+        }
+    }
+    quoted.push('"');
+    quoted
+}
+
+impl Log for LogfmtLogger {
+    fn enabled(&self, metadata: &Metadata) -> bool {
+        metadata.level() <= self.level
+    }
+
+    fn log(&self, record: &Record) {
+        if self.enabled(record.metadata()) {
+            let _ = writeln!(std::io::stderr().lock(), "{}", Self::format(record));
+        }
+    }
+
+    fn flush(&self) {
+        let _ = std::io::stderr().flush();
+    }
+}
@@ -0,0 +1,258 @@
+// Timed motion for the simulated robot
+//
+// Every move is planned as a trapezoidal velocity profile on the joint that takes the longest,
+// the other joints follow the same profile scaled to their own distance so every joint starts
+// and finishes together. Joint speeds and accelerations are the top rates of the simulated
+// robot, scaled by the motion profile and the system speed.
+
+use std::collections::VecDeque;
+use std::time::{Duration, Instant};
+
+/// Joint values in the order used by `wherej`: z, shoulder, elbow, wrist, gripper and rail
+pub type Joints = [f64; 6];
+
+/// Top joint speeds of the simulated robot in mm/s or deg/s
+const MAX_JOINT_SPEED: Joints = [800.0, 360.0, 360.0, 720.0, 200.0, 1000.0];
+/// Time taken to reach top speed at 100% acceleration
+const RAMP_TO_MAX_SPEED: f64 = 0.25;
+
+/// Motion profile settings as stored by the `profile` command
+#[derive(Debug, Clone, Copy, PartialEq, serde::Serialize, serde::Deserialize)]
+pub struct ProfileSettings {
+    pub speed: f64,
+    pub speed2: f64,
+    pub accel: f64,
+    pub decel: f64,
+    pub accel_ramp: f64,
+    pub decel_ramp: f64,
+    pub in_range: f64,
+    pub straight: bool,
+}
+
+impl ProfileSettings {
+    /// Parses the arguments of a `profile` command, not including the profile index
+    pub fn from_args(args: &[&str]) -> Result<Self, String> {
+        let values = args
+            .iter()
+            .map(|arg| arg.parse::<f64>())
+            .collect::<Result<Vec<f64>, _>>()
+            .map_err(|e| e.to_string())?;
+        if values.len() < 8 {
+            return Err("Insufficient parameters".to_string());
+        }
+        Ok(ProfileSettings {
+            speed: values[0],
+            speed2: values[1],
+            accel: values[2],
+            decel: values[3],
+            accel_ramp: values[4],
+            decel_ramp: values[5],
+            in_range: values[6],
+            straight: values[7] != 0.0,
+        })
+    }
+
+    /// Formats the settings the way the `profile` command takes them
+    pub fn to_args(&self) -> String {
+        format!(
+            "{} {} {} {} {} {} {} {}",
+            self.speed,
+            self.speed2,
+            self.accel,
+            self.decel,
+            self.accel_ramp,
+            self.decel_ramp,
+            self.in_range,
+            self.straight as i32
+        )
+    }
+}
+
+impl Default for ProfileSettings {
+    fn default() -> Self {
+        ProfileSettings {
+            speed: 50.0,
+            speed2: 0.0,
+            accel: 50.0,
+            decel: 50.0,
+            accel_ramp: 0.1,
+            decel_ramp: 0.1,
+            in_range: 10.0,
+            straight: false,
+        }
+    }
+}
+
+/// A move waiting for the moves before it to finish
+#[derive(Debug, Clone, PartialEq)]
+pub struct QueuedMove {
+    pub target: Joints,
+    /// Cartesian position reported once the move finishes, None to work it out from the joints
+    pub position: Option<[f64; 6]>,
+    pub profile: ProfileSettings,
+}
+
+/// Trapezoidal velocity profile along a normalised path from 0 to 1
+#[derive(Debug, Clone, Copy, PartialEq)]
+struct Trapezoid {
+    accel: f64,
+    decel: f64,
+    peak_speed: f64,
+    accel_time: f64,
+    cruise_time: f64,
+    decel_time: f64,
+}
+
+impl Trapezoid {
+    fn new(speed: f64, accel: f64, decel: f64) -> Self {
+        // shortest path that still reaches the top speed
+        let ramp_distance = speed * speed / (2.0 * accel) + speed * speed / (2.0 * decel);
+        let peak_speed = if ramp_distance > 1.0 {
+            (2.0 * accel * decel / (accel + decel)).sqrt()
+        } else {
+            speed
+        };
+        let accel_time = peak_speed / accel;
+        let decel_time = peak_speed / decel;
+        let ramp_distance = peak_speed * (accel_time + decel_time) / 2.0;
+        Trapezoid {
+            accel,
+            decel,
+            peak_speed,
+            accel_time,
+            cruise_time: (1.0 - ramp_distance).max(0.0) / peak_speed,
+            decel_time,
+        }
+    }
+
+    fn duration(&self) -> f64 {
+        self.accel_time + self.cruise_time + self.decel_time
+    }
+
+    fn progress(&self, elapsed: f64) -> f64 {
+        let cruise_start = self.accel_time;
+        let decel_start = cruise_start + self.cruise_time;
+        let progress = if elapsed <= 0.0 {
+            0.0
+        } else if elapsed < cruise_start {
+            self.accel * elapsed * elapsed / 2.0
+        } else if elapsed < decel_start {
+            self.peak_speed * (cruise_start / 2.0 + elapsed - cruise_start)
+        } else if elapsed < self.duration() {
+            let remaining = self.duration() - elapsed;
+            1.0 - self.decel * remaining * remaining / 2.0
+        } else {
+            1.0
+        };
+        progress.clamp(0.0, 1.0)
+    }
+}
+
+/// A move being carried out
+#[derive(Debug, Clone, PartialEq)]
+pub struct Motion {
+    from: Joints,
+    to: Joints,
+    position: Option<[f64; 6]>,
+    started: Instant,
+    trapezoid: Option<Trapezoid>,
+}
+
+impl Motion {
+    /// Plans a move from the current joints
+    /// # Arguments
+    /// * `from` - Joints at the start of the move
+    /// * `planned` - The move to carry out
+    /// * `system_speed` - System speed percentage every profile is scaled by
+    /// * `time_scale` - How long the move takes compared to a real robot
+    /// * `started` - When the move starts
+    pub fn plan(
+        from: Joints,
+        planned: &QueuedMove,
+        system_speed: f64,
+        time_scale: f64,
+        started: Instant,
+    ) -> Self {
+        // slowing speeds by the time scale and accelerations by its square stretches the whole
+        // profile evenly
+        let speed_scale =
+            (planned.profile.speed * system_speed / 10_000.0 / time_scale).max(f64::EPSILON);
+        let ramp_scale = time_scale * time_scale;
+        let accel_scale = (planned.profile.accel / 100.0 / ramp_scale).max(f64::EPSILON);
+        let decel_scale = (planned.profile.decel / 100.0 / ramp_scale).max(f64::EPSILON);
+        // the joint that takes the longest at its own top rates sets the pace for the others
+        let trapezoid = (0..from.len())
+            .filter(|&joint| (planned.target[joint] - from[joint]).abs() > f64::EPSILON)
+            .map(|joint| {
+                let distance = (planned.target[joint] - from[joint]).abs();
+                let speed = MAX_JOINT_SPEED[joint] * speed_scale / distance;
+                let accel = MAX_JOINT_SPEED[joint] / RAMP_TO_MAX_SPEED / distance;
+                Trapezoid::new(speed, accel * accel_scale, accel * decel_scale)
+            })
+            .max_by(|a, b| a.duration().total_cmp(&b.duration()));
+        Motion {
+            from,
+            to: planned.target,
+            position: planned.position,
+            started,
+            trapezoid,
+        }
+    }
+
+    /// Total time the move takes
+    pub fn duration(&self) -> Duration {
+        Duration::from_secs_f64(self.trapezoid.map_or(0.0, |t| t.duration()))
+    }
+
+    /// When the move finishes
+    pub fn finishes(&self) -> Instant {
+        self.started + self.duration()
+    }
+
+    /// Joints at a point in time during the move
+    pub fn joints_at(&self, now: Instant) -> Joints {
+        let elapsed = now.saturating_duration_since(self.started).as_secs_f64();
+        let progress = self.trapezoid.map_or(1.0, |t| t.progress(elapsed));
+        let mut joints = self.from;
+        for (joint, value) in joints.iter_mut().enumerate() {
+            *value += (self.to[joint] - self.from[joint]) * progress;
+        }
+        joints
+    }
+
+    /// Cartesian position to report once the move has finished
+    pub fn final_position(&self) -> Option<[f64; 6]> {
+        self.position
+    }
+
+    pub fn target(&self) -> Joints {
+        self.to
+    }
+}
+
+/// Moves being carried out and waiting to be carried out, in order
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct MotionQueue {
+    pub active: Option<Motion>,
+    pub queued: VecDeque<QueuedMove>,
+}
+
+impl MotionQueue {
+    pub fn is_moving(&self) -> bool {
+        self.active.is_some() || !self.queued.is_empty()
+    }
+
+    /// Joints the robot will end up at once every queued move has finished
+    pub fn final_joints(&self) -> Option<Joints> {
+        self.queued
+            .back()
+            .map(|planned| planned.target)
+            .or_else(|| self.active.as_ref().map(|motion| motion.target()))
+    }
+
+    /// Stops every move, leaving the robot wherever it is at the time
+    pub fn clear(&mut self) {
+        self.active = None;
+        self.queued.clear();
+    }
+}
@@ -0,0 +1,114 @@
+// Replaying a recorded session
+//
+// In replay mode the simulator doesn't simulate a robot, it answers each request with the reply
+// recorded for it by a SessionRecorder, in the order they were recorded. A request that isn't the
+// next one in the recording is a divergence: it is logged, kept for inspection through
+// `MockRobot::divergences`, and answered with an error so the client sees where it went off
+// script. The recording stays where it was, so the client can carry on once it is back in step.
+
+use crate::session::{Exchange, Session};
+use log::{debug, warn};
+use std::collections::VecDeque;
+use std::fmt;
+
+/// A request that didn't match the recording
+#[derive(Debug, Clone, PartialEq)]
+pub struct Divergence {
+    /// Index of the recorded exchange that was expected, starting at 0
+    pub index: usize,
+    /// Request that was recorded next, None if the recording had run out
+    pub expected: Option<String>,
+    /// Request that was received
+    pub received: String,
+}
+
+impl fmt::Display for Divergence {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match &self.expected {
+            Some(expected) => write!(
+                f,
+                "exchange {}: expected {:?}, received {:?}",
+                self.index, expected, self.received
+            ),
+            None => write!(
+                f,
+                "exchange {}: recording finished, received {:?}",
+                self.index, self.received
+            ),
+        }
+    }
+}
+
+/// Recorded replies waiting to be served
+#[derive(Debug, Clone, Default)]
+pub struct Replay {
+    exchanges: VecDeque<Exchange>,
+    served: usize,
+    divergences: Vec<Divergence>,
+}
+
+impl Replay {
+    /// Code the simulator replies with when a request doesn't match the recording
+    pub const DIVERGED_CODE: i32 = -1;
+
+    /// Creates a replay of a recorded session
+    /// # Arguments
+    /// * `session` - The recording to serve
+    pub fn new(session: Session) -> Self {
+        Replay {
+            exchanges: session.exchanges.into(),
+            served: 0,
+            divergences: Vec::new(),
+        }
+    }
+
+    /// Reply to a request, None if nothing was received for it when it was recorded
+    /// # Arguments
+    /// * `request` - The request as received, with or without its line ending
+    pub fn answer(&mut self, request: &str) -> Option<String> {
+        let request = request.trim_end_matches(['\r', '\n']);
+        match self.exchanges.front() {
+            Some(exchange) if exchange.request == request => {
+                let exchange = self.exchanges.pop_front().unwrap();
+                debug!(index = self.served; "replaying exchange");
+                self.served += 1;
+                exchange.reply.map(|reply| format!("{}\r\n", reply))
+            }
+            next => {
+                let divergence = Divergence {
+                    index: self.served,
+                    expected: next.map(|exchange| exchange.request.clone()),
+                    received: request.to_string(),
+                };
+                warn!("replay diverged {}", divergence);
+                let reply = format!(
+                    "{} Replay diverged: {}\r\n",
+                    Self::DIVERGED_CODE,
+                    divergence
+                );
+                self.divergences.push(divergence);
+                Some(reply)
+            }
+        }
+    }
+
+    /// Number of recorded exchanges served so far
+    pub fn served(&self) -> usize {
+        self.served
+    }
+
+    /// Number of recorded exchanges still to be served
+    pub fn remaining(&self) -> usize {
+        self.exchanges.len()
+    }
+
+    /// Whether every recorded exchange has been served
+    pub fn is_finished(&self) -> bool {
+        self.exchanges.is_empty()
+    }
+
+    /// Requests that didn't match the recording, in the order they were received
+    pub fn divergences(&self) -> &[Divergence] {
+        &self.divergences
+    }
+}
@@ -0,0 +1,767 @@
+// Terminal teach pendant
+//
+// The `pendant` binary draws a Pendant in the terminal and feeds it every key pressed, reading
+// the robot again between key presses so the positions and status stay live. Jog keys come in
+// pairs, the top row moves an axis in the positive direction and the row below moves it back:
+//
+// q/a  w/s  e/d  r/f  t/g      y/h
+// x    y    z    yaw           (Cartesian mode)
+// z    sh   el   wr   gripper  rail (joint mode)
+//
+// Space or Esc halts the robot, even while a location is being named. Free mode releases the
+// shoulder, elbow and wrist and keeps Z locked. Taught locations are kept in a TeachFile, saved
+// as TOML to the file given with `--file` whenever one is added or removed. Removing a location
+// is only done once it has been confirmed with y.
+
+use crate::args::{parse_arg, parse_timeout};
+use crate::connection::SharedConnection;
+use crate::enums::{CartesianAxis, JogFrame, Joint};
+use crate::error_codes::RobotError;
+use crate::pflex::PFlexRobot;
+use crate::structs::{EndEffectorPosition, JogStep, JointPosition, Waypoint};
+use crate::tcs_client::TCSClient;
+use crate::teach::TeachFile;
+use crate::units::{Degrees, Millimetres, Percent};
+use log::info;
+use ratatui::crossterm::event::{KeyCode, KeyEvent, KeyEventKind, KeyModifiers};
+use ratatui::layout::{Constraint, Layout};
+use ratatui::style::{Color, Modifier, Style, Stylize};
+use ratatui::text::{Line, Span};
+use ratatui::widgets::{Block, List, ListItem, ListState, Paragraph};
+use ratatui::Frame;
+use std::fmt;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+/// Connection settings and the file taught locations are kept in
+#[derive(Debug, Clone, PartialEq)]
+pub struct PendantOptions {
+    /// IP address of the controller
+    pub host: String,
+    /// Port the TCS server is listening on
+    pub port: u16,
+    /// Index of the robot on the controller, starting at 1
+    pub robot: i32,
+    /// Whether the robot is mounted on a rail
+    pub rail: bool,
+    /// Timeout for every socket read and write in seconds
+    pub timeout: Option<f64>,
+    /// Motion profile used for jogging and moves
+    pub profile: i32,
+    /// Width of the plates the gripper closes on
+    pub plate_width: Millimetres,
+    /// Teach file the locations are read from and saved to
+    pub file: Option<PathBuf>,
+}
+
+impl PendantOptions {
+    /// Help printed for `--help`
+    pub const USAGE: &'static str = "\
+usage: pendant [options]
+options:
+  --host <ip>           controller address, defaults to the ROBOT_IP environment variable
+  --port <port>         TCS server port
+  --robot <index>       robot on the controller, starting at 1
+  --rail / --no-rail    whether the robot is mounted on a rail, off by default
+  --timeout <seconds>   timeout for every socket read and write
+  --profile <id>        motion profile used for jogging and moves
+  --plate <mm>          width of the plates the gripper closes on
+  --file <path>         teach file the locations are read from and saved to";
+
+    /// Builds the options from command line flags, see `USAGE`
+    /// # Arguments
+    /// * `args` - The flags, not including the program name
+    pub fn from_args<I: IntoIterator<Item = String>>(args: I) -> Result<Self, RobotError> {
+        let mut options = PendantOptions {
+            host: "127.0.0.1".to_string(),
+            port: TCSClient::TCS_SERVER_PORT,
+            robot: SharedConnection::DEFAULT_ROBOT_INDEX,
+            rail: false,
+            timeout: None,
+            profile: Pendant::DEFAULT_MOTION_PROFILE,
+            plate_width: Pendant::DEFAULT_PLATE_WIDTH_MM,
+            file: None,
+        };
+        let mut args = args.into_iter();
+        while let Some(arg) = args.next() {
+            let mut value = || args.next().ok_or(format!("{} needs a value", arg));
+            match arg.as_str() {
+                "--host" => options.host = value()?,
+                "--port" => options.port = parse_arg(&arg, &value()?)?,
+                "--robot" => options.robot = parse_arg(&arg, &value()?)?,
+                "--rail" => options.rail = true,
+                "--no-rail" => options.rail = false,
+                "--timeout" => options.timeout = Some(parse_timeout(&arg, &value()?)?),
+                "--profile" => options.profile = parse_arg(&arg, &value()?)?,
+                "--plate" => {
+                    options.plate_width = Millimetres::from_mm(parse_arg(&arg, &value()?)?)
+                }
+                "--file" => options.file = Some(PathBuf::from(value()?)),
+                _ => return Err(format!("Unrecognised option: {}\n{}", arg, Self::USAGE)),
+            }
+        }
+        Ok(options)
+    }
+
+    /// Connects to the robot and opens the pendant on it
+    pub fn open(&self) -> Result<Pendant, RobotError> {
+        info!("PendantOptions::open called");
+        let connection = SharedConnection::connect(&self.host, self.port, self.timeout)
+            .map_err(|e| format!("Failed to connect to {}:{}: {}", self.host, self.port, e))?;
+        let mut pendant = Pendant::new(connection.robot(self.robot, self.rail));
+        pendant.motion_profile_id = self.profile;
+        pendant.plate_width = self.plate_width;
+        if let Some(file) = &self.file {
+            pendant.open_file(file)?;
+        }
+        Ok(pendant)
+    }
+}
+
+/// What the jog keys move
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum JogMode {
+    /// Along and about the world axes
+    Cartesian,
+    /// One joint at a time
+    Joint,
+}
+
+impl fmt::Display for JogMode {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            JogMode::Cartesian => write!(f, "cartesian"),
+            JogMode::Joint => write!(f, "joint"),
+        }
+    }
+}
+
+/// State of the robot shown on the pendant, as last read
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct PendantReadings {
+    pub powered: bool,
+    pub attached: bool,
+    pub homed: bool,
+    pub motion_state: String,
+    pub system_speed: Percent,
+    pub pose: Option<EndEffectorPosition>,
+    pub joints: Option<JointPosition>,
+}
+
+/// Teach pendant driving a robot from key presses
+pub struct Pendant {
+    robot: PFlexRobot,
+    readings: PendantReadings,
+    mode: JogMode,
+    step_index: usize,
+    motion_profile_id: i32,
+    plate_width: Millimetres,
+    free_mode: bool,
+    locations: TeachFile,
+    file: Option<PathBuf>,
+    selected: usize,
+    // name of the location being saved while it is typed
+    naming: Option<String>,
+    // location waiting for a y/n answer before it is removed
+    removing: Option<String>,
+    message: String,
+    finished: bool,
+}
+
+impl Pendant {
+    /// Jog step sizes along an axis or linear joint, `+` and `-` move through them
+    pub const LINEAR_STEPS: [Millimetres; 6] = [
+        Millimetres::from_mm(0.1),
+        Millimetres::from_mm(0.5),
+        Millimetres::from_mm(1.0),
+        Millimetres::from_mm(2.0),
+        Millimetres::from_mm(5.0),
+        Millimetres::from_mm(10.0),
+    ];
+    /// Jog step sizes about an axis or rotary joint, picked alongside `LINEAR_STEPS`
+    pub const ANGULAR_STEPS: [Degrees; 6] = [
+        Degrees::from_deg(0.1),
+        Degrees::from_deg(0.5),
+        Degrees::from_deg(1.0),
+        Degrees::from_deg(2.0),
+        Degrees::from_deg(5.0),
+        Degrees::from_deg(10.0),
+    ];
+    /// Motion profile used when none is given
+    pub const DEFAULT_MOTION_PROFILE: i32 = 1;
+    /// Plate width the gripper closes on when none is given
+    pub const DEFAULT_PLATE_WIDTH_MM: Millimetres = Millimetres::from_mm(127.0);
+    /// Width the gripper opens to
+    pub const OPEN_WIDTH_MM: Millimetres = Millimetres::from_mm(130.0);
+    /// Keys moving each axis in the positive and negative direction
+    pub const JOG_KEYS: [(char, char); 6] = [
+        ('q', 'a'),
+        ('w', 's'),
+        ('e', 'd'),
+        ('r', 'f'),
+        ('t', 'g'),
+        ('y', 'h'),
+    ];
+    const DEFAULT_STEP_INDEX: usize = 2;
+    const FINGER_SPEED_PERCENT: f64 = 50.0;
+    const GRASP_FORCE_PERCENT: f64 = 50.0;
+    const CARTESIAN_AXES: [CartesianAxis; 4] = [
+        CartesianAxis::X,
+        CartesianAxis::Y,
+        CartesianAxis::Z,
+        CartesianAxis::Yaw,
+    ];
+    const JOINTS: [Joint; 6] = [
+        Joint::Z,
+        Joint::Shoulder,
+        Joint::Elbow,
+        Joint::Wrist,
+        Joint::Gripper,
+        Joint::Rail,
+    ];
+
+    /// Opens a pendant on a connected robot, with no locations
+    /// # Arguments
+    /// * `robot` - The robot to drive
+    pub fn new(robot: PFlexRobot) -> Self {
+        Pendant {
+            robot,
+            readings: PendantReadings::default(),
+            mode: JogMode::Cartesian,
+            step_index: Self::DEFAULT_STEP_INDEX,
+            motion_profile_id: Self::DEFAULT_MOTION_PROFILE,
+            plate_width: Self::DEFAULT_PLATE_WIDTH_MM,
+            free_mode: false,
+            locations: TeachFile::new(),
+            file: None,
+            selected: 0,
+            naming: None,
+            removing: None,
+            message: "Ready".to_string(),
+            finished: false,
+        }
+    }
+
+    /// Reads locations from a teach file and saves them back to it from then on, the file is
+    /// created when the first location is saved if it doesn't exist
+    /// # Arguments
+    /// * `path` - The TOML teach file
+    pub fn open_file<P: AsRef<Path>>(&mut self, path: P) -> Result<(), RobotError> {
+        info!("Pendant::open_file called");
+        let path = path.as_ref();
+        if path.exists() {
+            let contents = fs::read_to_string(path)
+                .map_err(|e| format!("Failed to read {}: {}", path.display(), e))?;
+            let file: TeachFile = toml::from_str(&contents)
+                .map_err(|e| format!("Invalid teach file {}: {}", path.display(), e))?;
+            self.locations = file.migrate()?;
+        }
+        self.file = Some(path.to_path_buf());
+        Ok(())
+    }
+
+    /// The robot being driven
+    pub fn robot(&mut self) -> &mut PFlexRobot {
+        &mut self.robot
+    }
+
+    /// State of the robot as last read
+    pub fn readings(&self) -> &PendantReadings {
+        &self.readings
+    }
+
+    /// Locations taught so far, along with those read from the teach file
+    pub fn locations(&self) -> &TeachFile {
+        &self.locations
+    }
+
+    /// What the jog keys move
+    pub fn mode(&self) -> JogMode {
+        self.mode
+    }
+
+    /// Size of a jog step along an axis or linear joint
+    pub fn linear_step(&self) -> Millimetres {
+        Self::LINEAR_STEPS[self.step_index]
+    }
+
+    /// Size of a jog step about an axis or rotary joint
+    pub fn angular_step(&self) -> Degrees {
+        Self::ANGULAR_STEPS[self.step_index]
+    }
+
+    /// Result of the last action, or the error it failed with
+    pub fn message(&self) -> &str {
+        &self.message
+    }
+
+    /// Whether the operator has asked to leave
+    pub fn is_finished(&self) -> bool {
+        self.finished
+    }
+
+    /// Reads the status and position of the robot again
+    pub fn refresh(&mut self) -> Result<(), RobotError> {
+        let robot = &mut self.robot;
+        self.readings = PendantReadings {
+            powered: robot.is_robot_powered()?,
+            attached: robot.is_robot_attached()?,
+            homed: robot.is_robot_home()?,
+            motion_state: robot.get_motion_state()?.join(" "),
+            system_speed: robot.get_system_speed()?,
+            pose: robot.get_endeffector_position().ok(),
+            joints: robot.get_joint_positions().ok(),
+        };
+        Ok(())
+    }
+
+    /// Carries out the action bound to a key, see the module comment for the bindings
+    /// # Arguments
+    /// * `key` - The key pressed
+    pub fn handle_key(&mut self, key: KeyEvent) {
+        if key.kind == KeyEventKind::Release {
+            return;
+        }
+        if key.modifiers.contains(KeyModifiers::CONTROL)
+            && matches!(key.code, KeyCode::Char('c') | KeyCode::Char('q'))
+        {
+            self.finished = true;
+            return;
+        }
+        // halting comes first, whatever else is going on, and drops a name being typed
+        if matches!(key.code, KeyCode::Char(' ') | KeyCode::Esc) {
+            self.naming = None;
+            self.removing = None;
+            let result = self.robot.halt().map(|_| "Halted".to_string());
+            self.report(result);
+            return;
+        }
+        if let Some(name) = self.naming.as_mut() {
+            match key.code {
+                KeyCode::Char(c) if !c.is_whitespace() => name.push(c),
+                KeyCode::Backspace => {
+                    name.pop();
+                }
+                KeyCode::Enter => {
+                    let name = self.naming.take().unwrap_or_default();
+                    let result = self.save_location(&name);
+                    self.report(result);
+                }
+                KeyCode::Tab => {
+                    self.naming = None;
+                    self.message = "Cancelled".to_string();
+                }
+                _ => {}
+            }
+            return;
+        }
+        if let Some(name) = self.removing.take() {
+            let result = match key.code {
+                KeyCode::Char('y') => self.remove_location(&name),
+                _ => Ok(format!("Kept {}", name)),
+            };
+            self.report(result);
+            return;
+        }
+
+        let result = match key.code {
+            KeyCode::Char(c) if Self::jog_direction(c).is_some() => {
+                let (axis, forwards) = Self::jog_direction(c).unwrap();
+                self.jog(axis, forwards)
+            }
+            KeyCode::Char('m') => {
+                self.mode = match self.mode {
+                    JogMode::Cartesian => JogMode::Joint,
+                    JogMode::Joint => JogMode::Cartesian,
+                };
+                Ok(format!("Jogging in {} mode", self.mode))
+            }
+            KeyCode::Char('+') | KeyCode::Char('=') => {
+                self.step_index = (self.step_index + 1).min(Self::LINEAR_STEPS.len() - 1);
+                Ok(format!("Step {}", self.step_label()))
+            }
+            KeyCode::Char('-') => {
+                self.step_index = self.step_index.saturating_sub(1);
+                Ok(format!("Step {}", self.step_label()))
+            }
+            KeyCode::Char('o') => self
+                .robot
+                .release_plate(
+                    Self::OPEN_WIDTH_MM,
+                    Self::percent(Self::FINGER_SPEED_PERCENT),
+                )
+                .map(|_| "Gripper opened".to_string()),
+            KeyCode::Char('c') => self
+                .robot
+                .grasp_plate(
+                    self.plate_width,
+                    Self::percent(Self::FINGER_SPEED_PERCENT),
+                    Self::percent(Self::GRASP_FORCE_PERCENT),
+                )
+                .map(|grasped| match grasped {
+                    true => "Plate grasped".to_string(),
+                    false => "Gripper closed, no plate".to_string(),
+                }),
+            KeyCode::Char('F') => {
+                let free_mode = !self.free_mode;
+                // Z stays locked so the arm can't drop while it is guided by hand
+                let released = match free_mode {
+                    true => self.robot.set_free_mode_joints(&Joint::HORIZONTAL, false),
+                    false => self.robot.set_free_mode(false),
+                };
+                released.map(|_| {
+                    self.free_mode = free_mode;
+                    format!("Free mode {}", on_off(free_mode))
+                })
+            }
+            KeyCode::Char('P') => {
+                let power = !self.readings.powered;
+                self.robot
+                    .send_power(power)
+                    .map(|_| format!("Power {}", on_off(power)))
+            }
+            KeyCode::Char('A') => self.robot.attach_robot().map(|_| "Attached".to_string()),
+            KeyCode::Char('H') => self.robot.home_robot().map(|_| "Homed".to_string()),
+            KeyCode::Up => {
+                self.selected = self.selected.saturating_sub(1);
+                return;
+            }
+            KeyCode::Down => {
+                let last = self.locations.locations.len().saturating_sub(1);
+                self.selected = (self.selected + 1).min(last);
+                return;
+            }
+            KeyCode::Enter => self.move_to_selected(),
+            KeyCode::Char('n') => {
+                self.naming = Some(String::new());
+                self.message = "Name the location and press Enter, or Tab to cancel".to_string();
+                return;
+            }
+            KeyCode::Delete | KeyCode::Char('x') => {
+                match self.selected_location().map(|(name, _)| name.clone()) {
+                    Some(name) => {
+                        self.message = format!("Remove {}? [y/n]", name);
+                        self.removing = Some(name);
+                        return;
+                    }
+                    None => Err("No location selected".to_string()),
+                }
+            }
+            _ => return,
+        };
+        self.report(result);
+    }
+
+    /// Saves the current pose as a location, replacing any with the same name
+    /// # Arguments
+    /// * `name` - Name of the location
+    pub fn save_location(&mut self, name: &str) -> Result<String, RobotError> {
+        info!("Pendant::save_location called");
+        if name.is_empty() {
+            return Err("Locations need a name".to_string());
+        }
+        let pose = self.robot.get_endeffector_position()?;
+        let joints = self.robot.get_joint_positions()?;
+        let id = match self.locations.locations.get(name) {
+            Some(existing) => existing.id,
+            None => {
+                let ids = self
+                    .locations
+                    .locations
+                    .values()
+                    .map(|waypoint| waypoint.id);
+                ids.max().unwrap_or(0) + 1
+            }
+        };
+        self.locations
+            .locations
+            .insert(name.to_string(), pose.to_waypoint(id, joints.rail_mm));
+        self.selected = self
+            .locations
+            .locations
+            .keys()
+            .position(|key| key == name)
+            .unwrap_or_default();
+        self.write_file()?;
+        Ok(format!("Saved {} as location {}", name, id))
+    }
+
+    /// Moves to the selected location, moving the rail first if the location was taught on one
+    pub fn move_to_selected(&mut self) -> Result<String, RobotError> {
+        info!("Pendant::move_to_selected called");
+        let (name, waypoint) = self.selected_location().ok_or("No location selected")?;
+        let (name, rail_mm) = (name.clone(), waypoint.rail_position_mm);
+        let target = waypoint.to_endeffector_position();
+        // the controller queues the pose behind the rail move
+        if let Some(rail_mm) = rail_mm.filter(|_| self.robot.has_rail()) {
+            self.robot.move_rail(rail_mm)?;
+        }
+        self.robot
+            .move_to_cartesian(target, self.motion_profile_id)?;
+        Ok(format!("Moving to {}", name))
+    }
+
+    fn remove_location(&mut self, name: &str) -> Result<String, RobotError> {
+        self.locations.locations.remove(name);
+        self.selected = self
+            .selected
+            .min(self.locations.locations.len().saturating_sub(1));
+        self.write_file()?;
+        Ok(format!("Removed {}", name))
+    }
+
+    fn selected_location(&self) -> Option<(&String, &Waypoint)> {
+        self.locations.locations.iter().nth(self.selected)
+    }
+
+    fn write_file(&self) -> Result<(), RobotError> {
+        let Some(path) = &self.file else {
+            return Ok(());
+        };
+        let contents = toml::to_string(&self.locations).map_err(|e| e.to_string())?;
+        fs::write(path, contents).map_err(|e| format!("Failed to write {}: {}", path.display(), e))
+    }
+
+    fn jog(&mut self, axis: usize, forwards: bool) -> Result<String, RobotError> {
+        let step = |linear: bool| {
+            let step = if linear {
+                JogStep::Linear(self.linear_step())
+            } else {
+                JogStep::Angular(self.angular_step())
+            };
+            if forwards {
+                step
+            } else {
+                -step
+            }
+        };
+        match self.mode {
+            JogMode::Cartesian => {
+                let axis = Self::CARTESIAN_AXES
+                    .get(axis)
+                    .ok_or("No Cartesian axis on that key")?;
+                let step = step(!axis.is_rotation());
+                self.robot
+                    .jog_cartesian(*axis, step, &JogFrame::World, self.motion_profile_id)?;
+                Ok(format!("Jogged {:?} {}", axis, step))
+            }
+            JogMode::Joint => {
+                let joint = Self::JOINTS[axis];
+                let step = step(joint.is_linear());
+                self.robot.jog_joint(joint, step, self.motion_profile_id)?;
+                Ok(format!("Jogged {} {}", joint, step))
+            }
+        }
+    }
+
+    // axis index a jog key moves, and whether it moves it forwards
+    fn jog_direction(key: char) -> Option<(usize, bool)> {
+        Self::JOG_KEYS
+            .iter()
+            .enumerate()
+            .find_map(|(axis, &(plus, minus))| match key {
+                _ if key == plus => Some((axis, true)),
+                _ if key == minus => Some((axis, false)),
+                _ => None,
+            })
+    }
+
+    fn step_label(&self) -> String {
+        format!("{} / {}", self.linear_step(), self.angular_step())
+    }
+
+    fn percent(value: f64) -> Percent {
+        Percent::new(value).expect("Constant percentages are in range")
+    }
+
+    fn report(&mut self, result: Result<String, RobotError>) {
+        self.message = match result {
+            Ok(message) => message,
+            Err(e) => format!("error: {}", e),
+        };
+    }
+
+    /// Draws the pendant over the whole frame
+    /// # Arguments
+    /// * `frame` - The terminal frame being drawn
+    pub fn draw(&self, frame: &mut Frame) {
+        let [status, panels, keys, message] = Layout::vertical([
+            Constraint::Length(3),
+            Constraint::Min(9),
+            Constraint::Length(5),
+            Constraint::Length(1),
+        ])
+        .areas(frame.area());
+        let [cartesian, joints, locations] = Layout::horizontal([
+            Constraint::Length(24),
+            Constraint::Length(24),
+            Constraint::Min(24),
+        ])
+        .areas(panels);
+        let [help, halt] =
+            Layout::horizontal([Constraint::Min(40), Constraint::Length(18)]).areas(keys);
+
+        let readings = &self.readings;
+        let flag = |name: &str, value: bool| {
+            let style = match value {
+                true => Style::new().fg(Color::Green),
+                false => Style::new().fg(Color::Red),
+            };
+            vec![
+                Span::raw(format!("{} ", name)),
+                Span::styled(yes_no(value), style.add_modifier(Modifier::BOLD)),
+                Span::raw("   "),
+            ]
+        };
+        let mut line = Vec::new();
+        line.extend(flag("Power", readings.powered));
+        line.extend(flag("Attached", readings.attached));
+        line.extend(flag("Homed", readings.homed));
+        line.extend(flag("Free", self.free_mode));
+        line.push(Span::raw(format!(
+            "Motion {}   Speed {}",
+            readings.motion_state, readings.system_speed
+        )));
+        frame.render_widget(
+            Paragraph::new(Line::from(line)).block(Block::bordered().title(" PreciseFlex ")),
+            status,
+        );
+
+        let value_lines = |values: Vec<(&str, Option<f64>, &str)>| -> Vec<Line> {
+            values
+                .into_iter()
+                .map(|(name, value, unit)| match value {
+                    Some(value) => Line::from(format!("{:<9}{:>9.2} {}", name, value, unit)),
+                    None => Line::from(format!("{:<9}{:>9}", name, "-")),
+                })
+                .collect()
+        };
+        let pose = readings.pose.as_ref();
+        let cartesian_lines = value_lines(vec![
+            ("x", pose.map(|pose| pose.x_mm.mm()), "mm"),
+            ("y", pose.map(|pose| pose.y_mm.mm()), "mm"),
+            ("z", pose.map(|pose| pose.z_mm.mm()), "mm"),
+            ("yaw", pose.map(|pose| pose.yaw_deg.deg()), "deg"),
+            ("pitch", pose.map(|pose| pose.pitch_deg.deg()), "deg"),
+            ("roll", pose.map(|pose| pose.roll_deg.deg()), "deg"),
+        ]);
+        let joint_values = readings.joints.as_ref();
+        let joint_lines = value_lines(vec![
+            ("z", joint_values.map(|joints| joints.z_mm.mm()), "mm"),
+            (
+                "shoulder",
+                joint_values.map(|j| j.shoulder_deg.deg()),
+                "deg",
+            ),
+            ("elbow", joint_values.map(|j| j.elbow_deg.deg()), "deg"),
+            ("wrist", joint_values.map(|j| j.wrist_deg.deg()), "deg"),
+            ("gripper", joint_values.map(|j| j.gripper_mm.mm()), "mm"),
+            (
+                "rail",
+                joint_values.and_then(|j| j.rail_mm.map(|rail| rail.mm())),
+                "mm",
+            ),
+        ]);
+        let active = Style::new().fg(Color::Yellow);
+        let panel = |title: &'static str, mode: JogMode| {
+            let block = Block::bordered().title(title);
+            match self.mode == mode {
+                true => block.border_style(active),
+                false => block,
+            }
+        };
+        frame.render_widget(
+            Paragraph::new(cartesian_lines).block(panel(" Cartesian ", JogMode::Cartesian)),
+            cartesian,
+        );
+        frame.render_widget(
+            Paragraph::new(joint_lines).block(panel(" Joints ", JogMode::Joint)),
+            joints,
+        );
+
+        let items: Vec<ListItem> = self
+            .locations
+            .locations
+            .iter()
+            .map(|(name, waypoint)| {
+                ListItem::new(format!(
+                    "{:>3} {:<16} {:>8.1} {:>8.1} {:>8.1} {:>7.1}",
+                    waypoint.id,
+                    name,
+                    waypoint.x_mm.mm(),
+                    waypoint.y_mm.mm(),
+                    waypoint.z_mm.mm(),
+                    waypoint.orientation_deg.deg()
+                ))
+            })
+            .collect();
+        let title = match &self.file {
+            Some(path) => format!(" Locations - {} ", path.display()),
+            None => " Locations ".to_string(),
+        };
+        let mut list_state = ListState::default();
+        if !items.is_empty() {
+            list_state.select(Some(self.selected));
+        }
+        frame.render_stateful_widget(
+            List::new(items)
+                .block(Block::bordered().title(title))
+                .highlight_style(Style::new().reversed())
+                .highlight_symbol("> "),
+            locations,
+            &mut list_state,
+        );
+
+        let axes = match self.mode {
+            JogMode::Cartesian => "x y z yaw",
+            JogMode::Joint => "z shoulder elbow wrist gripper rail",
+        };
+        let help_lines = vec![
+            Line::from(format!(
+                "Jog {} mode, step {} [m] mode  [+/-] step",
+                self.mode,
+                self.step_label()
+            )),
+            Line::from(format!("[q/a w/s e/d r/f t/g y/h] {}", axes)),
+            Line::from("[o/c] gripper  [F] free  [P] power  [A] attach  [H] home  [Ctrl-C] quit"),
+            Line::from("[n] save pose  [Enter] move to  [x] remove  [Up/Down] select"),
+        ];
+        frame.render_widget(Paragraph::new(help_lines).block(Block::bordered()), help);
+        let halt_style = Style::new()
+            .fg(Color::White)
+            .bg(Color::Red)
+            .add_modifier(Modifier::BOLD);
+        frame.render_widget(
+            Paragraph::new(vec![
+                Line::from(""),
+                Line::from("HALT").centered(),
+                Line::from("[Space] [Esc]").centered(),
+            ])
+            .style(halt_style)
+            .block(Block::bordered()),
+            halt,
+        );
+
+        let message_line = match &self.naming {
+            Some(name) => Line::from(format!("Location name: {}_", name)).yellow(),
+            None if self.removing.is_some() => Line::from(self.message.as_str()).yellow(),
+            None if self.message.starts_with("error") => Line::from(self.message.as_str()).red(),
+            None => Line::from(self.message.as_str()),
+        };
+        frame.render_widget(Paragraph::new(message_line), message);
+    }
+}
+
+fn yes_no(value: bool) -> &'static str {
+    match value {
+        true => "yes",
+        false => "no",
+    }
+}
+
+fn on_off(value: bool) -> &'static str {
+    match value {
+        true => "on",
+        false => "off",
+    }
+}
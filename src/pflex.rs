@@ -1,9 +1,18 @@
 use crate::commands::TCSCommand;
-use crate::enums::ParamIDs;
+use crate::connection::SharedConnection;
+use crate::enums::{CartesianAxis, JogFrame, Joint, ParamIDs, RobotModel};
 use crate::error_codes::RobotError;
-use crate::structs::{EndEffectorPosition, MotionProfile, Waypoint};
+use crate::kinematics::{Handedness, Kinematics};
+use crate::structs::{
+    EndEffectorPosition, GripperPayloads, JogLimits, JogStep, JointPosition, MotionProfile,
+    Station, Waypoint,
+};
 use crate::tcs_client::TCSClient;
-use log::{debug, info};
+use crate::transforms::Transform;
+use crate::units::{Degrees, Mass, Millimetres, Percent, Seconds};
+use crate::workspace::Workspace;
+use log::{debug, info, warn};
+use std::collections::HashMap;
 
 /// Represents the rail on the robot
 /// # Fields
@@ -15,11 +24,27 @@ pub struct Rail {
 
 /// Containts the TCS client and the rail struct
 /// # Fields
-/// * `tcs_client` - A TCSClient instance
+/// * `connection` - Connection to the controller, which may be shared with other robots
+/// * `robot_index` - Index of the robot on the controller
 /// * `rail` - A Rail instance
+/// * `model` - The PreciseFlex model being driven
+/// * `gripper_payloads` - Payloads switched to when grasping or releasing a plate
+/// * `tool_frame` - Offset from the flange to the tool centre point
+/// * `frames` - Named user frames, relative to the world frame
+/// * `jog_limits` - Largest steps allowed by the jog commands
+/// * `workspace` - Zones and limits every outgoing motion command is checked against
+/// * `kinematics` - Offline kinematics used to check moves in both joint and Cartesian space
 pub struct PFlexRobot {
-    tcs_client: TCSClient,
+    connection: SharedConnection,
+    robot_index: i32,
     rail: Rail,
+    model: RobotModel,
+    gripper_payloads: Option<GripperPayloads>,
+    tool_frame: Transform,
+    frames: HashMap<String, Transform>,
+    jog_limits: JogLimits,
+    workspace: Option<Workspace>,
+    kinematics: Option<Kinematics>,
 }
 
 /// Creates a new PFlexRobot instance and panics if it cannot connect to the robot
@@ -27,10 +52,13 @@ pub struct PFlexRobot {
 /// * `ip` - A string slice that holds the IP address of the robot
 /// * `has_rail` - A boolean that indicates whether the robot has a rail
 impl PFlexRobot {
-    const DEFAULT_ROBOT_INDEX: i32 = 1;
-    pub const DEFAULT_EE_PITCH: f64 = 90.0; // Unless you plan on hitting your robot with a hammer...
-    pub const DEFAULT_EE_ROLL: f64 = -180.0; // ...then these should be constant throughout
+    pub const DEFAULT_EE_PITCH: Degrees = Degrees::from_deg(90.0); // Unless you plan on hitting your robot with a hammer...
+    pub const DEFAULT_EE_ROLL: Degrees = Degrees::from_deg(-180.0); // ...then these should be constant throughout
     const GRIPPER_JOINT_NUMBER: i32 = 5;
+    const FREE_MODE_ALL_JOINTS: i32 = 0;
+    const FREE_MODE_OFF: i32 = -1;
+    const ZERO_TORQUE_ALL_JOINTS: i32 = -1;
+    const MOTION_STATE_MOVING: &'static str = "2";
 
     /// Creates a new PFlexRobot instance
     /// # Arguments
@@ -39,31 +67,294 @@ impl PFlexRobot {
     /// # Returns
     /// * A PFlexRobot instance
     pub fn new(ip: &str, has_rail: bool) -> Self {
-        let mut tcs_client = TCSClient::new();
-        let is_connected = tcs_client.connect(ip, None);
-        if is_connected.is_err() {
+        Self::new_with_port(ip, TCSClient::TCS_SERVER_PORT, has_rail)
+    }
+
+    /// Creates a new PFlexRobot instance connected to a TCS server on a non-standard port
+    /// # Arguments
+    /// * `ip` - A string slice that holds the IP address of the robot
+    /// * `port` - Port the TCS server is listening on, e.g. the port of a MockRobot
+    /// * `has_rail` - A boolean that indicates whether the robot has a rail
+    /// # Returns
+    /// * A PFlexRobot instance
+    pub fn new_with_port(ip: &str, port: u16, has_rail: bool) -> Self {
+        let connection = match SharedConnection::connect(ip, port, None) {
+            Ok(connection) => connection,
             // todo: change this to be a return error
-            panic!("Could not connect to TCSClient");
-        }
+            Err(_) => panic!("Could not connect to TCSClient"),
+        };
+        Self::with_connection(connection, SharedConnection::DEFAULT_ROBOT_INDEX, has_rail)
+    }
 
+    /// Creates a PFlexRobot that sends its commands over a connection, which can be shared with
+    /// the other robots driven by the same controller
+    /// # Arguments
+    /// * `connection` - Connection to the controller
+    /// * `robot_index` - Index of the robot on the controller, starting at 1
+    /// * `has_rail` - A boolean that indicates whether the robot has a rail
+    /// # Returns
+    /// * A PFlexRobot instance
+    pub fn with_connection(connection: SharedConnection, robot_index: i32, has_rail: bool) -> Self {
         PFlexRobot {
-            tcs_client,
+            connection,
+            robot_index,
             rail: Rail { exists: has_rail },
+            model: RobotModel::default(),
+            gripper_payloads: None,
+            tool_frame: Transform::IDENTITY,
+            frames: HashMap::new(),
+            jog_limits: JogLimits::default(),
+            workspace: None,
+            kinematics: None,
+        }
+    }
+
+    /// Index of the robot on the controller
+    pub fn robot_index(&self) -> i32 {
+        self.robot_index
+    }
+
+    /// Whether the robot is mounted on a rail
+    pub fn has_rail(&self) -> bool {
+        self.rail.exists
+    }
+
+    /// Changes which robot on the controller the commands are carried out on
+    /// # Arguments
+    /// * `robot_index` - Index of the robot on the controller, starting at 1
+    pub fn set_robot_index(&mut self, robot_index: i32) {
+        self.robot_index = robot_index;
+    }
+
+    /// Connection to the controller, clone it to drive another robot over the same connection
+    pub fn connection(&self) -> &SharedConnection {
+        &self.connection
+    }
+
+    /// Gets the PreciseFlex model the robot is configured as
+    pub fn model(&self) -> RobotModel {
+        self.model
+    }
+
+    /// Sets the PreciseFlex model, used to validate payloads against the rated payload
+    /// # Arguments
+    /// * `model` - The model of the connected robot
+    pub fn set_model(&mut self, model: RobotModel) {
+        self.model = model;
+    }
+
+    /// Sets the payloads that are switched to automatically by `grasp_plate` and `release_plate`
+    /// # Arguments
+    /// * `payloads` - The empty and holding payloads, or None to stop switching payloads
+    pub fn set_gripper_payloads(&mut self, payloads: Option<GripperPayloads>) {
+        self.gripper_payloads = payloads;
+    }
+
+    /// Gets the offset from the flange to the tool centre point
+    pub fn tool_frame(&self) -> Transform {
+        self.tool_frame
+    }
+
+    /// Sets the offset from the flange to the tool centre point, used by poses given in a frame
+    /// # Arguments
+    /// * `tool_frame` - The tool offset, e.g. for a different set of fingers
+    pub fn set_tool_frame(&mut self, tool_frame: Transform) {
+        self.tool_frame = tool_frame;
+    }
+
+    /// Gets a named user frame
+    /// # Arguments
+    /// * `name` - Name of the frame
+    pub fn frame(&self, name: &str) -> Option<Transform> {
+        self.frames.get(name).copied()
+    }
+
+    /// Adds or replaces a named user frame
+    /// # Arguments
+    /// * `name` - Name of the frame, e.g. the instrument it belongs to
+    /// * `frame` - The frame relative to the world frame
+    pub fn set_frame(&mut self, name: &str, frame: Transform) {
+        self.frames.insert(name.to_string(), frame);
+    }
+
+    /// Removes a named user frame
+    /// # Arguments
+    /// * `name` - Name of the frame
+    pub fn remove_frame(&mut self, name: &str) -> Option<Transform> {
+        self.frames.remove(name)
+    }
+
+    /// Converts a tool pose in a named frame into the flange pose in the world frame
+    /// # Arguments
+    /// * `pose` - Pose of the tool centre point
+    /// * `frame` - Name of the frame the pose is in, or None for the world frame
+    pub fn to_world_pose(
+        &self,
+        pose: &EndEffectorPosition,
+        frame: Option<&str>,
+    ) -> Result<EndEffectorPosition, RobotError> {
+        let frame = self.resolve_frame(frame)?;
+        Ok((frame * Transform::from_pose(pose) * self.tool_frame.inverse()).to_pose())
+    }
+
+    /// Converts a flange pose in the world frame into a tool pose in a named frame
+    /// # Arguments
+    /// * `pose` - Pose of the flange in the world frame
+    /// * `frame` - Name of the frame to express the pose in, or None for the world frame
+    pub fn to_frame_pose(
+        &self,
+        pose: &EndEffectorPosition,
+        frame: Option<&str>,
+    ) -> Result<EndEffectorPosition, RobotError> {
+        let frame = self.resolve_frame(frame)?;
+        Ok((frame.inverse() * Transform::from_pose(pose) * self.tool_frame).to_pose())
+    }
+
+    fn resolve_frame(&self, frame: Option<&str>) -> Result<Transform, RobotError> {
+        match frame {
+            Some(name) => self.frame(name).ok_or(format!("Unknown frame: {}", name)),
+            None => Ok(Transform::IDENTITY),
+        }
+    }
+
+    /// Gets the largest steps allowed by the jog commands
+    pub fn jog_limits(&self) -> JogLimits {
+        self.jog_limits
+    }
+
+    /// Sets the largest steps allowed by the jog commands
+    /// # Arguments
+    /// * `jog_limits` - The new limits
+    pub fn set_jog_limits(&mut self, jog_limits: JogLimits) {
+        self.jog_limits = jog_limits;
+    }
+
+    /// Gets the workspace motion commands are checked against
+    pub fn workspace(&self) -> Option<&Workspace> {
+        self.workspace.as_ref()
+    }
+
+    /// Sets the workspace every outgoing motion command is checked against
+    /// # Arguments
+    /// * `workspace` - The workspace, or None to stop checking motion commands
+    pub fn set_workspace(&mut self, workspace: Option<Workspace>) {
+        self.workspace = workspace;
+    }
+
+    /// Gets the kinematics used to check moves
+    pub fn kinematics(&self) -> Option<Kinematics> {
+        self.kinematics
+    }
+
+    /// Sets the kinematics used to check moves
+    ///
+    /// With kinematics set, Cartesian moves are also checked against the joint limits and joint
+    /// moves are also checked against the Cartesian zones of the workspace.
+    /// # Arguments
+    /// * `kinematics` - The kinematics, or None to only check moves in the space they're given in
+    pub fn set_kinematics(&mut self, kinematics: Option<Kinematics>) {
+        self.kinematics = kinematics;
+    }
+
+    /// Checks a flange pose in the world frame, and the tool centre point, against the workspace
+    ///
+    /// With kinematics set, the pose is accepted if either elbow configuration reaches it within
+    /// the joint limits, as the controller keeps the configuration the arm is already in.
+    /// # Arguments
+    /// * `pose` - Pose of the flange in the world frame
+    /// * `rail_mm` - Rail position the pose is reached from, the joints of a robot with a rail
+    ///   are only checked when it's given
+    pub fn check_pose(
+        &self,
+        pose: &EndEffectorPosition,
+        rail_mm: Option<Millimetres>,
+    ) -> Result<(), RobotError> {
+        let Some(workspace) = &self.workspace else {
+            return Ok(());
+        };
+        workspace.check_pose(pose)?;
+        let tool = Transform::from_pose(pose) * self.tool_frame;
+        workspace.check_point(tool.translation)?;
+        let Some(kinematics) = self.kinematics else {
+            return Ok(());
+        };
+        if self.rail.exists && rail_mm.is_none() {
+            return Ok(());
+        }
+        let check = |handedness: Handedness| -> Result<(), RobotError> {
+            let joints = Kinematics {
+                handedness,
+                ..kinematics
+            }
+            .inverse(pose, rail_mm, Millimetres::ZERO)?;
+            workspace.check_joint(Joint::Z, joints.z_mm.mm())?;
+            workspace.check_joint(Joint::Shoulder, joints.shoulder_deg.deg())?;
+            workspace.check_joint(Joint::Elbow, joints.elbow_deg.deg())?;
+            workspace.check_joint(Joint::Wrist, joints.wrist_deg.deg())
+        };
+        let other = match kinematics.handedness {
+            Handedness::Lefty => Handedness::Righty,
+            Handedness::Righty => Handedness::Lefty,
+        };
+        match check(kinematics.handedness) {
+            Ok(()) => Ok(()),
+            Err(e) => check(other).map_err(|_| e),
         }
     }
 
+    /// Checks a joint position against the workspace joint limits
+    /// # Arguments
+    /// * `joints` - Target joint position
+    pub fn check_joints(&self, joints: &JointPosition) -> Result<(), RobotError> {
+        let Some(workspace) = &self.workspace else {
+            return Ok(());
+        };
+        workspace.check_joints(joints)?;
+        match &self.kinematics {
+            Some(kinematics) => self.check_pose(&kinematics.forward(joints), joints.rail_mm),
+            None => Ok(()),
+        }
+    }
+
+    /// Checks a single joint target against the workspace joint limits
+    /// # Arguments
+    /// * `joint` - The joint being moved
+    /// * `value` - Target value in millimetres or degrees
+    pub fn check_joint(&self, joint: Joint, value: f64) -> Result<(), RobotError> {
+        match &self.workspace {
+            Some(workspace) => workspace.check_joint(joint, value),
+            None => Ok(()),
+        }
+    }
+
+    // the rail position Cartesian targets are reached from, only read when the joints of a robot
+    // with a rail are going to be checked
+    fn checked_rail_position(&mut self) -> Result<Option<Millimetres>, RobotError> {
+        if !self.rail.exists || self.workspace.is_none() || self.kinematics.is_none() {
+            return Ok(None);
+        }
+        Ok(self.get_joint_positions()?.rail_mm)
+    }
+
     /// Polls the robot with a NoOp call to check the connection status
     /// # Returns
     /// * A boolean that indicates whether the connection is alive
     pub fn is_connection_alive(&mut self) -> bool {
         info!("is_connection_alive called");
-        let res = self
-            .tcs_client
-            .send_command(TCSCommand::NoOp, None, true, None);
+        let res = self.send_command(TCSCommand::NoOp, None, true, None);
+        // it'll throw a timeout error as it'll be waiting for a response that never comes
+        res.is_ok()
+    }
+
+    /// Checks if the robot power is enabled
+    /// # Returns
+    /// * A boolean that indicates whether power is enabled
+    pub fn is_robot_powered(&mut self) -> Result<bool, RobotError> {
+        info!("is_robot_powered called");
+        let res = self.send_command(TCSCommand::Power, None, true, None);
         match res {
-            Ok(_) => true,
-            // it'll throw a timeout error as it'll be waiting for a response that never comes
-            Err(_) => false,
+            Ok(data) => Ok(data.first().is_some_and(|state| state != "0")),
+            Err(e) => Err(e),
         }
     }
 
@@ -73,9 +364,7 @@ impl PFlexRobot {
     /// * A RobotError if the robot is not attached
     pub fn is_robot_attached(&mut self) -> Result<bool, RobotError> {
         info!("is_robot_attached called");
-        let res = self
-            .tcs_client
-            .send_command(TCSCommand::Attach, None, true, None);
+        let res = self.send_command(TCSCommand::Attach, None, true, None);
         match res {
             Ok(data) => {
                 if data[0] == "0" {
@@ -94,24 +383,49 @@ impl PFlexRobot {
     /// * A RobotError if the robot is not homed
     pub fn is_robot_home(&mut self) -> Result<bool, RobotError> {
         info!("is_robot_home called");
-        let res = self
-            .tcs_client
-            .send_command(TCSCommand::Home, None, true, None);
+        let res = self.get_home();
+        match res {
+            Ok(data) => Ok(data.first().is_some_and(|status| status == "1")),
+            Err(e) => Err(e),
+        }
+    }
+
+    /// Homes the robot, the robot has to be powered and attached
+    /// # Returns
+    /// * A RobotError if the robot could not be homed
+    pub fn home_robot(&mut self) -> Result<(), RobotError> {
+        info!("home_robot called");
+        let res = self.send_command(TCSCommand::Home, None, true, None);
         match res {
-            Ok(_) => Ok(true),
+            Ok(_) => Ok(()),
             Err(e) => Err(e),
         }
     }
 
+    /// Brings the robot up ready for motion, powering, attaching and homing it where needed
+    /// # Returns
+    /// * A RobotError from the first step that failed, e.g. power can't be enabled during an E-STOP
+    pub fn ensure_ready(&mut self) -> Result<(), RobotError> {
+        info!("ensure_ready called");
+        self.send_power(true)?;
+        if !self.is_robot_attached()? {
+            self.attach_robot()?;
+        }
+        if !self.is_robot_home()? {
+            self.home_robot()?;
+        }
+        Ok(())
+    }
+
     /// Attaches to the default robot
     /// # Returns
     /// * A RobotError if the robot cannot be attached
     /// * Ok if the robot is attached
     pub fn attach_robot(&mut self) -> Result<(), RobotError> {
         info!("attach_robot called");
-        let res = self.tcs_client.send_command(
+        let res = self.send_command(
             TCSCommand::Attach,
-            Some(vec![&Self::DEFAULT_ROBOT_INDEX.to_string()]),
+            Some(vec![&self.robot_index.to_string()]),
             true,
             None,
         );
@@ -121,14 +435,15 @@ impl PFlexRobot {
         }
     }
 
-    /// Selects the default robot
+    /// Selects the robot on the controller, commands for it select it automatically when another
+    /// robot sharing the connection was selected since
     /// * A RobotError if the robot cannot be selected
     /// * Ok if the robot is selected
     pub fn select_robot(&mut self) -> Result<(), RobotError> {
         info!("select_robot called");
-        let res = self.tcs_client.send_command(
+        let res = self.send_command(
             TCSCommand::Select,
-            Some(vec![Self::DEFAULT_ROBOT_INDEX.to_string().as_str()]),
+            Some(vec![self.robot_index.to_string().as_str()]),
             true,
             None,
         );
@@ -144,9 +459,7 @@ impl PFlexRobot {
         info!("create_waypoint called");
         let payload = waypoint.to_payload();
         let payload_ref = payload.iter().map(|s| s.as_str()).collect::<Vec<&str>>();
-        let res = self
-            .tcs_client
-            .send_command(TCSCommand::LocXyz, Some(payload_ref), true, None);
+        let res = self.send_command(TCSCommand::LocXyz, Some(payload_ref), true, None);
 
         match res {
             Ok(_) => Ok(()),
@@ -154,31 +467,73 @@ impl PFlexRobot {
         }
     }
 
-    pub fn create_station(self) {
+    /// Reads a location stored on the robot
+    /// # Arguments
+    /// * `location_id` - Location index to read
+    /// * `rail_position_mm` - Rail position to go with the location, the controller doesn't store it
+    pub fn get_waypoint(
+        &mut self,
+        location_id: i32,
+        rail_position_mm: Option<Millimetres>,
+    ) -> Result<Waypoint, RobotError> {
+        info!("get_waypoint called");
+        let location_id_str = location_id.to_string();
+        let res = self.send_command(TCSCommand::Loc, Some(vec![&location_id_str]), true, None);
+        match res {
+            Ok(data) => Waypoint::from_response(location_id, &data, rail_position_mm),
+            Err(e) => Err(e),
+        }
+    }
+
+    /// Stores the station location on the robot, converting it out of the station's frame
+    /// # Arguments
+    /// * `station` - The station to store
+    pub fn create_station(&mut self, station: &Station) -> Result<(), RobotError> {
         info!("create_station called");
-        unimplemented!()
+        debug!("creating station: {}", station.name);
+        let pose = self.to_world_pose(
+            &station.location.to_endeffector_position(),
+            station.frame.as_deref(),
+        )?;
+        let waypoint = pose.to_waypoint(station.location.id, station.location.rail_position_mm);
+        self.create_waypoint(waypoint)
     }
 
-    pub fn create_motion_profile(&mut self, profile: MotionProfile) {
+    pub fn create_motion_profile(&mut self, profile: MotionProfile) -> Result<(), RobotError> {
         // todo: test this
         info!("create_motion_profile called");
-        let ordered_args = profile.to_payload();
+        let ordered_args = profile.to_payload()?;
         let referenced_args = ordered_args
             .iter()
             .map(|x| x.as_str())
             .collect::<Vec<&str>>(); // todo: this is VERY hacky
-        let _res =
-            self.tcs_client
-                .send_command(TCSCommand::Profile, Some(referenced_args), false, None);
+        let res = self.send_command(TCSCommand::Profile, Some(referenced_args), true, None);
+        match res {
+            Ok(_) => Ok(()),
+            Err(e) => Err(e),
+        }
+    }
+
+    /// Reads a motion profile stored on the robot
+    /// # Arguments
+    /// * `profile_id` - Profile index to read
+    pub fn get_motion_profile(&mut self, profile_id: i32) -> Result<MotionProfile, RobotError> {
+        info!("get_motion_profile called");
+        let profile_id_str = profile_id.to_string();
+        let res = self.send_command(TCSCommand::Profile, Some(vec![&profile_id_str]), true, None);
+        match res {
+            Ok(data) => MotionProfile::from_response(profile_id, &data),
+            Err(e) => Err(e),
+        }
     }
 
     pub fn get_home(&mut self) -> Result<Vec<String>, RobotError> {
         info!("get_home called");
-        let res = self.tcs_client.send_command(
+        let res = self.send_command(
             TCSCommand::GetParam,
             Some(vec![
                 &ParamIDs::HomingStatus.to_string(),
-                Self::DEFAULT_ROBOT_INDEX.to_string().as_str(),
+                self.robot_index.to_string().as_str(),
                 "0",
                 "1",
             ]),
@@ -194,9 +549,7 @@ impl PFlexRobot {
 
     pub fn get_location(&mut self) -> Result<Vec<String>, RobotError> {
         info!("get_location called");
-        let res = self
-            .tcs_client
-            .send_command(TCSCommand::Loc, None, true, None);
+        let res = self.send_command(TCSCommand::Loc, None, true, None);
         match res {
             Ok(data) => Ok(data),
             Err(e) => Err(e),
@@ -205,55 +558,67 @@ impl PFlexRobot {
 
     pub fn get_endeffector_position(&mut self) -> Result<EndEffectorPosition, RobotError> {
         info!("get_current_position called");
-        let res = self
-            .tcs_client
-            .send_command(TCSCommand::GetLocCart, None, true, None);
+        let res = self.send_command(TCSCommand::GetLocCart, None, true, None);
 
         match res {
-            Ok(eepos) => {
-                if eepos.len() < 6 {
-                    panic!("Issue with fetching current position");
-                }
-                Ok(EndEffectorPosition {
-                    x_mm: eepos[0].to_string().parse::<f64>().unwrap(),
-                    y_mm: eepos[1].to_string().parse::<f64>().unwrap(),
-                    z_mm: eepos[2].to_string().parse::<f64>().unwrap(),
-                    yaw_mm: eepos[3].to_string().parse::<f64>().unwrap(),
-                    pitch_mm: eepos[4].to_string().parse::<f64>().unwrap(),
-                    roll_mm: eepos[5].to_string().parse::<f64>().unwrap(),
-                })
-            }
+            Ok(eepos) => EndEffectorPosition::from_response(&eepos),
             Err(e) => Err(e),
         }
     }
 
-    pub fn get_system_speed(&mut self) -> i32 {
+    /// Gets the system speed every motion profile is scaled by
+    pub fn get_system_speed(&mut self) -> Result<Percent, RobotError> {
         info!("get_system_speed called");
-        // todo: test this
-        let res = self
-            .tcs_client
-            .send_command(TCSCommand::SystemSpeed, None, true, None);
+        let res = self.send_command(TCSCommand::SystemSpeed, None, true, None);
         match res {
-            Ok(speed) => speed[0].to_string().parse::<i32>().unwrap(),
-            Err(_) => 0,
+            Ok(speed) => {
+                let speed = speed
+                    .first()
+                    .and_then(|speed| speed.parse::<f64>().ok())
+                    .ok_or(format!("Invalid system speed: {:?}", speed))?;
+                Percent::new(speed)
+            }
+            Err(e) => Err(e),
         }
     }
 
     pub fn get_all_joints(&mut self) -> Result<Vec<String>, String> {
         info!("get_all_joints called");
-        let res = self
-            .tcs_client
-            .send_command(TCSCommand::GetLocJoints, None, true, None);
+        let res = self.send_command(TCSCommand::GetLocJoints, None, true, None);
         match res {
             Ok(r) => Ok(r),
             Err(e) => Err(e),
         }
     }
 
+    /// Gets the current tool pose expressed in a named frame
+    /// # Arguments
+    /// * `frame` - Name of the frame, or None for the world frame
+    pub fn get_endeffector_position_in_frame(
+        &mut self,
+        frame: Option<&str>,
+    ) -> Result<EndEffectorPosition, RobotError> {
+        info!("get_endeffector_position_in_frame called");
+        let pose = self.get_endeffector_position()?;
+        self.to_frame_pose(&pose, frame)
+    }
+
+    /// Gets the current joint positions of the robot
+    /// # Returns
+    /// * A JointPosition parsed from the `wherej` response
+    pub fn get_joint_positions(&mut self) -> Result<JointPosition, RobotError> {
+        info!("get_joint_positions called");
+        let res = self.send_command(TCSCommand::GetLocJoints, None, true, None);
+        match res {
+            Ok(joints) => JointPosition::from_response(&joints),
+            Err(e) => Err(e),
+        }
+    }
+
     pub fn get_previous_error(&mut self) -> Result<Vec<String>, RobotError> {
         // todo: test this
         info!("get_previous_error called");
-        let res = self.tcs_client.send_command(
+        let res = self.send_command(
             TCSCommand::GetParam,
             Some(vec![&ParamIDs::LastError.to_string()]),
             true,
@@ -267,33 +632,47 @@ impl PFlexRobot {
 
     pub fn get_motion_state(&mut self) -> Result<Vec<String>, RobotError> {
         info!("get_motion_state called");
-        let res = self
-            .tcs_client
-            .send_command(TCSCommand::MotionState, None, true, None);
+        let res = self.send_command(TCSCommand::MotionState, None, true, None);
         match res {
             Ok(data) => Ok(data),
             Err(e) => Err(e),
         }
     }
 
-    pub fn set_system_speed(&mut self, speed: i32) {
+    /// Checks whether the robot is carrying out a motion, without waiting for it to finish
+    pub fn is_moving(&mut self) -> Result<bool, RobotError> {
+        info!("is_moving called");
+        let state = self.get_motion_state()?;
+        Ok(state.first().map(String::as_str) == Some(Self::MOTION_STATE_MOVING))
+    }
+
+    /// Sets the system speed every motion profile is scaled by
+    /// # Arguments
+    /// * `speed` - Percentage of full speed
+    pub fn set_system_speed(&mut self, speed: Percent) -> Result<(), RobotError> {
         // todo: test this
         info!("set_system_speed called");
-        // i'll see myself out...
-        let _res = self.tcs_client.send_command(
+        let res = self.send_command(
             TCSCommand::SystemSpeed,
-            Some(vec![&speed.to_string()]),
-            false,
+            Some(vec![&speed.value().to_string()]),
+            true,
             None,
         );
+        match res {
+            Ok(_) => Ok(()),
+            Err(e) => Err(e),
+        }
     }
 
-    pub fn set_payload(&mut self, payload: i32) -> Result<(), RobotError> {
-        // todo: test this
+    /// Sets the payload held by the robot so motion uses the correct dynamics
+    /// # Arguments
+    /// * `payload` - Mass held by the gripper, must not exceed the rated payload of the model
+    pub fn set_payload(&mut self, payload: Mass) -> Result<(), RobotError> {
         info!("set_payload called");
-        let res = self.tcs_client.send_command(
+        let percent = self.model.payload_percent(payload)?;
+        let res = self.send_command(
             TCSCommand::Payload,
-            Some(vec![payload.to_string().as_str()]),
+            Some(vec![percent.to_string().as_str()]),
             true,
             None,
         );
@@ -303,61 +682,195 @@ impl PFlexRobot {
         }
     }
 
+    /// Gets the payload the robot is currently configured for
+    pub fn get_payload(&mut self) -> Result<Mass, RobotError> {
+        info!("get_payload called");
+        let res = self.send_command(TCSCommand::Payload, None, true, None);
+        match res {
+            Ok(data) => {
+                let percent = data
+                    .first()
+                    .and_then(|value| value.parse::<f64>().ok())
+                    .ok_or(format!("Unexpected payload response: {:?}", data))?;
+                Ok(self.model.payload_from_percent(percent))
+            }
+            Err(e) => Err(e),
+        }
+    }
+
+    /// Switches free mode, so the arm can be guided by hand
+    ///
+    /// Only `Joint::HORIZONTAL` is released, Z keeps holding the arm up. Use
+    /// `set_free_mode_joints` to release Z as well.
+    /// # Arguments
+    /// * `free_on` - Whether to release the horizontal joints or drive every joint again
     pub fn set_free_mode(&mut self, free_on: bool) -> Result<(), RobotError> {
         info!("set_free_mode called");
-        // todo: you CAN do this by joint but I'm not sure if that's necessary
-        let true_vec = vec!["0"];
-        let false_vec = vec!["-1"];
         if free_on {
-            let res = self.tcs_client.send_command(
+            self.set_free_mode_joints(&Joint::HORIZONTAL, false)
+        } else {
+            let res = self.send_command(
                 TCSCommand::FreeMode,
-                Some(true_vec),
-                false, // I have ZERO idea why this request doesn't give you a response back
+                Some(vec![&Self::FREE_MODE_OFF.to_string()]),
+                true,
                 None,
             );
             match res {
                 Ok(_) => Ok(()),
                 Err(code) => Err(code),
             }
-        } else {
-            let res =
-                self.tcs_client
-                    .send_command(TCSCommand::FreeMode, Some(false_vec), true, None);
-            match res {
-                Ok(_) => Ok(()),
-                Err(code) => Err(code),
+        }
+    }
+
+    /// Puts only the selected joints into free mode, every other joint keeps holding position
+    ///
+    /// Releasing `Joint::Z` lets the arm drop under gravity, so it is refused unless
+    /// `allow_gravity_loaded` is set. Use `Joint::HORIZONTAL` to swing the arm while keeping the
+    /// column locked.
+    /// # Arguments
+    /// * `joints` - Joints to release, every joint is released if empty
+    /// * `allow_gravity_loaded` - Whether joints that fall under gravity may be released
+    pub fn set_free_mode_joints(
+        &mut self,
+        joints: &[Joint],
+        allow_gravity_loaded: bool,
+    ) -> Result<(), RobotError> {
+        info!("set_free_mode_joints called");
+        // an empty list releases every joint, Z included
+        if !allow_gravity_loaded
+            && (joints.is_empty() || joints.iter().any(Joint::is_gravity_loaded))
+        {
+            return Err(
+                "Releasing the Z joint lets the arm fall under gravity, allow gravity loaded joints to release it"
+                    .to_string(),
+            );
+        }
+        if joints.is_empty() {
+            warn!("releasing every joint in free mode, Z will fall under gravity");
+            return self.send_free_mode(Self::FREE_MODE_ALL_JOINTS);
+        }
+        for joint in joints {
+            if joint.is_gravity_loaded() {
+                warn!(
+                    "releasing joint {} in free mode, it will fall under gravity",
+                    joint
+                );
             }
+            self.send_free_mode(joint.value())?;
+        }
+        Ok(())
+    }
+
+    /// Enables or disables zero-torque (compliance) mode on the selected joints
+    ///
+    /// Only available on controller firmware that supports zero-torque mode, the controller's
+    /// error is returned otherwise.
+    /// # Arguments
+    /// * `joints` - Joints to make compliant, every joint is used if empty
+    /// * `enabled` - Whether zero-torque mode is switched on or off
+    pub fn set_zero_torque(&mut self, joints: &[Joint], enabled: bool) -> Result<(), RobotError> {
+        info!("set_zero_torque called");
+        let axis_mask = if joints.is_empty() {
+            Self::ZERO_TORQUE_ALL_JOINTS
+        } else {
+            joints
+                .iter()
+                .fold(0, |mask, joint| mask | (1 << (joint.value() - 1)))
+        };
+        let enable = if enabled { "1" } else { "0" };
+        let res = self.send_command(
+            TCSCommand::ZeroTorque,
+            Some(vec![enable, &axis_mask.to_string()]),
+            true,
+            None,
+        );
+        match res {
+            Ok(_) => Ok(()),
+            Err(e) => Err(e),
+        }
+    }
+
+    fn send_free_mode(&mut self, axis: i32) -> Result<(), RobotError> {
+        let res = self.send_command(
+            TCSCommand::FreeMode,
+            Some(vec![&axis.to_string()]),
+            false, // I have ZERO idea why this request doesn't give you a response back
+            None,
+        );
+        match res {
+            Ok(_) => Ok(()),
+            Err(code) => Err(code),
+        }
+    }
+
+    /// Reads a digital input or output signal
+    /// # Arguments
+    /// * `signal` - Signal number
+    pub fn get_signal(&mut self, signal: i32) -> Result<bool, RobotError> {
+        info!("get_signal called");
+        let res = self.send_command(
+            TCSCommand::Signal,
+            Some(vec![&signal.to_string()]),
+            true,
+            None,
+        );
+        match res {
+            // the state is the last value, some firmware echoes the signal number before it
+            Ok(data) => data
+                .iter()
+                .rev()
+                .find(|value| !value.is_empty())
+                .and_then(|value| value.parse::<i32>().ok())
+                .map(|value| value != 0)
+                .ok_or(format!("Unexpected signal response: {:?}", data)),
+            Err(e) => Err(e),
+        }
+    }
+
+    /// Sets a digital output signal
+    /// # Arguments
+    /// * `signal` - Signal number
+    /// * `state` - Whether the signal is switched on
+    pub fn set_signal(&mut self, signal: i32, state: bool) -> Result<(), RobotError> {
+        info!("set_signal called");
+        let res = self.send_command(
+            TCSCommand::Signal,
+            Some(vec![&signal.to_string(), if state { "1" } else { "0" }]),
+            true,
+            None,
+        );
+        match res {
+            Ok(_) => Ok(()),
+            Err(e) => Err(e),
         }
     }
 
     pub fn set_power(&mut self, power: bool) {
         info!("set_power called");
-        match power {
-            true => {
-                let result =
-                    self.tcs_client
-                        .send_command(TCSCommand::Power, Some(vec!["1"]), true, None);
-                if let Err(e) = result {
-                    // todo: add in error return
-                    debug!("Error setting power: {}", e);
-                }
-            }
-            false => {
-                let result =
-                    self.tcs_client
-                        .send_command(TCSCommand::Power, Some(vec!["0"]), true, None);
-                if let Err(e) = result {
-                    // todo: add in error return
-                    debug!("Error setting power: {}", e);
-                }
-            }
+        if let Err(e) = self.send_power(power) {
+            // todo: add in error return
+            debug!("Error setting power: {}", e);
+        }
+    }
+
+    /// Switches the robot power on or off, unlike `set_power` the controller's error is returned,
+    /// e.g. power can't be enabled during an E-STOP
+    /// # Arguments
+    /// * `power` - Whether power is switched on
+    pub fn send_power(&mut self, power: bool) -> Result<(), RobotError> {
+        info!("send_power called");
+        let state = if power { "1" } else { "0" };
+        let res = self.send_command(TCSCommand::Power, Some(vec![state]), true, None);
+        match res {
+            Ok(_) => Ok(()),
+            Err(e) => Err(e),
         }
     }
 
     pub fn set_mode(&mut self, verbose_tcs: bool) -> Result<(), RobotError> {
         // todo: test this
         info!("set_mode called");
-        let res = self.tcs_client.send_command(
+        let res = self.send_command(
             TCSCommand::Mode,
             Some(vec![{
                 if verbose_tcs {
@@ -375,33 +888,102 @@ impl PFlexRobot {
         }
     }
 
-    pub fn move_gripper(&mut self, target_state: f64, motion_profile_id: i32) {
+    pub fn move_gripper(
+        &mut self,
+        target_state: Millimetres,
+        motion_profile_id: i32,
+    ) -> Result<(), RobotError> {
         // todo: test this
         info!("move_gripper called");
-        let payload = vec![
+        self.check_joint(Joint::Gripper, target_state.mm())?;
+        let payload = [
             PFlexRobot::GRIPPER_JOINT_NUMBER.to_string(),
-            target_state.to_string(),
+            target_state.mm().to_string(),
             motion_profile_id.to_string(),
         ];
         let payload_ref = payload.iter().map(|s| s.as_str()).collect::<Vec<&str>>();
-        let res =
-            self.tcs_client
-                .send_command(TCSCommand::MoveOneAxis, Some(payload_ref), true, None);
-        if res.is_err() {
-            // todo: add in error return
-            debug!("Err on gripper: {}", res.unwrap_err());
+        let res = self.send_command(TCSCommand::MoveOneAxis, Some(payload_ref), true, None);
+        match res {
+            Ok(_) => Ok(()),
+            Err(e) => Err(e),
+        }
+    }
+
+    /// Closes the gripper on a plate, switching to the holding payload if a plate was grasped
+    /// # Arguments
+    /// * `plate_width_mm` - Width of the plate being grasped
+    /// * `finger_speed_percent` - Speed the fingers close at
+    /// * `grasp_force_percent` - Force the plate is held with
+    /// # Returns
+    /// * A boolean that indicates whether a plate was grasped
+    pub fn grasp_plate(
+        &mut self,
+        plate_width_mm: Millimetres,
+        finger_speed_percent: Percent,
+        grasp_force_percent: Percent,
+    ) -> Result<bool, RobotError> {
+        info!("grasp_plate called");
+        let res = self.send_command(
+            TCSCommand::GraspPlate,
+            Some(vec![
+                &plate_width_mm.mm().to_string(),
+                &finger_speed_percent.value().to_string(),
+                &grasp_force_percent.value().to_string(),
+            ]),
+            true,
+            None,
+        );
+        // the controller replies with -1 when a plate is detected between the fingers
+        let grasped = match res {
+            Ok(data) => data.first().is_some_and(|value| value == "-1"),
+            Err(e) => return Err(e),
+        };
+        if let Some(payloads) = self.gripper_payloads {
+            let payload = if grasped {
+                payloads.holding
+            } else {
+                payloads.empty
+            };
+            self.set_payload(payload)?;
         }
+        Ok(grasped)
     }
 
-    pub fn move_rail(&mut self, position: f64) -> Result<(), RobotError> {
+    /// Opens the gripper to release a plate, switching to the empty payload
+    /// # Arguments
+    /// * `open_width_mm` - Width to open the fingers to
+    /// * `finger_speed_percent` - Speed the fingers open at
+    pub fn release_plate(
+        &mut self,
+        open_width_mm: Millimetres,
+        finger_speed_percent: Percent,
+    ) -> Result<(), RobotError> {
+        info!("release_plate called");
+        self.send_command(
+            TCSCommand::ReleasePlate,
+            Some(vec![
+                &open_width_mm.mm().to_string(),
+                &finger_speed_percent.value().to_string(),
+            ]),
+            true,
+            None,
+        )?;
+        if let Some(payloads) = self.gripper_payloads {
+            self.set_payload(payloads.empty)?;
+        }
+        Ok(())
+    }
+
+    pub fn move_rail(&mut self, position: Millimetres) -> Result<(), RobotError> {
         // todo: test this
         info!("move_rail called");
         if !self.rail.exists {
             panic!("No rail exists");
         }
-        let res = self.tcs_client.send_command(
+        self.check_joint(Joint::Rail, position.mm())?;
+        let res = self.send_command(
             TCSCommand::MoveRail,
-            Some(vec!["1", "1", &position.to_string()]),
+            Some(vec!["1", "1", &position.mm().to_string()]),
             true,
             None,
         );
@@ -418,49 +1000,171 @@ impl PFlexRobot {
     ) -> Result<(), RobotError> {
         // todo: test this
         info!("move_to_position called");
+        let rail_mm = self.checked_rail_position()?;
+        self.check_pose(&ee_position, rail_mm)?;
         let mut payload = ee_position.to_payload();
         payload.insert(0, format!("{}", motion_profile_id)); // lazy but it works...
         let payload_ref = payload.iter().map(|s| s.as_str()).collect::<Vec<&str>>();
-        let res =
-            self.tcs_client
-                .send_command(TCSCommand::MoveToCart, Some(payload_ref), true, None);
+        let res = self.send_command(TCSCommand::MoveToCart, Some(payload_ref), true, None);
         match res {
             Ok(_) => Ok(()),
             Err(e) => Err(e),
         }
     }
 
-    pub fn move_to_waypoint(&mut self, waypoint_id: i32, motion_profile_id: i32) {
-        // todo: test this
+    /// Moves the tool centre point to a pose given in a named frame
+    /// # Arguments
+    /// * `ee_position` - Pose of the tool centre point in the frame
+    /// * `frame` - Name of the frame, or None for the world frame
+    /// * `motion_profile_id` - Motion profile used for the move
+    pub fn move_to_cartesian_in_frame(
+        &mut self,
+        ee_position: EndEffectorPosition,
+        frame: Option<&str>,
+        motion_profile_id: i32,
+    ) -> Result<(), RobotError> {
+        info!("move_to_cartesian_in_frame called");
+        let world_pose = self.to_world_pose(&ee_position, frame)?;
+        self.move_to_cartesian(world_pose, motion_profile_id)
+    }
+
+    pub fn move_to_waypoint(
+        &mut self,
+        waypoint_id: i32,
+        motion_profile_id: i32,
+    ) -> Result<(), RobotError> {
         info!("move_to_waypoint called");
-        let _res = self.tcs_client.send_command(
+        // the location is stored on the controller, so read it back to check it
+        if self.workspace.is_some() {
+            let waypoint = self.get_waypoint(waypoint_id, None)?;
+            let rail_mm = self.checked_rail_position()?;
+            self.check_pose(&waypoint.to_endeffector_position(), rail_mm)?;
+        }
+        let res = self.send_command(
             TCSCommand::Move,
             Some(vec![
                 &waypoint_id.to_string(),
                 &motion_profile_id.to_string(),
             ]),
-            false,
+            true,
             None,
         );
+        match res {
+            Ok(_) => Ok(()),
+            Err(e) => Err(e),
+        }
     }
 
+    /// Sends a raw `movej` request
+    /// # Arguments
+    /// * `joint_config` - The motion profile followed by the target of each joint
     pub fn move_to_joints(&mut self, joint_config: Vec<&str>) -> Result<(), RobotError> {
         // todo: test this
         info!("move_to_joints called");
-        let res =
-            self.tcs_client
-                .send_command(TCSCommand::MoveToJoints, Some(joint_config), false, None);
+        if self.workspace.is_some() {
+            let joints = joint_config
+                .iter()
+                .skip(1)
+                .map(|joint| joint.to_string())
+                .collect::<Vec<String>>();
+            self.check_joints(&JointPosition::from_response(&joints)?)?;
+        }
+        let res = self.send_command(TCSCommand::MoveToJoints, Some(joint_config), true, None);
         match res {
             Ok(_) => Ok(()),
             Err(e) => Err(e),
         }
     }
 
-    pub fn wait_until_static(&mut self, max_timeout_s: f64) -> Result<(), RobotError> {
+    /// Moves every joint to the given position
+    /// # Arguments
+    /// * `joints` - The target joint position
+    /// * `motion_profile_id` - Motion profile used for the move
+    pub fn move_to_joint_position(
+        &mut self,
+        joints: &JointPosition,
+        motion_profile_id: i32,
+    ) -> Result<(), RobotError> {
+        info!("move_to_joint_position called");
+        self.check_joints(joints)?;
+        let mut payload = joints.to_payload();
+        payload.insert(0, motion_profile_id.to_string());
+        let payload_ref = payload.iter().map(|s| s.as_str()).collect::<Vec<&str>>();
+        let res = self.send_command(TCSCommand::MoveToJoints, Some(payload_ref), true, None);
+        match res {
+            Ok(_) => Ok(()),
+            Err(e) => Err(e),
+        }
+    }
+
+    /// Moves the tool by a small step along or about a Cartesian axis
+    /// # Arguments
+    /// * `axis` - Axis to jog along, or about for rotations
+    /// * `step` - The step, a distance along the axis or an angle for rotations
+    /// * `frame` - Frame the axis belongs to
+    /// * `motion_profile_id` - Motion profile used for the move
+    pub fn jog_cartesian(
+        &mut self,
+        axis: CartesianAxis,
+        step: JogStep,
+        frame: &JogFrame,
+        motion_profile_id: i32,
+    ) -> Result<(), RobotError> {
+        info!("jog_cartesian called");
+        if step.is_linear() == axis.is_rotation() {
+            return Err(format!("Can't jog {:?} by {}", axis, step));
+        }
+        self.jog_limits.check(step)?;
+        let delta = step.value();
+        let flange = Transform::from_pose(&self.get_endeffector_position()?);
+        let tool = flange * self.tool_frame;
+        let step = match axis {
+            CartesianAxis::X => Transform::from_translation(delta, 0.0, 0.0),
+            CartesianAxis::Y => Transform::from_translation(0.0, delta, 0.0),
+            CartesianAxis::Z => Transform::from_translation(0.0, 0.0, delta),
+            CartesianAxis::Yaw => Transform::from_xyz_yaw(0.0, 0.0, 0.0, delta),
+        };
+        let target = match frame {
+            JogFrame::Tool => tool * step,
+            JogFrame::World => jog_in_frame(Transform::IDENTITY, tool, step),
+            JogFrame::User(name) => jog_in_frame(self.resolve_frame(Some(name))?, tool, step),
+        };
+        let target = (target * self.tool_frame.inverse()).to_pose();
+        self.move_to_cartesian(target, motion_profile_id)
+    }
+
+    /// Moves a single joint by a small step
+    /// # Arguments
+    /// * `joint` - Joint to jog
+    /// * `step` - The step, a distance for linear joints or an angle for rotary joints
+    /// * `motion_profile_id` - Motion profile used for the move
+    pub fn jog_joint(
+        &mut self,
+        joint: Joint,
+        step: JogStep,
+        motion_profile_id: i32,
+    ) -> Result<(), RobotError> {
+        info!("jog_joint called");
+        self.jog_limits.check(step)?;
+        let mut target = self.get_joint_positions()?;
+        match (joint, step) {
+            (Joint::Z, JogStep::Linear(step)) => target.z_mm += step,
+            (Joint::Shoulder, JogStep::Angular(step)) => target.shoulder_deg += step,
+            (Joint::Elbow, JogStep::Angular(step)) => target.elbow_deg += step,
+            (Joint::Wrist, JogStep::Angular(step)) => target.wrist_deg += step,
+            (Joint::Gripper, JogStep::Linear(step)) => target.gripper_mm += step,
+            (Joint::Rail, JogStep::Linear(step)) => match target.rail_mm.as_mut() {
+                Some(rail_mm) => *rail_mm += step,
+                None => return Err("No rail exists".to_string()),
+            },
+            (joint, step) => return Err(format!("Can't jog joint {} by {}", joint, step)),
+        }
+        self.move_to_joint_position(&target, motion_profile_id)
+    }
+
+    pub fn wait_until_static(&mut self, max_timeout: Seconds) -> Result<(), RobotError> {
         info!("wait_until_static called");
-        let res =
-            self.tcs_client
-                .send_command(TCSCommand::WaitForEOM, None, true, Some(max_timeout_s));
+        let res = self.send_command(TCSCommand::WaitForEOM, None, true, Some(max_timeout.secs()));
         match res {
             Ok(_) => Ok(()),
             Err(e) => Err(e),
@@ -470,35 +1174,66 @@ impl PFlexRobot {
     pub fn halt(&mut self) -> Result<(), RobotError> {
         // todo: test this
         info!("halt called");
-        let res = self
-            .tcs_client
-            .send_command(TCSCommand::Halt, None, false, None);
+        let res = self.send_command(TCSCommand::Halt, None, true, None);
         match res {
             Ok(_) => Ok(()),
             Err(e) => Err(e),
         }
     }
 
-    /// Cleanly disconnects from the robot
+    /// Sends a request written out in full, for commands this API doesn't wrap
+    /// # Arguments
+    /// * `request` - The command and its arguments, e.g. `pd 2800 1 0 1`
+    /// # Returns
+    /// * The reply after the response code
+    pub fn send_raw(&mut self, request: &str) -> Result<Vec<String>, RobotError> {
+        info!("send_raw called");
+        self.connection.send_raw(self.robot_index, request, None)
+    }
+
+    /// Cleanly disconnects from the robot, leaving the connection open if another robot is still
+    /// using it
     pub fn disconnect(&mut self) {
         // this is also in the Drop trait because I really couldn't be bothered to remember...
         info!("disconnect called");
-        if self.tcs_client.socket.is_none() {
+        if !self.connection.is_connected() {
             debug!("Socket is already dead...");
             return;
         }
-        let _res = self
-            .tcs_client
-            .send_command(TCSCommand::Exit, None, false, None);
-        let _kill = self.tcs_client.disconnect();
+        if self.connection.is_shared() {
+            debug!("Connection is still in use by another robot");
+            return;
+        }
+        let _kill = self.connection.disconnect();
+    }
+
+    fn send_command(
+        &mut self,
+        command: TCSCommand,
+        command_args: Option<Vec<&str>>,
+        wait_for_response: bool,
+        read_timeout: Option<f64>,
+    ) -> Result<Vec<String>, RobotError> {
+        self.connection.send_command(
+            self.robot_index,
+            command,
+            command_args,
+            wait_for_response,
+            read_timeout,
+        )
     }
 }
 
+/// Applies a jog step expressed in `frame` to a tool pose, rotating about the tool centre point
+fn jog_in_frame(frame: Transform, tool: Transform, step: Transform) -> Transform {
+    let tool_in_frame = frame.inverse() * tool;
+    let position = tool_in_frame.translation;
+    let to_tool = Transform::from_translation(position[0], position[1], position[2]);
+    frame * to_tool * step * to_tool.inverse() * tool_in_frame
+}
+
 impl Drop for PFlexRobot {
     fn drop(&mut self) {
-        if self.tcs_client.socket.is_none() {
-            return;
-        }
         self.disconnect();
     }
 }
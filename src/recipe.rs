@@ -0,0 +1,406 @@
+use crate::error_codes::RobotError;
+use crate::pflex::PFlexRobot;
+use crate::sequence::{
+    MotionSequence, MotionStep, SequenceControl, SequenceError, SequenceProgress,
+};
+use crate::structs::{MotionProfile, Station, Waypoint};
+use crate::units::{Degrees, Millimetres, Percent, Seconds};
+use log::info;
+use serde::Deserialize;
+use std::collections::HashMap;
+use std::fs;
+use std::path::Path;
+use toml::Spanned;
+
+/// Motion profile as written in a recipe file, missing fields use the MotionProfile defaults
+#[derive(Debug, Deserialize)]
+#[serde(deny_unknown_fields)]
+struct RecipeProfile {
+    id: i32,
+    speed_percent: Option<Percent>,
+    speed2_percent: Option<Percent>,
+    accel_percent: Option<Percent>,
+    decel_percent: Option<Percent>,
+    accel_ramp_s: Option<Seconds>,
+    decel_ramp_s: Option<Seconds>,
+    in_range: Option<f64>,
+    straight_line: Option<bool>,
+}
+
+/// Station as written in a recipe file
+#[derive(Debug, Deserialize)]
+#[serde(deny_unknown_fields)]
+struct RecipeStation {
+    name: String,
+    id: i32,
+    x_mm: Millimetres,
+    y_mm: Millimetres,
+    z_mm: Millimetres,
+    yaw_deg: Degrees,
+    rail_mm: Option<Millimetres>,
+    approach_height_mm: Option<Millimetres>,
+    frame: Option<String>,
+}
+
+/// Step as written in a recipe file, exactly one action has to be given
+#[derive(Debug, Default, Deserialize)]
+#[serde(deny_unknown_fields)]
+struct RecipeStep {
+    #[serde(rename = "move")]
+    move_to: Option<String>,
+    move_location: Option<i32>,
+    gripper_mm: Option<Millimetres>,
+    wait_signal: Option<i32>,
+    set_signal: Option<i32>,
+    state: Option<bool>,
+    timeout_s: Option<Seconds>,
+    delay_s: Option<Seconds>,
+    speed: Option<Percent>,
+    profile: Option<i32>,
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(deny_unknown_fields)]
+struct RecipeFile {
+    name: String,
+    default_profile: Option<i32>,
+    #[serde(default)]
+    profiles: Vec<Spanned<RecipeProfile>>,
+    #[serde(default)]
+    stations: Vec<Spanned<RecipeStation>>,
+    #[serde(default)]
+    steps: Vec<Spanned<RecipeStep>>,
+}
+
+/// A robot task defined in a TOML recipe file
+///
+/// ```toml
+/// name = "plate transfer"
+/// default_profile = 1
+///
+/// [[profiles]]
+/// id = 1
+/// speed_percent = 40
+///
+/// [[stations]]
+/// name = "reader"
+/// id = 10
+/// x_mm = 400.0
+/// y_mm = -250.0
+/// z_mm = 180.0
+/// yaw_deg = 90.0
+///
+/// [[steps]]
+/// gripper_mm = 120.0
+///
+/// [[steps]]
+/// move = "reader"
+///
+/// [[steps]]
+/// wait_signal = 10001
+/// state = true
+/// timeout_s = 5.0
+/// ```
+///
+/// Steps take one of `move` (station name), `move_location` (location index), `gripper_mm`,
+/// `wait_signal` (with `state` and `timeout_s`), `set_signal` (with `state`), `delay_s` or
+/// `speed`. Moves use `profile` if given, or the recipe's `default_profile`.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Recipe {
+    pub name: String,
+    pub profiles: Vec<MotionProfile>,
+    pub stations: Vec<Station>,
+    pub sequence: MotionSequence,
+    step_lines: Vec<usize>,
+}
+
+impl Recipe {
+    const DEFAULT_PROFILE_ID: i32 = 1;
+    const DEFAULT_SIGNAL_TIMEOUT: Seconds = Seconds::from_millis(10_000);
+
+    /// Loads a recipe from a TOML file
+    /// # Arguments
+    /// * `path` - Path to the recipe file
+    pub fn load<P: AsRef<Path>>(path: P) -> Result<Self, RobotError> {
+        let contents = fs::read_to_string(path.as_ref())
+            .map_err(|e| format!("Failed to read {}: {}", path.as_ref().display(), e))?;
+        Self::parse(&contents)
+    }
+
+    /// Parses and validates a recipe
+    /// # Arguments
+    /// * `contents` - The recipe as TOML
+    /// # Returns
+    /// * A RobotError starting with the line the problem was found on
+    pub fn parse(contents: &str) -> Result<Self, RobotError> {
+        let file: RecipeFile = toml::from_str(contents).map_err(|e| {
+            let line = e.span().map_or(1, |span| line_of(contents, span.start));
+            format!("line {}: {}", line, e.message())
+        })?;
+        let default_profile = file.default_profile.unwrap_or(Self::DEFAULT_PROFILE_ID);
+
+        let mut profiles = Vec::new();
+        for profile in &file.profiles {
+            let line = line_of(contents, profile.span().start);
+            let profile = profile.get_ref();
+            if profiles.iter().any(|p: &MotionProfile| p.id == profile.id) {
+                return Err(format!("line {}: duplicate profile {}", line, profile.id));
+            }
+            let profile = profile
+                .to_motion_profile()
+                .map_err(|e| format!("line {}: {}", line, e))?;
+            profiles.push(profile);
+        }
+
+        let mut stations: Vec<Station> = Vec::new();
+        for station in &file.stations {
+            let line = line_of(contents, station.span().start);
+            let station = station.get_ref();
+            if stations.iter().any(|s| s.name == station.name) {
+                return Err(format!("line {}: duplicate station {}", line, station.name));
+            }
+            // a second station at the same location would overwrite the first on the controller
+            if let Some(other) = stations.iter().find(|s| s.location.id == station.id) {
+                return Err(format!(
+                    "line {}: station {} has the same id {} as station {}",
+                    line, station.name, station.id, other.name
+                ));
+            }
+            stations.push(station.to_station());
+        }
+        let station_ids = stations
+            .iter()
+            .map(|station| (station.name.as_str(), station.location.id))
+            .collect::<HashMap<&str, i32>>();
+
+        let mut sequence = MotionSequence::new();
+        let mut step_lines = Vec::new();
+        for step in &file.steps {
+            let line = line_of(contents, step.span().start);
+            let motion_step = step
+                .get_ref()
+                .to_motion_step(&station_ids, default_profile)
+                .and_then(|motion_step| motion_step.check().map(|_| motion_step))
+                .map_err(|e| format!("line {}: {}", line, e))?;
+            if let Some(profile_id) = profile_of(&motion_step) {
+                let known = profiles.is_empty() || profiles.iter().any(|p| p.id == profile_id);
+                if !known {
+                    return Err(format!("line {}: unknown profile {}", line, profile_id));
+                }
+            }
+            sequence = sequence.then(motion_step);
+            step_lines.push(line);
+        }
+
+        Ok(Recipe {
+            name: file.name,
+            profiles,
+            stations,
+            sequence,
+            step_lines,
+        })
+    }
+
+    /// Line of the recipe file a step was defined on
+    /// # Arguments
+    /// * `step` - Index of the step
+    pub fn step_line(&self, step: usize) -> Option<usize> {
+        self.step_lines.get(step).copied()
+    }
+
+    /// Validates the recipe and describes what it would do, without sending anything
+    /// # Arguments
+    /// * `robot` - Robot whose workspace the steps are checked against, if one is connected
+    /// # Returns
+    /// * One line per action, or a RobotError naming the line of the step that failed
+    pub fn dry_run(&self, robot: Option<&PFlexRobot>) -> Result<Vec<String>, RobotError> {
+        info!("Recipe::dry_run called");
+        if let Some(robot) = robot {
+            self.sequence
+                .validate(robot)
+                .map_err(|e| self.describe_error(&e))?;
+        }
+        let mut plan = Vec::new();
+        for profile in &self.profiles {
+            plan.push(format!("create profile {}: {:?}", profile.id, profile));
+        }
+        for station in &self.stations {
+            plan.push(format!(
+                "create station {} at location {}",
+                station.name, station.location.id
+            ));
+        }
+        for (step, motion_step) in self.sequence.steps().iter().enumerate() {
+            plan.push(format!(
+                "step {} (line {}): {:?}",
+                step, self.step_lines[step], motion_step
+            ));
+        }
+        Ok(plan)
+    }
+
+    /// Creates the recipe's profiles and stations on the robot then executes its steps
+    /// # Arguments
+    /// * `robot` - The robot to run the recipe on
+    /// * `control` - Handle used to pause, resume or abort the recipe
+    /// * `progress` - Called as steps start and finish
+    pub fn execute<F>(
+        &self,
+        robot: &mut PFlexRobot,
+        control: &SequenceControl,
+        progress: F,
+    ) -> Result<(), RobotError>
+    where
+        F: FnMut(SequenceProgress),
+    {
+        info!("Recipe::execute called");
+        self.sequence
+            .validate(robot)
+            .map_err(|e| self.describe_error(&e))?;
+        for profile in &self.profiles {
+            robot.create_motion_profile(profile.clone())?;
+        }
+        for station in &self.stations {
+            robot.create_station(station)?;
+        }
+        self.sequence
+            .execute(robot, control, progress)
+            .map_err(|e| self.describe_error(&e))
+    }
+
+    fn describe_error(&self, error: &SequenceError) -> RobotError {
+        match self.step_line(error.step) {
+            Some(line) => format!("line {}: {}", line, error),
+            None => error.to_string(),
+        }
+    }
+}
+
+impl RecipeProfile {
+    fn to_motion_profile(&self) -> Result<MotionProfile, RobotError> {
+        let mut builder = MotionProfile::builder(self.id);
+        if let Some(speed) = self.speed_percent {
+            builder = builder.speed(speed);
+        }
+        if let Some(speed2) = self.speed2_percent {
+            builder = builder.speed2(speed2);
+        }
+        if let Some(accel) = self.accel_percent {
+            builder = builder.accel(accel);
+        }
+        if let Some(decel) = self.decel_percent {
+            builder = builder.decel(decel);
+        }
+        if let Some(ramp) = self.accel_ramp_s {
+            builder = builder.accel_ramp(ramp);
+        }
+        if let Some(ramp) = self.decel_ramp_s {
+            builder = builder.decel_ramp(ramp);
+        }
+        if let Some(in_range) = self.in_range {
+            builder = builder.in_range(in_range);
+        }
+        if let Some(straight_line) = self.straight_line {
+            builder = builder.straight_line(straight_line);
+        }
+        builder.build()
+    }
+}
+
+impl RecipeStation {
+    fn to_station(&self) -> Station {
+        let location = Waypoint::new(
+            self.id,
+            self.x_mm,
+            self.y_mm,
+            self.z_mm,
+            self.yaw_deg,
+            self.rail_mm,
+        );
+        Station {
+            approach_height_mm: self
+                .approach_height_mm
+                .unwrap_or(Station::DEFAULT_APPROACH_HEIGHT_MM),
+            frame: self.frame.clone(),
+            ..Station::new(&self.name, location)
+        }
+    }
+}
+
+impl RecipeStep {
+    fn to_motion_step(
+        &self,
+        station_ids: &HashMap<&str, i32>,
+        default_profile: i32,
+    ) -> Result<MotionStep, RobotError> {
+        let motion_profile_id = self.profile.unwrap_or(default_profile);
+        let mut actions = Vec::new();
+        if let Some(station) = &self.move_to {
+            let waypoint_id = *station_ids
+                .get(station.as_str())
+                .ok_or(format!("unknown station {}", station))?;
+            actions.push(MotionStep::MoveToWaypoint {
+                waypoint_id,
+                motion_profile_id,
+            });
+        }
+        if let Some(waypoint_id) = self.move_location {
+            actions.push(MotionStep::MoveToWaypoint {
+                waypoint_id,
+                motion_profile_id,
+            });
+        }
+        if let Some(width_mm) = self.gripper_mm {
+            actions.push(MotionStep::Gripper {
+                width_mm,
+                motion_profile_id,
+            });
+        }
+        if let Some(signal) = self.wait_signal {
+            let timeout = self.timeout_s.unwrap_or(Recipe::DEFAULT_SIGNAL_TIMEOUT);
+            actions.push(MotionStep::WaitForSignal {
+                signal,
+                state: self.state.unwrap_or(true),
+                timeout: timeout.to_duration()?,
+            });
+        }
+        if let Some(signal) = self.set_signal {
+            let state = self.state.ok_or("set_signal needs a state")?;
+            actions.push(MotionStep::SetSignal { signal, state });
+        }
+        if let Some(delay_s) = self.delay_s {
+            actions.push(MotionStep::Delay(delay_s.to_duration()?));
+        }
+        if let Some(speed) = self.speed {
+            actions.push(MotionStep::SetSpeed(speed));
+        }
+        match actions.len() {
+            1 => Ok(actions.remove(0)),
+            0 => Err("step has no action".to_string()),
+            _ => Err("step has more than one action".to_string()),
+        }
+    }
+}
+
+fn profile_of(motion_step: &MotionStep) -> Option<i32> {
+    match motion_step {
+        MotionStep::MoveToWaypoint {
+            motion_profile_id, ..
+        }
+        | MotionStep::MoveToCartesian {
+            motion_profile_id, ..
+        }
+        | MotionStep::MoveToJoints {
+            motion_profile_id, ..
+        }
+        | MotionStep::Gripper {
+            motion_profile_id, ..
+        } => Some(*motion_profile_id),
+        _ => None,
+    }
+}
+
+/// Line number, counting from 1, of a byte offset into the file
+fn line_of(contents: &str, offset: usize) -> usize {
+    contents[..offset.min(contents.len())].matches('\n').count() + 1
+}
@@ -0,0 +1,584 @@
+// Interactive TCS shell for commissioning
+//
+// Requests are typed the way they are sent, e.g. `wherec` or `hp 1`, and the reply is printed
+// decoded, with the description of its response code. Lines starting with `:` control the shell
+// and `@name` runs a macro:
+//
+// tcs> :def ready hp 1; attach 1; home
+// tcs> @ready
+// tcs> wherec
+// x: 350
+// ...
+//
+// Macros files hold one macro per line, `name = request; request`. Blank lines and anything after
+// a `#` are ignored. The `tcs_repl` binary adds history and tab completion through ReplHelper.
+
+use crate::args::{parse_arg, parse_timeout};
+use crate::commands::TCSCommand;
+use crate::error_codes::{ResponseCodes, RobotError};
+use crate::tcs_client::TCSClient;
+use log::info;
+use rustyline::completion::Completer;
+use rustyline::highlight::Highlighter;
+use rustyline::hint::Hinter;
+use rustyline::validate::Validator;
+use rustyline::{Context, Helper};
+use std::borrow::Cow;
+use std::collections::BTreeMap;
+use std::fs;
+use std::path::{Path, PathBuf};
+use strum::IntoEnumIterator;
+
+/// Connection settings and files used by the shell
+#[derive(Debug, Clone, PartialEq)]
+pub struct ReplOptions {
+    /// IP address of the controller
+    pub host: String,
+    /// Port the TCS server is listening on
+    pub port: u16,
+    /// Timeout for every socket read and write in seconds
+    pub timeout: Option<f64>,
+    /// Macros file loaded on start
+    pub macros: Option<PathBuf>,
+    /// File the line history is kept in, None to keep no history
+    pub history: Option<PathBuf>,
+}
+
+impl ReplOptions {
+    /// Help printed for `--help`
+    pub const USAGE: &'static str = "\
+usage: tcs_repl [options]
+options:
+  --host <ip>           controller address, defaults to the ROBOT_IP environment variable
+  --port <port>         TCS server port
+  --timeout <seconds>   timeout for every socket read and write
+  --macros <path>       macros file loaded on start
+  --history <path>      file the line history is kept in, defaults to ~/.pflex_tcs_history
+  --no-history          don't keep a history";
+
+    /// Name of the history file in the home directory
+    pub const HISTORY_FILE: &'static str = ".pflex_tcs_history";
+
+    /// Builds the options from command line flags, see `USAGE`
+    /// # Arguments
+    /// * `args` - The flags, not including the program name
+    pub fn from_args<I: IntoIterator<Item = String>>(args: I) -> Result<Self, RobotError> {
+        let mut options = ReplOptions {
+            host: "127.0.0.1".to_string(),
+            port: TCSClient::TCS_SERVER_PORT,
+            timeout: None,
+            macros: None,
+            history: std::env::var_os("HOME").map(|home| Path::new(&home).join(Self::HISTORY_FILE)),
+        };
+        let mut args = args.into_iter();
+        while let Some(arg) = args.next() {
+            let mut value = || args.next().ok_or(format!("{} needs a value", arg));
+            match arg.as_str() {
+                "--host" => options.host = value()?,
+                "--port" => options.port = parse_arg(&arg, &value()?)?,
+                "--timeout" => options.timeout = Some(parse_timeout(&arg, &value()?)?),
+                "--macros" => options.macros = Some(PathBuf::from(value()?)),
+                "--history" => options.history = Some(PathBuf::from(value()?)),
+                "--no-history" => options.history = None,
+                _ => return Err(format!("Unrecognised option: {}\n{}", arg, Self::USAGE)),
+            }
+        }
+        Ok(options)
+    }
+
+    /// Connects to the controller the options point at
+    pub fn connect(&self) -> Result<TCSClient, RobotError> {
+        info!("ReplOptions::connect called");
+        let mut client = TCSClient::new();
+        client
+            .connect_with_port(&self.host, self.port, self.timeout)
+            .map_err(|e| format!("Failed to connect to {}:{}: {}", self.host, self.port, e))?;
+        Ok(client)
+    }
+}
+
+/// What the shell should do after a line
+#[derive(Debug, Clone, PartialEq)]
+pub enum ReplOutcome {
+    /// Print the text and read the next line
+    Continue(String),
+    /// Print the text and leave the shell
+    Quit(String),
+}
+
+/// Shell over a TCS connection
+#[derive(Debug)]
+pub struct Repl {
+    client: TCSClient,
+    macros: BTreeMap<String, Vec<String>>,
+}
+
+impl Repl {
+    /// Prompt shown before every line
+    pub const PROMPT: &'static str = "tcs> ";
+    /// Commands controlling the shell, with their arguments and description
+    pub const META_COMMANDS: [(&'static str, &'static str, &'static str); 7] = [
+        (":help", "[command]", "Lists the commands, or describes one"),
+        (":def", "<name> <request>; ...", "Defines a macro"),
+        (":undef", "<name>", "Removes a macro"),
+        (":macros", "", "Lists the macros"),
+        (":save", "<path>", "Writes the macros to a file"),
+        (":load", "<path>", "Reads macros from a file"),
+        (":quit", "", "Leaves the shell"),
+    ];
+    // how deep macros may run other macros, so one running itself stops
+    const MAX_MACRO_DEPTH: usize = 8;
+    // how long `waitForEOM` waits for the robot to stop, in seconds
+    const MOTION_TIMEOUT_SECONDS: f64 = 60.0;
+
+    /// Creates a shell over a connected client
+    /// # Arguments
+    /// * `client` - The connection requests are sent on
+    pub fn new(client: TCSClient) -> Self {
+        Repl {
+            client,
+            macros: BTreeMap::new(),
+        }
+    }
+
+    /// Macros by name, each a list of lines
+    pub fn macros(&self) -> &BTreeMap<String, Vec<String>> {
+        &self.macros
+    }
+
+    /// Defines a macro, replacing any with the same name
+    /// # Arguments
+    /// * `name` - Letters, digits, `_` and `-`
+    /// * `lines` - The lines run in order, requests, meta commands or other macros
+    pub fn define_macro(&mut self, name: &str, lines: Vec<String>) -> Result<(), RobotError> {
+        check_macro(name, &lines)?;
+        self.macros.insert(name.to_string(), lines);
+        Ok(())
+    }
+
+    /// Reads macros from a file, replacing those with the same names
+    /// # Arguments
+    /// * `path` - The macros file
+    /// # Returns
+    /// * The number of macros read
+    pub fn load_macros<P: AsRef<Path>>(&mut self, path: P) -> Result<usize, RobotError> {
+        info!("Repl::load_macros called");
+        let path = path.as_ref();
+        let contents = fs::read_to_string(path)
+            .map_err(|e| format!("Failed to read {}: {}", path.display(), e))?;
+        let macros = Self::parse_macros(&contents)?;
+        let count = macros.len();
+        self.macros.extend(macros);
+        Ok(count)
+    }
+
+    /// Writes every macro to a file
+    /// # Arguments
+    /// * `path` - The macros file, replaced if it exists
+    pub fn save_macros<P: AsRef<Path>>(&self, path: P) -> Result<(), RobotError> {
+        info!("Repl::save_macros called");
+        let path = path.as_ref();
+        let contents: String = self
+            .macros
+            .iter()
+            .map(|(name, lines)| format!("{} = {}\n", name, lines.join("; ")))
+            .collect();
+        fs::write(path, contents).map_err(|e| format!("Failed to write {}: {}", path.display(), e))
+    }
+
+    /// Parses the contents of a macros file
+    /// # Arguments
+    /// * `contents` - One `name = request; request` per line
+    pub fn parse_macros(contents: &str) -> Result<BTreeMap<String, Vec<String>>, RobotError> {
+        let mut macros = BTreeMap::new();
+        for (number, line) in contents.lines().enumerate() {
+            let line = line.split('#').next().unwrap_or_default().trim();
+            if line.is_empty() {
+                continue;
+            }
+            let (name, body) = line
+                .split_once('=')
+                .ok_or(format!("line {}: expected name = request; ...", number + 1))?;
+            let (name, lines) = (name.trim(), split_lines(body));
+            check_macro(name, &lines).map_err(|e| format!("line {}: {}", number + 1, e))?;
+            macros.insert(name.to_string(), lines);
+        }
+        Ok(macros)
+    }
+
+    /// Runs a line typed at the prompt
+    /// # Arguments
+    /// * `line` - A TCS request, a meta command starting with `:` or a macro starting with `@`
+    pub fn eval(&mut self, line: &str) -> ReplOutcome {
+        let mut output = Vec::new();
+        let quit = self
+            .run(line.trim(), 0, &mut output)
+            .unwrap_or_else(|quit| quit);
+        let text = output.join("\n");
+        if quit {
+            ReplOutcome::Quit(text)
+        } else {
+            ReplOutcome::Continue(text)
+        }
+    }
+
+    // runs a line, Ok(quit) when it worked, Err(quit) to stop the macro running it
+    fn run(&mut self, line: &str, depth: usize, output: &mut Vec<String>) -> Result<bool, bool> {
+        if line.is_empty() {
+            return Ok(false);
+        }
+        if let Some(name) = line.strip_prefix('@') {
+            let Some(lines) = self.macros.get(name.trim()).cloned() else {
+                output.push(format!("error: no macro named {}", name.trim()));
+                return Err(false);
+            };
+            if depth >= Self::MAX_MACRO_DEPTH {
+                output.push(format!("error: macros nested deeper than {}", depth));
+                return Err(false);
+            }
+            for line in lines {
+                output.push(format!("{}{}", Self::PROMPT, line));
+                if self.run(&line, depth + 1, output)? {
+                    return Ok(true);
+                }
+            }
+            return Ok(false);
+        }
+        if line.starts_with(':') {
+            return self.meta(line, output).map(|_| line == ":quit");
+        }
+
+        let mut words = line.split_whitespace();
+        let command = words.next().and_then(TCSCommand::from_mnemonic);
+        // the controller doesn't reply when free mode is switched on, only to `freemode -1`
+        let wait_for_response =
+            command != Some(TCSCommand::FreeMode) || words.next().is_none_or(|axis| axis == "-1");
+        // `waitForEOM` only replies once the robot has stopped
+        let read_timeout =
+            (command == Some(TCSCommand::WaitForEOM)).then_some(Self::MOTION_TIMEOUT_SECONDS);
+        let reply = self
+            .client
+            .send_raw_unchecked(line, wait_for_response, read_timeout);
+        // the controller closes the connection after `exit`
+        let quit = command == Some(TCSCommand::Exit);
+        match reply {
+            Ok(_) if !wait_for_response => {
+                output.push("sent, the controller doesn't reply".to_string());
+                Ok(quit)
+            }
+            Ok(reply) => {
+                let failed = reply
+                    .first()
+                    .and_then(|code| code.parse::<i32>().ok())
+                    .is_none_or(|code| code < 0);
+                output.push(describe_reply(command.as_ref(), &reply));
+                if failed {
+                    Err(quit)
+                } else {
+                    Ok(quit)
+                }
+            }
+            Err(e) => {
+                output.push(format!("error: {}", e));
+                Err(quit)
+            }
+        }
+    }
+
+    fn meta(&mut self, line: &str, output: &mut Vec<String>) -> Result<(), bool> {
+        let (command, rest) = line.split_once(char::is_whitespace).unwrap_or((line, ""));
+        let rest = rest.trim();
+        let result = match (command, rest) {
+            (":help", "") => Ok(help()),
+            (":help", name) => help_for(name),
+            (":def", body) => match body.split_once(char::is_whitespace) {
+                Some((name, lines)) => self
+                    .define_macro(name, split_lines(lines))
+                    .map(|_| format!("defined @{}", name)),
+                None => Err("usage: :def <name> <request>; ...".to_string()),
+            },
+            (":undef", name) => match self.macros.remove(name) {
+                Some(_) => Ok(format!("removed @{}", name)),
+                None => Err(format!("no macro named {}", name)),
+            },
+            (":macros", "") if self.macros.is_empty() => Ok("no macros defined".to_string()),
+            (":macros", "") => Ok(self
+                .macros
+                .iter()
+                .map(|(name, lines)| format!("@{} = {}", name, lines.join("; ")))
+                .collect::<Vec<String>>()
+                .join("\n")),
+            (":save", path) if !path.is_empty() => self
+                .save_macros(path)
+                .map(|_| format!("saved {} macros to {}", self.macros.len(), path)),
+            (":load", path) if !path.is_empty() => self
+                .load_macros(path)
+                .map(|count| format!("loaded {} macros from {}", count, path)),
+            (":quit", "") => Ok(String::new()),
+            _ => Err(format!("unrecognised command: {} (try :help)", line)),
+        };
+        match result {
+            Ok(text) => {
+                if !text.is_empty() {
+                    output.push(text);
+                }
+                Ok(())
+            }
+            Err(e) => {
+                output.push(format!("error: {}", e));
+                Err(false)
+            }
+        }
+    }
+
+    /// Closes the connection
+    pub fn disconnect(&mut self) {
+        self.client.disconnect().ok();
+    }
+}
+
+/// Formats a reply for reading, labelling the values of replies with a known layout
+/// # Arguments
+/// * `command` - The command the reply is to, if known
+/// * `reply` - The response code followed by the rest of the reply
+pub fn describe_reply(command: Option<&TCSCommand>, reply: &[String]) -> String {
+    let Some((code, data)) = reply.split_first() else {
+        return "no reply".to_string();
+    };
+    let data: Vec<&str> = data
+        .iter()
+        .map(String::as_str)
+        .filter(|value| !value.is_empty())
+        .collect();
+    let Ok(value) = code.parse::<i32>() else {
+        return format!("unrecognised reply: {}", reply.join(" "));
+    };
+    if value < 0 {
+        let description = match ResponseCodes::from_value(value) {
+            Some(known) => known.description().to_string(),
+            None if data.is_empty() => "unknown error".to_string(),
+            None => data.join(" "),
+        };
+        return format!("error {}: {}", value, description);
+    }
+    let status = match ResponseCodes::from_value(value) {
+        Some(ResponseCodes::Success) => "ok".to_string(),
+        Some(ResponseCodes::Warning) => "warning".to_string(),
+        _ => format!("code {}", value),
+    };
+    let labels = command.map_or(&[][..], reply_fields);
+    if data.is_empty() {
+        status
+    } else if !labels.is_empty() && data.len() <= labels.len() {
+        labels
+            .iter()
+            .zip(&data)
+            .map(|(label, value)| format!("{}: {}", label, value))
+            .collect::<Vec<String>>()
+            .join("\n")
+    } else if value == 0 {
+        data.join(" ")
+    } else {
+        format!("{}: {}", status, data.join(" "))
+    }
+}
+
+/// Completions for the word before the cursor
+/// # Arguments
+/// * `line` - The line being typed
+/// * `pos` - Position of the cursor in the line
+/// * `macro_names` - Names of the macros that can be completed after `@`
+/// # Returns
+/// * Where the completed word starts, and the candidates for it
+pub fn complete(line: &str, pos: usize, macro_names: &[String]) -> (usize, Vec<String>) {
+    let before = &line[..pos];
+    let start = before
+        .rfind(char::is_whitespace)
+        .map_or(0, |index| index + 1);
+    let word = &before[start..];
+    let first_word = before[..start].trim().is_empty();
+    let matches = |candidate: &str| candidate.to_lowercase().starts_with(&word.to_lowercase());
+    let candidates: Vec<String> = if first_word && word.starts_with(':') {
+        Repl::META_COMMANDS
+            .iter()
+            .map(|(name, _, _)| name.to_string())
+            .filter(|name| matches(name))
+            .collect()
+    } else if word.starts_with('@') {
+        macro_names
+            .iter()
+            .map(|name| format!("@{}", name))
+            .filter(|name| matches(name))
+            .collect()
+    } else if first_word || before.trim_start().starts_with(":help") {
+        let mut mnemonics: Vec<String> = TCSCommand::iter()
+            .map(|command| command.to_string())
+            .filter(|mnemonic| matches(mnemonic))
+            .collect();
+        mnemonics.sort();
+        mnemonics
+    } else {
+        Vec::new()
+    };
+    (start, candidates)
+}
+
+/// Hint shown after the cursor at the end of a line, the rest of the command being typed and the
+/// arguments it takes
+/// # Arguments
+/// * `line` - The line being typed
+pub fn hint(line: &str) -> Option<String> {
+    let arguments = |command: &TCSCommand| -> Option<String> {
+        Some(command.arguments().to_string()).filter(|arguments| !arguments.is_empty())
+    };
+    let (word, rest) = line.split_once(' ').unwrap_or((line, ""));
+    if word.is_empty() || word.starts_with([':', '@']) {
+        return None;
+    }
+    if let Some(command) = TCSCommand::from_mnemonic(word) {
+        return match rest {
+            _ if line == word => arguments(&command).map(|arguments| format!(" {}", arguments)),
+            "" => arguments(&command),
+            _ => None,
+        };
+    }
+    if line.contains(' ') {
+        return None;
+    }
+    let mut matching = TCSCommand::iter().filter(|command| command.to_string().starts_with(word));
+    let command = matching.next()?;
+    if matching.next().is_some() {
+        return None;
+    }
+    let rest = command.to_string()[word.len()..].to_string();
+    Some(match arguments(&command) {
+        Some(arguments) => format!("{} {}", rest, arguments),
+        None => rest,
+    })
+}
+
+/// Line editor helper completing commands and macros and hinting at arguments
+#[derive(Debug, Default)]
+pub struct ReplHelper {
+    macro_names: Vec<String>,
+}
+
+impl ReplHelper {
+    /// Updates the macros offered after `@`
+    /// # Arguments
+    /// * `repl` - The shell whose macros are completed
+    pub fn update_macros(&mut self, repl: &Repl) {
+        self.macro_names = repl.macros().keys().cloned().collect();
+    }
+}
+
+impl Completer for ReplHelper {
+    type Candidate = String;
+
+    fn complete(
+        &self,
+        line: &str,
+        pos: usize,
+        _ctx: &Context<'_>,
+    ) -> rustyline::Result<(usize, Vec<String>)> {
+        Ok(complete(line, pos, &self.macro_names))
+    }
+}
+
+impl Hinter for ReplHelper {
+    type Hint = String;
+
+    fn hint(&self, line: &str, pos: usize, _ctx: &Context<'_>) -> Option<String> {
+        if pos < line.len() {
+            return None;
+        }
+        hint(line)
+    }
+}
+
+impl Highlighter for ReplHelper {
+    fn highlight_hint<'h>(&self, hint: &'h str) -> Cow<'h, str> {
+        // dimmed
+        Cow::Owned(format!("\x1b[2m{}\x1b[0m", hint))
+    }
+}
+
+impl Validator for ReplHelper {}
+
+impl Helper for ReplHelper {}
+
+fn help() -> String {
+    let mut lines = vec!["requests:".to_string()];
+    for command in TCSCommand::iter() {
+        let usage = format!("{} {}", command, command.arguments());
+        lines.push(format!(
+            "  {:<60} {}",
+            usage.trim_end(),
+            command.description()
+        ));
+    }
+    lines.push("shell:".to_string());
+    for (name, arguments, description) in Repl::META_COMMANDS {
+        let usage = format!("{} {}", name, arguments);
+        lines.push(format!("  {:<60} {}", usage.trim_end(), description));
+    }
+    lines.push(format!("  {:<60} {}", "@<name>", "Runs a macro"));
+    lines.join("\n")
+}
+
+fn help_for(name: &str) -> Result<String, RobotError> {
+    if let Some((name, arguments, description)) = Repl::META_COMMANDS
+        .iter()
+        .find(|(meta, _, _)| *meta == name || meta[1..] == *name)
+    {
+        return Ok(format!("{} {}\n  {}", name, arguments, description).replace(" \n", "\n"));
+    }
+    let command = TCSCommand::from_mnemonic(name).ok_or(format!("unknown command: {}", name))?;
+    Ok(format!(
+        "{} {}\n  {}",
+        command,
+        command.arguments(),
+        command.description()
+    )
+    .replace(" \n", "\n"))
+}
+
+// names of the values in the replies to commands that read something
+fn reply_fields(command: &TCSCommand) -> &'static [&'static str] {
+    match command {
+        TCSCommand::GetLocCart => &["x", "y", "z", "yaw", "pitch", "roll"],
+        TCSCommand::GetLocJoints => &["z", "shoulder", "elbow", "wrist", "gripper", "rail"],
+        TCSCommand::Mode => &["verbose"],
+        TCSCommand::Power => &["power"],
+        TCSCommand::Select => &["robot"],
+        TCSCommand::Attach => &["attached"],
+        TCSCommand::MotionState => &["state"],
+        TCSCommand::SystemSpeed => &["speed"],
+        TCSCommand::Payload => &["payload"],
+        TCSCommand::Rail => &["rail"],
+        TCSCommand::GraspPlate => &["grasped"],
+        _ => &[],
+    }
+}
+
+fn check_macro(name: &str, lines: &[String]) -> Result<(), RobotError> {
+    if name.is_empty()
+        || !name
+            .chars()
+            .all(|c| c.is_ascii_alphanumeric() || c == '_' || c == '-')
+    {
+        return Err(format!("Invalid macro name: {:?}", name));
+    }
+    if lines.is_empty() {
+        return Err(format!("Macro {} has no lines", name));
+    }
+    Ok(())
+}
+
+fn split_lines(body: &str) -> Vec<String> {
+    body.split(';')
+        .map(str::trim)
+        .filter(|line| !line.is_empty())
+        .map(str::to_string)
+        .collect()
+}
@@ -0,0 +1,446 @@
+use crate::enums::Joint;
+use crate::error_codes::RobotError;
+use crate::pflex::PFlexRobot;
+use crate::structs::{EndEffectorPosition, JointPosition};
+use crate::units::{Millimetres, Percent, Seconds};
+use log::{debug, info};
+use std::fmt;
+use std::sync::{Arc, Condvar, Mutex};
+use std::thread;
+use std::time::{Duration, Instant};
+
+/// A single step of a MotionSequence
+#[derive(Debug, Clone, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum MotionStep {
+    /// Move to a location stored on the robot
+    MoveToWaypoint {
+        waypoint_id: i32,
+        motion_profile_id: i32,
+    },
+    /// Move to a Cartesian pose in the world frame
+    MoveToCartesian {
+        position: EndEffectorPosition,
+        motion_profile_id: i32,
+    },
+    /// Move every joint to a joint position
+    MoveToJoints {
+        joints: JointPosition,
+        motion_profile_id: i32,
+    },
+    /// Move the gripper to a width
+    Gripper {
+        width_mm: Millimetres,
+        motion_profile_id: i32,
+    },
+    /// Wait for a signal to reach a state
+    WaitForSignal {
+        signal: i32,
+        state: bool,
+        timeout: Duration,
+    },
+    /// Switch an output signal on or off
+    SetSignal { signal: i32, state: bool },
+    /// Pause for a fixed time
+    Delay(Duration),
+    /// Change the system speed percentage
+    SetSpeed(Percent),
+}
+
+impl MotionStep {
+    /// Whether the step starts a motion that has to finish before the next step
+    pub fn is_motion(&self) -> bool {
+        matches!(
+            self,
+            MotionStep::MoveToWaypoint { .. }
+                | MotionStep::MoveToCartesian { .. }
+                | MotionStep::MoveToJoints { .. }
+                | MotionStep::Gripper { .. }
+        )
+    }
+
+    /// Checks the values of the step on their own, without a robot or workspace
+    pub fn check(&self) -> Result<(), RobotError> {
+        match self {
+            MotionStep::Gripper { width_mm, .. } if *width_mm < Millimetres::ZERO => {
+                Err(format!("Invalid gripper width: {}", width_mm))
+            }
+            MotionStep::WaitForSignal { timeout, .. } if timeout.is_zero() => {
+                Err("Signal wait has no timeout".to_string())
+            }
+            MotionStep::SetSpeed(speed) if speed.value() < 1.0 => {
+                Err(format!("Invalid system speed: {}", speed))
+            }
+            _ => Ok(()),
+        }
+    }
+
+    /// Checks the step without sending anything to the robot
+    /// # Arguments
+    /// * `robot` - Robot whose workspace the step is checked against
+    pub fn validate(&self, robot: &PFlexRobot) -> Result<(), RobotError> {
+        self.check()?;
+        match self {
+            // the rail position is only known once the move is sent
+            MotionStep::MoveToCartesian { position, .. } => robot.check_pose(position, None),
+            MotionStep::MoveToJoints { joints, .. } => robot.check_joints(joints),
+            MotionStep::Gripper { width_mm, .. } => {
+                robot.check_joint(Joint::Gripper, width_mm.mm())
+            }
+            _ => Ok(()),
+        }
+    }
+}
+
+/// Error raised while validating or executing a MotionSequence
+/// # Fields
+/// * `step` - Index of the step that failed
+/// * `error` - The error raised by the step
+#[derive(Debug, Clone, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct SequenceError {
+    pub step: usize,
+    pub error: RobotError,
+}
+
+impl fmt::Display for SequenceError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "step {}: {}", self.step, self.error)
+    }
+}
+
+/// Progress reported while a MotionSequence executes
+#[derive(Debug, Clone, Copy, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum SequenceProgress {
+    /// The step at the index has started
+    Started(usize),
+    /// The step at the index has finished
+    Completed(usize),
+    /// Execution has been paused before the step at the index
+    Paused(usize),
+    /// Execution has resumed before the step at the index
+    Resumed(usize),
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum ControlState {
+    Running,
+    Paused,
+    Aborted,
+}
+
+/// Handle used to pause, resume or abort a running MotionSequence, usually from another thread
+///
+/// Pausing takes effect between steps, a motion that has started finishes before the sequence
+/// pauses. Aborting sends a `halt` to the robot as soon as the running step sees it, which stops
+/// a motion that's under way.
+#[derive(Debug, Clone)]
+pub struct SequenceControl {
+    state: Arc<(Mutex<ControlState>, Condvar)>,
+}
+
+impl SequenceControl {
+    pub fn new() -> Self {
+        SequenceControl {
+            state: Arc::new((Mutex::new(ControlState::Running), Condvar::new())),
+        }
+    }
+
+    /// Pauses the sequence before its next step
+    pub fn pause(&self) {
+        self.set(ControlState::Paused);
+    }
+
+    /// Resumes a paused sequence
+    pub fn resume(&self) {
+        self.set(ControlState::Running);
+    }
+
+    /// Aborts the sequence and halts the robot
+    pub fn abort(&self) {
+        self.set(ControlState::Aborted);
+    }
+
+    /// Whether the sequence has been aborted
+    pub fn is_aborted(&self) -> bool {
+        self.get() == ControlState::Aborted
+    }
+
+    /// Whether the sequence has been paused
+    pub fn is_paused(&self) -> bool {
+        self.get() == ControlState::Paused
+    }
+
+    fn get(&self) -> ControlState {
+        *self.state.0.lock().unwrap()
+    }
+
+    fn set(&self, new_state: ControlState) {
+        let (state, changed) = &*self.state;
+        let mut state = state.lock().unwrap();
+        // an aborted sequence can't be resumed
+        if *state != ControlState::Aborted {
+            *state = new_state;
+        }
+        changed.notify_all();
+    }
+
+    /// Blocks while paused, returns false if the sequence was aborted
+    fn wait_while_paused(&self) -> bool {
+        let (state, changed) = &*self.state;
+        let state = changed
+            .wait_while(state.lock().unwrap(), |state| {
+                *state == ControlState::Paused
+            })
+            .unwrap();
+        *state != ControlState::Aborted
+    }
+}
+
+impl Default for SequenceControl {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// A chain of steps executed on the robot as one unit
+#[derive(Debug, Clone, Default, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct MotionSequence {
+    steps: Vec<MotionStep>,
+    motion_timeout: Option<Seconds>,
+}
+
+impl MotionSequence {
+    const DEFAULT_MOTION_TIMEOUT: Seconds = Seconds::from_millis(60_000);
+    const POLL_INTERVAL: Duration = Duration::from_millis(50);
+
+    pub fn new() -> Self {
+        MotionSequence::default()
+    }
+
+    /// The steps of the sequence, in order
+    pub fn steps(&self) -> &[MotionStep] {
+        &self.steps
+    }
+
+    /// Appends a step to the sequence
+    /// # Arguments
+    /// * `step` - The step to append
+    pub fn then(mut self, step: MotionStep) -> Self {
+        self.steps.push(step);
+        self
+    }
+
+    /// Appends a move to a location stored on the robot
+    pub fn move_to_waypoint(self, waypoint_id: i32, motion_profile_id: i32) -> Self {
+        self.then(MotionStep::MoveToWaypoint {
+            waypoint_id,
+            motion_profile_id,
+        })
+    }
+
+    /// Appends a move to a Cartesian pose in the world frame
+    pub fn move_to_cartesian(self, position: EndEffectorPosition, motion_profile_id: i32) -> Self {
+        self.then(MotionStep::MoveToCartesian {
+            position,
+            motion_profile_id,
+        })
+    }
+
+    /// Appends a move to a joint position
+    pub fn move_to_joints(self, joints: JointPosition, motion_profile_id: i32) -> Self {
+        self.then(MotionStep::MoveToJoints {
+            joints,
+            motion_profile_id,
+        })
+    }
+
+    /// Appends a gripper move
+    pub fn gripper(self, width_mm: impl Into<Millimetres>, motion_profile_id: i32) -> Self {
+        self.then(MotionStep::Gripper {
+            width_mm: width_mm.into(),
+            motion_profile_id,
+        })
+    }
+
+    /// Appends a wait for a signal to reach a state
+    pub fn wait_for_signal(self, signal: i32, state: bool, timeout: Duration) -> Self {
+        self.then(MotionStep::WaitForSignal {
+            signal,
+            state,
+            timeout,
+        })
+    }
+
+    /// Appends a change to an output signal
+    pub fn set_signal(self, signal: i32, state: bool) -> Self {
+        self.then(MotionStep::SetSignal { signal, state })
+    }
+
+    /// Appends a fixed pause
+    pub fn delay(self, duration: Duration) -> Self {
+        self.then(MotionStep::Delay(duration))
+    }
+
+    /// Appends a change to the system speed percentage
+    pub fn set_speed(self, speed: Percent) -> Self {
+        self.then(MotionStep::SetSpeed(speed))
+    }
+
+    /// Sets how long each motion step may take before the sequence fails
+    /// # Arguments
+    /// * `timeout` - Timeout of each motion step
+    pub fn with_motion_timeout(mut self, timeout: Seconds) -> Self {
+        self.motion_timeout = Some(timeout);
+        self
+    }
+
+    /// Checks every step up front without sending anything to the robot
+    /// # Arguments
+    /// * `robot` - Robot whose workspace the steps are checked against
+    pub fn validate(&self, robot: &PFlexRobot) -> Result<(), SequenceError> {
+        for (step, motion_step) in self.steps.iter().enumerate() {
+            motion_step
+                .validate(robot)
+                .map_err(|error| SequenceError { step, error })?;
+        }
+        Ok(())
+    }
+
+    /// Validates then executes every step in order
+    /// # Arguments
+    /// * `robot` - The robot to run the sequence on
+    /// * `control` - Handle used to pause, resume or abort the sequence
+    /// * `progress` - Called as steps start and finish
+    /// # Returns
+    /// * A SequenceError with the index of the step that failed
+    pub fn execute<F>(
+        &self,
+        robot: &mut PFlexRobot,
+        control: &SequenceControl,
+        mut progress: F,
+    ) -> Result<(), SequenceError>
+    where
+        F: FnMut(SequenceProgress),
+    {
+        info!("MotionSequence::execute called");
+        self.validate(robot)?;
+        for (step, motion_step) in self.steps.iter().enumerate() {
+            if control.is_paused() {
+                progress(SequenceProgress::Paused(step));
+                if control.wait_while_paused() {
+                    progress(SequenceProgress::Resumed(step));
+                }
+            }
+            if control.is_aborted() {
+                return Err(Self::abort(robot, step));
+            }
+            progress(SequenceProgress::Started(step));
+            debug!("executing step {}: {:?}", step, motion_step);
+            if let Err(error) = self.execute_step(robot, control, motion_step) {
+                if control.is_aborted() {
+                    return Err(Self::abort(robot, step));
+                }
+                return Err(SequenceError { step, error });
+            }
+            progress(SequenceProgress::Completed(step));
+        }
+        Ok(())
+    }
+
+    fn execute_step(
+        &self,
+        robot: &mut PFlexRobot,
+        control: &SequenceControl,
+        motion_step: &MotionStep,
+    ) -> Result<(), RobotError> {
+        match motion_step {
+            MotionStep::MoveToWaypoint {
+                waypoint_id,
+                motion_profile_id,
+            } => robot.move_to_waypoint(*waypoint_id, *motion_profile_id)?,
+            MotionStep::MoveToCartesian {
+                position,
+                motion_profile_id,
+            } => robot.move_to_cartesian(position.clone(), *motion_profile_id)?,
+            MotionStep::MoveToJoints {
+                joints,
+                motion_profile_id,
+            } => robot.move_to_joint_position(joints, *motion_profile_id)?,
+            MotionStep::Gripper {
+                width_mm,
+                motion_profile_id,
+            } => robot.move_gripper(*width_mm, *motion_profile_id)?,
+            MotionStep::WaitForSignal {
+                signal,
+                state,
+                timeout,
+            } => {
+                let started = Instant::now();
+                while robot.get_signal(*signal)? != *state {
+                    if control.is_aborted() {
+                        return Err("Aborted while waiting for signal".to_string());
+                    }
+                    if started.elapsed() > *timeout {
+                        return Err(format!("Timed out waiting for signal {}", signal));
+                    }
+                    thread::sleep(Self::POLL_INTERVAL);
+                }
+            }
+            MotionStep::SetSignal { signal, state } => robot.set_signal(*signal, *state)?,
+            MotionStep::Delay(duration) => {
+                let started = Instant::now();
+                while started.elapsed() < *duration {
+                    if control.is_aborted() {
+                        return Err("Aborted during delay".to_string());
+                    }
+                    thread::sleep(
+                        Self::POLL_INTERVAL.min(duration.saturating_sub(started.elapsed())),
+                    );
+                }
+            }
+            MotionStep::SetSpeed(speed) => robot.set_system_speed(*speed)?,
+        }
+        if motion_step.is_motion() {
+            self.wait_for_motion(robot, control)?;
+        }
+        Ok(())
+    }
+
+    // polls rather than blocking on waitForEOM, which holds the connection until the motion
+    // finishes, so an abort can halt the robot mid-move
+    fn wait_for_motion(
+        &self,
+        robot: &mut PFlexRobot,
+        control: &SequenceControl,
+    ) -> Result<(), RobotError> {
+        let timeout = self.motion_timeout.unwrap_or(Self::DEFAULT_MOTION_TIMEOUT);
+        let started = Instant::now();
+        while robot.is_moving()? {
+            if control.is_aborted() {
+                return Err("Aborted during motion".to_string());
+            }
+            if started.elapsed().as_secs_f64() > timeout.secs() {
+                // the robot isn't left running a move nothing is waiting for any more
+                let error = format!("Motion didn't finish within {}", timeout);
+                return Err(match robot.halt() {
+                    Ok(_) => format!("{}, halted", error),
+                    Err(e) => format!("{} and halt failed: {}", error, e),
+                });
+            }
+            thread::sleep(Self::POLL_INTERVAL);
+        }
+        robot.wait_until_static(timeout)
+    }
+
+    fn abort(robot: &mut PFlexRobot, step: usize) -> SequenceError {
+        info!("MotionSequence aborted at step {}", step);
+        let error = match robot.halt() {
+            Ok(_) => "Sequence aborted".to_string(),
+            Err(e) => format!("Sequence aborted but halt failed: {}", e),
+        };
+        SequenceError { step, error }
+    }
+}
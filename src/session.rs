@@ -0,0 +1,100 @@
+// Recorded TCS conversations
+//
+// A TCSClient with a SessionRecorder writes every request it sends, and the reply it got back,
+// as one JSON object per line. The file can be replayed by the simulator with
+// `MockRobot::start_replay` to reproduce a field issue without the robot, e.g.
+//
+// {"timestamp_s":1718000000.125,"elapsed_s":0.002,"request":"hp 1","reply":"0 ","error":null}
+
+use crate::error_codes::RobotError;
+use log::debug;
+use std::fmt;
+use std::fs::{self, File};
+use std::io::{BufWriter, Write};
+use std::path::Path;
+
+/// One request sent to the robot and what came back
+#[derive(Debug, Clone, PartialEq, serde::Serialize, serde::Deserialize)]
+pub struct Exchange {
+    /// When the request was sent, in seconds since the Unix epoch
+    pub timestamp_s: f64,
+    /// Time taken to send the request and read the reply
+    pub elapsed_s: f64,
+    /// The request without its line ending
+    pub request: String,
+    /// The reply without its line ending, None if no reply was waited for or read
+    pub reply: Option<String>,
+    /// Why the request or its reply failed
+    pub error: Option<String>,
+}
+
+/// Writes exchanges to a JSONL file as they happen
+pub struct SessionRecorder {
+    writer: Box<dyn Write + Send>,
+}
+
+impl SessionRecorder {
+    /// Creates a recorder writing to a new file, replacing any file already at the path
+    /// # Arguments
+    /// * `path` - Path of the JSONL file
+    pub fn create<P: AsRef<Path>>(path: P) -> Result<Self, RobotError> {
+        let file = File::create(path.as_ref())
+            .map_err(|e| format!("Failed to create {}: {}", path.as_ref().display(), e))?;
+        Ok(Self::new(BufWriter::new(file)))
+    }
+
+    /// Creates a recorder writing to any writer, e.g. a buffer in a test
+    pub fn new<W: Write + Send + 'static>(writer: W) -> Self {
+        SessionRecorder {
+            writer: Box::new(writer),
+        }
+    }
+
+    /// Writes an exchange as one line, flushing it straight away so nothing is lost if the
+    /// program crashes
+    pub fn record(&mut self, exchange: &Exchange) -> Result<(), RobotError> {
+        let line = serde_json::to_string(exchange).map_err(|e| e.to_string())?;
+        debug!("recording exchange: {}", line);
+        writeln!(self.writer, "{}", line)
+            .and_then(|_| self.writer.flush())
+            .map_err(|e| format!("Failed to record exchange: {}", e))
+    }
+}
+
+impl fmt::Debug for SessionRecorder {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("SessionRecorder").finish_non_exhaustive()
+    }
+}
+
+/// A recorded conversation, in the order it happened
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct Session {
+    pub exchanges: Vec<Exchange>,
+}
+
+impl Session {
+    /// Reads a session recorded by a SessionRecorder
+    /// # Arguments
+    /// * `path` - Path of the JSONL file
+    pub fn load<P: AsRef<Path>>(path: P) -> Result<Self, RobotError> {
+        let contents = fs::read_to_string(path.as_ref())
+            .map_err(|e| format!("Failed to read {}: {}", path.as_ref().display(), e))?;
+        Self::parse(&contents)
+    }
+
+    /// Parses the contents of a JSONL session file, blank lines are skipped
+    /// # Arguments
+    /// * `contents` - One exchange per line
+    pub fn parse(contents: &str) -> Result<Self, RobotError> {
+        let exchanges = contents
+            .lines()
+            .enumerate()
+            .filter(|(_, line)| !line.trim().is_empty())
+            .map(|(index, line)| {
+                serde_json::from_str(line).map_err(|e| format!("line {}: {}", index + 1, e))
+            })
+            .collect::<Result<Vec<Exchange>, RobotError>>()?;
+        Ok(Session { exchanges })
+    }
+}
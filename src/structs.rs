@@ -1,113 +1,587 @@
+use crate::error_codes::RobotError;
 use crate::pflex::PFlexRobot;
+use crate::transforms::Transform;
+use crate::units::{Degrees, Mass, Millimetres, Percent, Seconds};
+use std::fmt;
+use std::ops::Neg;
 
 /// Cartesian coordinates for a waypoint including optional rail position
 #[derive(Debug, Clone, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct Waypoint {
     pub id: i32,
-    pub x_mm: f64,
-    pub y_mm: f64,
-    pub z_mm: f64,
-    pub orientation_deg: f64,
-    pub rail_position_mm: Option<f64>,
+    pub x_mm: Millimetres,
+    pub y_mm: Millimetres,
+    pub z_mm: Millimetres,
+    pub orientation_deg: Degrees,
+    pub rail_position_mm: Option<Millimetres>,
 }
 
 impl Waypoint {
     pub fn new(
         id: i32,
-        x_mm: f64,
-        y_mm: f64,
-        z_mm: f64,
-        orientation_deg: f64,
-        rail_position_mm: Option<f64>,
+        x_mm: impl Into<Millimetres>,
+        y_mm: impl Into<Millimetres>,
+        z_mm: impl Into<Millimetres>,
+        orientation_deg: impl Into<Degrees>,
+        rail_position_mm: Option<Millimetres>,
     ) -> Self {
         Waypoint {
             id,
-            x_mm,
-            y_mm,
-            z_mm,
-            orientation_deg,
+            x_mm: x_mm.into(),
+            y_mm: y_mm.into(),
+            z_mm: z_mm.into(),
+            orientation_deg: orientation_deg.into(),
             rail_position_mm,
         }
     }
 
+    /// Converts the Waypoint into an end effector position with the default pitch and roll
+    pub fn to_endeffector_position(&self) -> EndEffectorPosition {
+        EndEffectorPosition {
+            x_mm: self.x_mm,
+            y_mm: self.y_mm,
+            z_mm: self.z_mm,
+            yaw_deg: self.orientation_deg,
+            pitch_deg: PFlexRobot::DEFAULT_EE_PITCH,
+            roll_deg: PFlexRobot::DEFAULT_EE_ROLL,
+        }
+    }
+
+    /// Parses the response of a `loc` request for a stored location into a Waypoint
+    /// # Arguments
+    /// * `id` - Location index that was requested
+    /// * `response` - The response data returned by the TCSClient, the location type and station
+    ///   index followed by x, y, z, yaw, pitch and roll
+    /// * `rail_position_mm` - Rail position to go with the location, the controller doesn't store it
+    pub fn from_response(
+        id: i32,
+        response: &[String],
+        rail_position_mm: Option<Millimetres>,
+    ) -> Result<Self, RobotError> {
+        let values = parse_values(response, 8)?;
+        Ok(Waypoint::new(
+            id,
+            values[2],
+            values[3],
+            values[4],
+            values[5],
+            rail_position_mm,
+        ))
+    }
+
     /// Converts the Waypoint struct to a Vec\<String\> payload for use with the TCSClient
     pub fn to_payload(&self) -> Vec<String> {
         vec![
             self.id.to_string(),
-            self.x_mm.to_string(),
-            self.y_mm.to_string(),
-            self.z_mm.to_string(),
-            self.orientation_deg.to_string(),
-            PFlexRobot::DEFAULT_EE_PITCH.to_string(),
-            PFlexRobot::DEFAULT_EE_ROLL.to_string(),
+            self.x_mm.mm().to_string(),
+            self.y_mm.mm().to_string(),
+            self.z_mm.mm().to_string(),
+            self.orientation_deg.deg().to_string(),
+            PFlexRobot::DEFAULT_EE_PITCH.deg().to_string(),
+            PFlexRobot::DEFAULT_EE_ROLL.deg().to_string(),
         ]
     }
 }
 
 /// Motion profile settings for the robot
+///
+/// Profiles are created with `MotionProfile::builder`, which checks every field against the
+/// limits the controller accepts.
 #[derive(Debug, Clone, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct MotionProfile {
     pub id: i32,
-    pub max_speed_percent: f64,  // Default: 50.0
-    pub max_accel_percent: f64,  // Default: 50.0
-    pub max_decel_percent: f64,  // Default: 50.0
-    pub accel_ramp_seconds: f64, // Default: 0.1
-    pub decel_ramp_seconds: f64, // Default: 0.1
-    pub in_range: f64,           // Default: 10.0
-    pub straight_line: i32,      // Default: 0 (False)
+    pub max_speed_percent: Percent,          // Default: 50.0
+    pub max_speed2_percent: Option<Percent>, // Default: None, sent as 0
+    pub max_accel_percent: Percent,          // Default: 50.0
+    pub max_decel_percent: Percent,          // Default: 50.0
+    pub accel_ramp_seconds: Seconds,         // Default: 0.1
+    pub decel_ramp_seconds: Seconds,         // Default: 0.1
+    pub in_range: f64,                       // Default: 10.0
+    pub straight_line: bool,                 // Default: false
 }
 
 impl MotionProfile {
-    pub fn default(id: i32) -> Self {
-        MotionProfile {
-            id,
-            max_speed_percent: 50.0,
-            max_accel_percent: 50.0,
-            max_decel_percent: 50.0,
-            accel_ramp_seconds: 0.1,
-            decel_ramp_seconds: 0.1,
-            in_range: 10.0,
-            straight_line: 0,
+    /// Lowest speed, acceleration or deceleration the controller accepts
+    pub const MIN_PERCENT: Percent = Percent::from_const(1.0);
+    /// Longest acceleration or deceleration ramp the controller accepts
+    pub const MAX_RAMP: Seconds = Seconds::from_millis(10_000);
+    /// In range value that disables the in range check
+    pub const IN_RANGE_DISABLED: f64 = -1.0;
+    /// Largest in range value the controller accepts
+    pub const MAX_IN_RANGE: f64 = 100.0;
+
+    const DEFAULT_PERCENT: Percent = Percent::from_const(50.0);
+    const DEFAULT_RAMP: Seconds = Seconds::from_millis(100);
+    const DEFAULT_IN_RANGE: f64 = 10.0;
+
+    /// Starts building a profile, every field starts at its default
+    /// # Arguments
+    /// * `id` - Profile index the settings will be stored under
+    pub fn builder(id: i32) -> MotionProfileBuilder {
+        MotionProfileBuilder {
+            profile: MotionProfile {
+                id,
+                max_speed_percent: Self::DEFAULT_PERCENT,
+                max_speed2_percent: None,
+                max_accel_percent: Self::DEFAULT_PERCENT,
+                max_decel_percent: Self::DEFAULT_PERCENT,
+                accel_ramp_seconds: Self::DEFAULT_RAMP,
+                decel_ramp_seconds: Self::DEFAULT_RAMP,
+                in_range: Self::DEFAULT_IN_RANGE,
+                straight_line: false,
+            },
+        }
+    }
+
+    /// Creates a profile with the default settings
+    /// # Arguments
+    /// * `id` - Profile index the settings will be stored under
+    pub fn with_defaults(id: i32) -> Self {
+        Self::builder(id).profile
+    }
+
+    /// Checks every field against the controller limits
+    /// # Returns
+    /// * A RobotError naming the first field that is out of range
+    pub fn validate(&self) -> Result<(), RobotError> {
+        let percents = [
+            ("speed", Some(self.max_speed_percent)),
+            ("speed2", self.max_speed2_percent),
+            ("acceleration", Some(self.max_accel_percent)),
+            ("deceleration", Some(self.max_decel_percent)),
+        ];
+        for (name, percent) in percents {
+            match percent {
+                Some(percent) if percent.value() < Self::MIN_PERCENT.value() => {
+                    return Err(format!(
+                        "Motion profile {}: {} of {} is below {}",
+                        self.id,
+                        name,
+                        percent,
+                        Self::MIN_PERCENT
+                    ))
+                }
+                _ => {}
+            }
+        }
+        let ramps = [
+            ("acceleration ramp", self.accel_ramp_seconds),
+            ("deceleration ramp", self.decel_ramp_seconds),
+        ];
+        for (name, ramp) in ramps {
+            if ramp.secs() > Self::MAX_RAMP.secs() {
+                return Err(format!(
+                    "Motion profile {}: {} of {} is above {}",
+                    self.id,
+                    name,
+                    ramp,
+                    Self::MAX_RAMP
+                ));
+            }
+        }
+        let in_range_valid = self.in_range == Self::IN_RANGE_DISABLED
+            || (0.0..=Self::MAX_IN_RANGE).contains(&self.in_range);
+        if !in_range_valid {
+            return Err(format!(
+                "Motion profile {}: in range of {} is not {} or between 0 and {}",
+                self.id,
+                self.in_range,
+                Self::IN_RANGE_DISABLED,
+                Self::MAX_IN_RANGE
+            ));
         }
+        Ok(())
+    }
+
+    /// Parses the response of a `profile` request into a MotionProfile
+    /// # Arguments
+    /// * `id` - Profile index that was requested
+    /// * `response` - The response data returned by the TCSClient, in the order of `to_payload`
+    /// # Returns
+    /// * A RobotError if the stored profile is outside the controller limits
+    pub fn from_response(id: i32, response: &[String]) -> Result<Self, RobotError> {
+        let values = parse_values(response, 8)?;
+        let builder = MotionProfile::builder(id)
+            .speed(Percent::new(values[0])?)
+            .accel(Percent::new(values[2])?)
+            .decel(Percent::new(values[3])?)
+            .accel_ramp(Seconds::new(values[4])?)
+            .decel_ramp(Seconds::new(values[5])?)
+            .in_range(values[6])
+            .straight_line(values[7] != 0.0);
+        let builder = match values[1] {
+            speed2 if speed2 > 0.0 => builder.speed2(Percent::new(speed2)?),
+            _ => builder,
+        };
+        builder.build()
     }
 
     /// Converts the MotionProfile struct to a Vec\<String\> payload for use with the TCSClient
-    pub fn to_payload(&self) -> Vec<String> {
-        vec![
+    /// # Returns
+    /// * A RobotError if the profile is outside the controller limits
+    pub fn to_payload(&self) -> Result<Vec<String>, RobotError> {
+        self.validate()?;
+        Ok(vec![
             self.id.to_string(),
-            self.max_speed_percent.to_string(),
-            "0".to_string(),
-            self.max_accel_percent.to_string(),
-            self.max_decel_percent.to_string(),
-            self.accel_ramp_seconds.to_string(),
-            self.decel_ramp_seconds.to_string(),
+            self.max_speed_percent.value().to_string(),
+            self.max_speed2_percent
+                .map_or(0.0, |speed2| speed2.value())
+                .to_string(),
+            self.max_accel_percent.value().to_string(),
+            self.max_decel_percent.value().to_string(),
+            self.accel_ramp_seconds.secs().to_string(),
+            self.decel_ramp_seconds.secs().to_string(),
             self.in_range.to_string(),
-            self.straight_line.to_string(),
-        ]
+            (self.straight_line as i32).to_string(),
+        ])
+    }
+}
+
+/// Builds a MotionProfile, checking the settings against the controller limits
+#[derive(Debug, Clone)]
+pub struct MotionProfileBuilder {
+    profile: MotionProfile,
+}
+
+impl MotionProfileBuilder {
+    /// Maximum speed as a percentage of the robot's top speed
+    pub fn speed(mut self, speed: Percent) -> Self {
+        self.profile.max_speed_percent = speed;
+        self
+    }
+
+    /// Maximum rotational speed of Cartesian moves, the controller uses `speed` if not set
+    pub fn speed2(mut self, speed2: Percent) -> Self {
+        self.profile.max_speed2_percent = Some(speed2);
+        self
+    }
+
+    /// Maximum acceleration as a percentage of the robot's top acceleration
+    pub fn accel(mut self, accel: Percent) -> Self {
+        self.profile.max_accel_percent = accel;
+        self
+    }
+
+    /// Maximum deceleration as a percentage of the robot's top deceleration
+    pub fn decel(mut self, decel: Percent) -> Self {
+        self.profile.max_decel_percent = decel;
+        self
+    }
+
+    /// Time taken to reach the maximum acceleration
+    pub fn accel_ramp(mut self, ramp: Seconds) -> Self {
+        self.profile.accel_ramp_seconds = ramp;
+        self
+    }
+
+    /// Time taken to reach the maximum deceleration
+    pub fn decel_ramp(mut self, ramp: Seconds) -> Self {
+        self.profile.decel_ramp_seconds = ramp;
+        self
+    }
+
+    /// How close the robot has to be to the target before a move is finished, from 0 to 100
+    /// # Arguments
+    /// * `in_range` - 0 waits for the exact position, `MotionProfile::IN_RANGE_DISABLED` doesn't wait
+    pub fn in_range(mut self, in_range: f64) -> Self {
+        self.profile.in_range = in_range;
+        self
+    }
+
+    /// Moves in a straight Cartesian line instead of joint-interpolated
+    pub fn straight_line(mut self, straight_line: bool) -> Self {
+        self.profile.straight_line = straight_line;
+        self
+    }
+
+    /// Validates the settings and returns the profile
+    pub fn build(self) -> Result<MotionProfile, RobotError> {
+        self.profile.validate()?;
+        Ok(self.profile)
     }
 }
 
 /// End effector position for the robot
-#[derive(Debug)]
+#[derive(Debug, Clone, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct EndEffectorPosition {
-    pub yaw_mm: f64,
-    pub pitch_mm: f64,
-    pub roll_mm: f64,
-    pub x_mm: f64,
-    pub y_mm: f64,
-    pub z_mm: f64,
+    pub yaw_deg: Degrees,
+    pub pitch_deg: Degrees,
+    pub roll_deg: Degrees,
+    pub x_mm: Millimetres,
+    pub y_mm: Millimetres,
+    pub z_mm: Millimetres,
 }
 
 impl EndEffectorPosition {
+    /// Parses the response of a `wherec` request into an EndEffectorPosition
+    /// # Arguments
+    /// * `response` - The response data returned by the TCSClient
+    pub fn from_response(response: &[String]) -> Result<Self, RobotError> {
+        let values = parse_values(response, 6)?;
+        Ok(EndEffectorPosition {
+            x_mm: values[0].into(),
+            y_mm: values[1].into(),
+            z_mm: values[2].into(),
+            yaw_deg: values[3].into(),
+            pitch_deg: values[4].into(),
+            roll_deg: values[5].into(),
+        })
+    }
+
+    /// Converts the EndEffectorPosition into a Waypoint that can be stored on the robot
+    /// # Arguments
+    /// * `id` - Location index the waypoint will be stored under
+    pub fn to_waypoint(&self, id: i32, rail_position_mm: Option<Millimetres>) -> Waypoint {
+        Waypoint::new(
+            id,
+            self.x_mm,
+            self.y_mm,
+            self.z_mm,
+            self.yaw_deg,
+            rail_position_mm,
+        )
+    }
+
     /// Converts the EndEffectorPosition struct to a Vec\<String\> payload for use with the TCSClient
     pub fn to_payload(&self) -> Vec<String> {
         vec![
-            self.x_mm.to_string(),
-            self.y_mm.to_string(),
-            self.z_mm.to_string(),
-            self.yaw_mm.to_string(),
-            self.pitch_mm.to_string(),
-            self.roll_mm.to_string(),
+            self.x_mm.mm().to_string(),
+            self.y_mm.mm().to_string(),
+            self.z_mm.mm().to_string(),
+            self.yaw_deg.deg().to_string(),
+            self.pitch_deg.deg().to_string(),
+            self.roll_deg.deg().to_string(),
         ]
     }
 }
+
+/// Joint positions reported by the robot, in the order returned by `wherej`
+#[derive(Debug, Clone, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct JointPosition {
+    pub z_mm: Millimetres,
+    pub shoulder_deg: Degrees,
+    pub elbow_deg: Degrees,
+    pub wrist_deg: Degrees,
+    pub gripper_mm: Millimetres,
+    pub rail_mm: Option<Millimetres>,
+}
+
+impl JointPosition {
+    /// Parses the response of a `wherej` request into a JointPosition
+    /// # Arguments
+    /// * `response` - The response data returned by the TCSClient
+    pub fn from_response(response: &[String]) -> Result<Self, RobotError> {
+        let values = parse_values(response, 5)?;
+        Ok(JointPosition {
+            z_mm: values[0].into(),
+            shoulder_deg: values[1].into(),
+            elbow_deg: values[2].into(),
+            wrist_deg: values[3].into(),
+            gripper_mm: values[4].into(),
+            rail_mm: values.get(5).map(|&rail_mm| rail_mm.into()),
+        })
+    }
+
+    /// Converts the JointPosition struct to a Vec\<String\> payload for use with the TCSClient
+    pub fn to_payload(&self) -> Vec<String> {
+        let mut payload = vec![
+            self.z_mm.mm().to_string(),
+            self.shoulder_deg.deg().to_string(),
+            self.elbow_deg.deg().to_string(),
+            self.wrist_deg.deg().to_string(),
+            self.gripper_mm.mm().to_string(),
+        ];
+        if let Some(rail_mm) = self.rail_mm {
+            payload.push(rail_mm.mm().to_string());
+        }
+        payload
+    }
+}
+
+/// A named station on an instrument, stored on the robot as a location
+/// # Fields
+/// * `name` - Human readable name of the station
+/// * `location` - The waypoint the gripper picks from or places to
+/// * `approach_height_mm` - Clearance above the location used when approaching
+/// * `frame` - Name of the frame the location is taught in, or None for the world frame
+#[derive(Debug, Clone, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct Station {
+    pub name: String,
+    pub location: Waypoint,
+    pub approach_height_mm: Millimetres,
+    pub frame: Option<String>,
+}
+
+impl Station {
+    pub const DEFAULT_APPROACH_HEIGHT_MM: Millimetres = Millimetres::from_mm(50.0);
+
+    pub fn new(name: &str, location: Waypoint) -> Self {
+        Station {
+            name: name.to_string(),
+            location,
+            approach_height_mm: Self::DEFAULT_APPROACH_HEIGHT_MM,
+            frame: None,
+        }
+    }
+
+    /// Creates a station taught in a named frame
+    /// # Arguments
+    /// * `name` - Name of the station
+    /// * `location` - The waypoint, expressed in the named frame
+    /// * `frame` - Name of the frame
+    pub fn in_frame(name: &str, location: Waypoint, frame: &str) -> Self {
+        Station {
+            frame: Some(frame.to_string()),
+            ..Station::new(name, location)
+        }
+    }
+
+    /// Moves the station location by a transform, e.g. the correction from `Transform::reteach`
+    /// # Arguments
+    /// * `transform` - Transform applied to the location
+    pub fn transformed(&self, transform: &Transform) -> Station {
+        let pose = transform.transform_pose(&self.location.to_endeffector_position());
+        Station {
+            location: pose.to_waypoint(self.location.id, self.location.rail_position_mm),
+            ..self.clone()
+        }
+    }
+
+    /// Waypoint above the station location used to approach and retract
+    /// # Arguments
+    /// * `id` - Location index the approach waypoint will be stored under
+    pub fn approach_waypoint(&self, id: i32) -> Waypoint {
+        Waypoint {
+            id,
+            z_mm: self.location.z_mm + self.approach_height_mm,
+            ..self.location.clone()
+        }
+    }
+}
+
+/// A single jog step, a distance along a Cartesian axis or linear joint, or an angle about a
+/// Cartesian axis or rotary joint
+#[derive(Debug, Clone, Copy, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(feature = "serde", serde(rename_all = "lowercase"))]
+pub enum JogStep {
+    Linear(Millimetres),
+    Angular(Degrees),
+}
+
+impl JogStep {
+    /// Whether the step is a distance rather than an angle
+    pub fn is_linear(&self) -> bool {
+        matches!(self, JogStep::Linear(_))
+    }
+
+    /// Get the size of the step in millimetres or degrees
+    pub fn value(&self) -> f64 {
+        match self {
+            JogStep::Linear(step) => step.mm(),
+            JogStep::Angular(step) => step.deg(),
+        }
+    }
+}
+
+impl Neg for JogStep {
+    type Output = JogStep;
+
+    fn neg(self) -> JogStep {
+        match self {
+            JogStep::Linear(step) => JogStep::Linear(-step),
+            JogStep::Angular(step) => JogStep::Angular(-step),
+        }
+    }
+}
+
+impl fmt::Display for JogStep {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            JogStep::Linear(step) => write!(f, "{}", step),
+            JogStep::Angular(step) => write!(f, "{}", step),
+        }
+    }
+}
+
+/// Largest single step allowed when jogging
+/// # Fields
+/// * `max_linear_step_mm` - Largest step along a Cartesian axis or linear joint
+/// * `max_angular_step_deg` - Largest step about a Cartesian axis or rotary joint
+#[derive(Debug, Clone, Copy, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct JogLimits {
+    pub max_linear_step_mm: Millimetres,
+    pub max_angular_step_deg: Degrees,
+}
+
+impl Default for JogLimits {
+    fn default() -> Self {
+        JogLimits {
+            max_linear_step_mm: Millimetres::from_mm(10.0),
+            max_angular_step_deg: Degrees::from_deg(5.0),
+        }
+    }
+}
+
+impl JogLimits {
+    /// Checks a jog step against the limits
+    /// # Arguments
+    /// * `step` - The step
+    pub fn check(&self, step: JogStep) -> Result<(), RobotError> {
+        let (within, limit) = match step {
+            JogStep::Linear(step) => (
+                step.abs() <= self.max_linear_step_mm,
+                JogStep::Linear(self.max_linear_step_mm),
+            ),
+            JogStep::Angular(step) => (
+                step.abs() <= self.max_angular_step_deg,
+                JogStep::Angular(self.max_angular_step_deg),
+            ),
+        };
+        if !step.value().is_finite() || !within {
+            return Err(format!(
+                "Jog step of {} exceeds the limit of {}",
+                step, limit
+            ));
+        }
+        Ok(())
+    }
+}
+
+/// Payloads used by the robot depending on whether the gripper is holding a plate
+/// # Fields
+/// * `empty` - Payload with nothing in the gripper, usually just the fingers
+/// * `holding` - Payload while a plate is held
+#[derive(Debug, Clone, Copy, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct GripperPayloads {
+    pub empty: Mass,
+    pub holding: Mass,
+}
+
+/// Parses at least `expected` floating point values from a TCS response
+fn parse_values(response: &[String], expected: usize) -> Result<Vec<f64>, RobotError> {
+    let values = response
+        .iter()
+        .filter(|value| !value.is_empty())
+        .map(|value| {
+            value
+                .parse::<f64>()
+                .map_err(|_| format!("Unexpected value in response: {}", value))
+        })
+        .collect::<Result<Vec<f64>, RobotError>>()?;
+    if values.len() < expected {
+        return Err(format!(
+            "Expected {} values in response but got {}",
+            expected,
+            values.len()
+        ));
+    }
+    Ok(values)
+}
@@ -1,14 +1,26 @@
 pub use crate::commands::TCSCommand;
+#[cfg(feature = "record")]
+use crate::session::{Exchange, SessionRecorder};
 use log::{debug, info};
-use std::io::{Read, Write};
+use std::io::{Error, ErrorKind, Read, Write};
 use std::net::{Shutdown, TcpStream};
 use std::time::Duration;
+#[cfg(feature = "record")]
+use std::time::{Instant, SystemTime, UNIX_EPOCH};
 
 use crate::error_codes::{ResponseCodes, RobotError};
 
 #[derive(Debug)]
 pub struct TCSClient {
     pub socket: Option<TcpStream>,
+    // bytes read past the end of the last response
+    pending: Vec<u8>,
+    // address and timeout of the last connection, used to reconnect after a failed read
+    peer: Option<(String, u16, f64)>,
+    // set when the client reconnected, which loses anything the controller keeps per connection
+    reconnected: bool,
+    #[cfg(feature = "record")]
+    recorder: Option<SessionRecorder>,
 }
 
 /// Creates a new TCSClient instance without an active socket
@@ -17,10 +29,17 @@ impl TCSClient {
     const REQUEST_SEPARATOR: &'static str = "\n";
     const RESPONSE_SEPARATOR: &'static str = "\r\n";
     const SPACEBAR_SEPERATOR: &'static str = " ";
-    const TCS_SERVER_PORT: u16 = 10100;
+    pub(crate) const TCS_SERVER_PORT: u16 = 10100;
 
     pub fn new() -> TCSClient {
-        TCSClient { socket: None }
+        TCSClient {
+            socket: None,
+            pending: Vec::new(),
+            peer: None,
+            reconnected: false,
+            #[cfg(feature = "record")]
+            recorder: None,
+        }
     }
 
     /// Attempts to connect to the specified robot
@@ -28,15 +47,32 @@ impl TCSClient {
     /// * `ip` - IP address of the robot
     /// * `timeout` - Optional timeout setting for all socket read/write attempts
     pub fn connect(&mut self, ip: &str, timeout: Option<f64>) -> Result<(), std::io::Error> {
+        self.connect_with_port(ip, Self::TCS_SERVER_PORT, timeout)
+    }
+
+    /// Attempts to connect to a TCS server listening on a non-standard port, e.g. a simulator
+    /// # Arguments
+    /// * `ip` - IP address of the robot
+    /// * `port` - Port the TCS server is listening on
+    /// * `timeout` - Optional timeout setting for all socket read/write attempts
+    pub fn connect_with_port(
+        &mut self,
+        ip: &str,
+        port: u16,
+        timeout: Option<f64>,
+    ) -> Result<(), std::io::Error> {
         let timeout = timeout.unwrap_or(TCSClient::DEFAULT_TIMEOUT);
-        let addr = format!("{}:{}", ip, Self::TCS_SERVER_PORT);
+        let addr = format!("{}:{}", ip, port);
         info!("tcs_client::connect called");
         let conn_attempt = TcpStream::connect(addr.clone());
         match conn_attempt {
             Ok(stream) => {
-                stream.set_read_timeout(Some(Duration::from_secs_f64(timeout)))?;
-                stream.set_write_timeout(Some(Duration::from_secs_f64(timeout)))?;
+                let duration = timeout_duration(timeout)?;
+                stream.set_read_timeout(Some(duration))?;
+                stream.set_write_timeout(Some(duration))?;
                 self.socket = Some(stream);
+                self.pending.clear();
+                self.peer = Some((ip.to_string(), port, timeout));
                 debug!("connected to client");
                 Ok(())
             }
@@ -53,7 +89,7 @@ impl TCSClient {
     /// * `command_args` - Optional command arguments
     /// * `wait_for_response` - A boolean option should any commands not require waiting for a response
     /// * `read_timeout` - Optional argument to set the read timeout on the socket
-    pub fn send_command<'a>(
+    pub fn send_command(
         &mut self,
         command: TCSCommand,
         command_args: Option<Vec<&str>>,
@@ -61,72 +97,237 @@ impl TCSClient {
         read_timeout: Option<f64>,
     ) -> Result<Vec<String>, RobotError> {
         info!("tcs_client::send_command called");
-        if read_timeout.is_some() {
-            self.socket
-                .as_ref()
-                .unwrap()
-                .set_read_timeout(Some(Duration::from_secs_f64(read_timeout.unwrap())))
-                .expect("Failed to set read timeout");
+        let payload = match command_args {
+            // build the additional arguments if they exist
+            Some(command_args) => {
+                let payload_slice = command_args.join(TCSClient::SPACEBAR_SEPERATOR);
+                format!(
+                    "{}{}{}{}",
+                    command,
+                    TCSClient::SPACEBAR_SEPERATOR,
+                    payload_slice,
+                    TCSClient::REQUEST_SEPARATOR
+                )
+            }
+            // build the command without additional arguments
+            None => format!("{}{}", command, TCSClient::REQUEST_SEPARATOR),
+        };
+        self.transmit(payload, wait_for_response, read_timeout)
+    }
+
+    /// Sends a request written out in full, for commands the TCSCommand enum doesn't cover
+    /// # Arguments
+    /// * `request` - The command and its arguments, e.g. `pd 2800 1 0 1`
+    /// * `read_timeout` - Optional argument to set the read timeout on the socket
+    pub fn send_raw(
+        &mut self,
+        request: &str,
+        read_timeout: Option<f64>,
+    ) -> Result<Vec<String>, RobotError> {
+        info!("tcs_client::send_raw called");
+        let payload = format!("{}{}", request.trim(), TCSClient::REQUEST_SEPARATOR);
+        self.transmit(payload, true, read_timeout)
+    }
+
+    /// Sends a request written out in full and returns the whole reply, error codes included,
+    /// e.g. for a terminal showing the controller's replies as they are
+    /// # Arguments
+    /// * `request` - The command and its arguments, e.g. `pd 2800 1 0 1`
+    /// * `wait_for_response` - False for requests the controller doesn't answer, e.g. `freemode 0`
+    /// * `read_timeout` - Optional argument to set the read timeout on the socket
+    /// # Returns
+    /// * The response code followed by the rest of the reply, or nothing if it wasn't waited for
+    pub fn send_raw_unchecked(
+        &mut self,
+        request: &str,
+        wait_for_response: bool,
+        read_timeout: Option<f64>,
+    ) -> Result<Vec<String>, RobotError> {
+        info!("tcs_client::send_raw_unchecked called");
+        let payload = format!("{}{}", request.trim(), TCSClient::REQUEST_SEPARATOR);
+        self.exchange(payload, wait_for_response, read_timeout)
+            .map(Option::unwrap_or_default)
+    }
+
+    fn transmit(
+        &mut self,
+        payload: String,
+        wait_for_response: bool,
+        read_timeout: Option<f64>,
+    ) -> Result<Vec<String>, RobotError> {
+        match self.exchange(payload, wait_for_response, read_timeout)? {
+            Some(r) => {
+                let if_error_code = ResponseCodes::check_code(r[0].to_owned());
+                match if_error_code {
+                    Ok(_) => Ok(r[1..].to_vec()),
+                    Err(e) => Err(e),
+                }
+            }
+            None => Ok(vec![]),
         }
-        let payload: String;
-
-        // build the additional arguments if they exist
-        if command_args.is_some() {
-            let payload_slice = command_args.unwrap().join(TCSClient::SPACEBAR_SEPERATOR);
-            payload = format!(
-                "{}{}{}{}",
-                command,
-                TCSClient::SPACEBAR_SEPERATOR,
-                payload_slice,
-                TCSClient::REQUEST_SEPARATOR
-            );
-        // build the command without additional arguments
-        } else {
-            payload = format!("{}{}", command, TCSClient::REQUEST_SEPARATOR);
+    }
+
+    // sends the payload and reads the reply, if one is waited for, without checking its code
+    fn exchange(
+        &mut self,
+        payload: String,
+        wait_for_response: bool,
+        read_timeout: Option<f64>,
+    ) -> Result<Option<Vec<String>>, RobotError> {
+        let Some(mut socket) = self.socket.as_ref() else {
+            return Err("Not connected to a TCS".to_string());
+        };
+        if let Some(read_timeout) = read_timeout {
+            timeout_duration(read_timeout)
+                .and_then(|duration| socket.set_read_timeout(Some(duration)))
+                .map_err(|e| format!("Failed to set read timeout: {}", e))?;
         }
         debug!("tcs_client::send_command payload: {}", payload);
+        #[cfg(feature = "record")]
+        let started = (SystemTime::now(), Instant::now());
         // send the command
-        self.socket
-            .as_ref()
-            .unwrap()
-            .write_all(payload.as_bytes())
-            .expect("Failed to write message");
+        if let Err(e) = socket.write_all(payload.as_bytes()) {
+            let error = format!("Failed to write message: {}", e);
+            #[cfg(feature = "record")]
+            self.record(&payload, started, None, Some(&error));
+            return Err(error);
+        }
 
         // read the response (if needed)
         if wait_for_response {
             let response = self.get_response();
+            if read_timeout.is_some() {
+                self.restore_read_timeout();
+            }
+            #[cfg(feature = "record")]
+            match &response {
+                Ok(r) => self.record(&payload, started, Some(&r.join(" ")), None),
+                Err(e) => self.record(&payload, started, None, Some(&e.to_string())),
+            }
             match response {
-                Ok(r) => {
-                    let if_error_code = ResponseCodes::check_code(r[0].to_owned());
-                    match if_error_code {
-                        Ok(_) => Ok(r[1..].to_vec()),
-                        Err(e) => Err(e),
-                    }
-                }
-                Err(e) => Err(e.to_string()),
+                Ok(r) => Ok(Some(r)),
+                Err(e) => Err(self.resync(e)),
             }
         } else {
-            Ok(vec![])
+            #[cfg(feature = "record")]
+            self.record(&payload, started, None, None);
+            Ok(None)
+        }
+    }
+
+    // a read timeout given for one request, e.g. a long wait for a move, isn't kept for the next
+    fn restore_read_timeout(&mut self) {
+        if let (Some(socket), Some((_, _, timeout))) = (self.socket.as_ref(), self.peer.as_ref()) {
+            if let Err(e) =
+                timeout_duration(*timeout).and_then(|d| socket.set_read_timeout(Some(d)))
+            {
+                debug!("failed to restore the read timeout: {}", e);
+            }
         }
     }
 
-    fn get_response<'a>(&mut self) -> Result<Vec<String>, std::io::Error> {
+    // a reply that times out may still turn up and be taken for the reply to the next request, so
+    // the connection is dropped along with anything read of the reply and opened again
+    fn resync(&mut self, error: Error) -> RobotError {
+        info!("tcs_client::resync called");
+        self.pending.clear();
+        if let Some(socket) = self.socket.take() {
+            let _closed = socket.shutdown(Shutdown::Both);
+        }
+        let Some((ip, port, timeout)) = self.peer.clone() else {
+            return error.to_string();
+        };
+        match self.connect_with_port(&ip, port, Some(timeout)) {
+            Ok(_) => {
+                self.reconnected = true;
+                format!("{}, reconnected to discard the reply", error)
+            }
+            Err(e) => format!("{}, failed to reconnect: {}", error, e),
+        }
+    }
+
+    /// Whether the client has reconnected since this was last called
+    pub(crate) fn take_reconnected(&mut self) -> bool {
+        std::mem::take(&mut self.reconnected)
+    }
+
+    /// Whether the client has reconnected since `take_reconnected` was last called
+    pub(crate) fn has_reconnected(&self) -> bool {
+        self.reconnected
+    }
+
+    /// Records every request and reply to a session file from now on
+    /// # Arguments
+    /// * `recorder` - Where the exchanges are written, e.g. `SessionRecorder::create(path)?`
+    #[cfg(feature = "record")]
+    pub fn start_recording(&mut self, recorder: SessionRecorder) {
+        info!("tcs_client::start_recording called");
+        self.recorder = Some(recorder);
+    }
+
+    /// Stops recording, handing back the recorder
+    #[cfg(feature = "record")]
+    pub fn stop_recording(&mut self) -> Option<SessionRecorder> {
+        info!("tcs_client::stop_recording called");
+        self.recorder.take()
+    }
+
+    #[cfg(feature = "record")]
+    fn record(
+        &mut self,
+        payload: &str,
+        started: (SystemTime, Instant),
+        reply: Option<&str>,
+        error: Option<&str>,
+    ) {
+        let Some(recorder) = self.recorder.as_mut() else {
+            return;
+        };
+        let exchange = Exchange {
+            timestamp_s: started
+                .0
+                .duration_since(UNIX_EPOCH)
+                .unwrap_or_default()
+                .as_secs_f64(),
+            elapsed_s: started.1.elapsed().as_secs_f64(),
+            request: payload
+                .trim_end_matches(TCSClient::REQUEST_SEPARATOR)
+                .to_string(),
+            reply: reply.map(str::to_string),
+            error: error.map(str::to_string),
+        };
+        // a recording problem shouldn't stop the robot
+        if let Err(e) = recorder.record(&exchange) {
+            debug!("{}", e);
+        }
+    }
+
+    fn get_response(&mut self) -> Result<Vec<String>, std::io::Error> {
         info!("tcs_client::get_response called");
         let read_buffer = &mut [0; 1024];
-        let mut response: Vec<u8> = Vec::new();
-        // todo: remove unwrap
-        let read_attempt = self.socket.as_ref().unwrap().read(read_buffer);
-        match read_attempt {
-            Ok(bytes_read) => {
-                response.extend_from_slice(&read_buffer[..bytes_read]);
+        let separator = TCSClient::RESPONSE_SEPARATOR.as_bytes();
+        // a response can arrive in pieces, keep reading until the separator turns up
+        let end = loop {
+            if let Some(index) = self
+                .pending
+                .windows(separator.len())
+                .position(|window| window == separator)
+            {
+                break index + separator.len();
             }
-            Err(e) => {
-                return Err(e);
+            let bytes_read = self.socket.as_ref().unwrap().read(read_buffer)?;
+            if bytes_read == 0 {
+                return Err(Error::new(
+                    ErrorKind::UnexpectedEof,
+                    "Connection closed before a response was received",
+                ));
             }
-        }
-        debug!("tcs_client::get_response payload: {:#?}", read_attempt);
-        // todo: make this nicer
-        let response_str = std::str::from_utf8(&response).unwrap();
+            self.pending.extend_from_slice(&read_buffer[..bytes_read]);
+        };
+        let response: Vec<u8> = self.pending.drain(..end).collect();
+        debug!("tcs_client::get_response payload: {:#?}", response);
+        let response_str =
+            std::str::from_utf8(&response).map_err(|e| Error::new(ErrorKind::InvalidData, e))?;
         let response_parts = response_str
             .split(TCSClient::SPACEBAR_SEPERATOR)
             .collect::<Vec<&str>>();
@@ -150,14 +351,13 @@ impl TCSClient {
         // this only closes the socket, it doesn't tell the robot that you're disconnecting
         // that'll need to be done by calling the exit command
         info!("tcs_client::disconnect called");
-        if self.socket.is_some() {
-            self.socket
-                .as_ref()
-                .unwrap()
-                .shutdown(Shutdown::Both)
-                .expect("Shutdown failed so I'm failing I guess(?)");
-            self.socket = None;
-            Ok(())
+        if let Some(socket) = self.socket.take() {
+            self.pending.clear();
+            // the robot may already have closed the connection
+            match socket.shutdown(Shutdown::Both) {
+                Err(e) if e.kind() != ErrorKind::NotConnected => Err(e),
+                _ => Ok(()),
+            }
         } else {
             Err(std::io::Error::new(
                 std::io::ErrorKind::NotConnected,
@@ -166,3 +366,19 @@ impl TCSClient {
         }
     }
 }
+
+impl Default for TCSClient {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+// negative, infinite and NaN timeouts are rejected rather than panicking, zero is rejected by the socket
+fn timeout_duration(seconds: f64) -> Result<Duration, Error> {
+    Duration::try_from_secs_f64(seconds).map_err(|e| {
+        Error::new(
+            ErrorKind::InvalidInput,
+            format!("Invalid timeout of {} seconds: {}", seconds, e),
+        )
+    })
+}
@@ -0,0 +1,322 @@
+use crate::enums::Joint;
+use crate::error_codes::RobotError;
+use crate::pflex::PFlexRobot;
+use crate::structs::{EndEffectorPosition, JointPosition, MotionProfile, Station, Waypoint};
+use log::{debug, info};
+use std::collections::BTreeMap;
+use std::io::{BufRead, Write};
+
+/// A pose captured while teaching, in both Cartesian and joint space
+#[derive(Debug, Clone, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct TaughtPose {
+    pub cartesian: EndEffectorPosition,
+    pub joints: JointPosition,
+}
+
+impl TaughtPose {
+    /// Converts the captured pose into a Waypoint that can be stored on the robot
+    /// # Arguments
+    /// * `id` - Location index the waypoint will be stored under
+    pub fn to_waypoint(&self, id: i32) -> Waypoint {
+        self.cartesian.to_waypoint(id, self.joints.rail_mm)
+    }
+
+    /// Converts the captured pose into a Station
+    /// # Arguments
+    /// * `name` - Name of the station
+    /// * `id` - Location index the station will be stored under
+    pub fn to_station(&self, name: &str, id: i32) -> Station {
+        Station::new(name, self.to_waypoint(id))
+    }
+}
+
+/// Actions that drive a teaching session
+#[derive(Debug, Clone, PartialEq)]
+pub enum TeachAction {
+    /// Capture the current pose as a named location
+    CaptureLocation(String),
+    /// Capture the current pose as a named station
+    CaptureStation(String),
+    /// Report the current pose without capturing it
+    Where,
+    /// End the session and restore normal mode
+    Finish,
+}
+
+impl TeachAction {
+    /// Parses a line typed by the operator into a TeachAction
+    /// # Arguments
+    /// * `line` - e.g. `loc plate_nest`, `station reader`, `where` or `done`
+    /// # Returns
+    /// * None if the line isn't a recognised action
+    pub fn parse(line: &str) -> Option<TeachAction> {
+        let mut parts = line.split_whitespace();
+        let action = parts.next()?;
+        let name = parts.next();
+        match (action, name) {
+            ("loc" | "l", Some(name)) => Some(TeachAction::CaptureLocation(name.to_string())),
+            ("station" | "s", Some(name)) => Some(TeachAction::CaptureStation(name.to_string())),
+            ("where" | "w", None) => Some(TeachAction::Where),
+            ("done" | "quit" | "q", None) => Some(TeachAction::Finish),
+            _ => None,
+        }
+    }
+}
+
+/// Everything captured during a teaching session
+#[derive(Debug, Clone, Default, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct TaughtPoints {
+    pub locations: BTreeMap<String, TaughtPose>,
+    pub stations: BTreeMap<String, TaughtPose>,
+}
+
+/// Taught locations, motion profiles and stations saved between sessions
+///
+/// With the `serde` feature enabled the file can be written in any serde format, e.g. JSON or
+/// TOML. The version the file was written with is stored alongside the data, call `migrate`
+/// after reading a file so older layouts are brought up to date.
+#[derive(Debug, Clone, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct TeachFile {
+    pub version: u32,
+    #[cfg_attr(feature = "serde", serde(default))]
+    pub locations: BTreeMap<String, Waypoint>,
+    #[cfg_attr(feature = "serde", serde(default))]
+    pub profiles: Vec<MotionProfile>,
+    #[cfg_attr(feature = "serde", serde(default))]
+    pub stations: Vec<Station>,
+}
+
+impl TeachFile {
+    /// Layout version written by this version of the crate
+    pub const VERSION: u32 = 1;
+
+    pub fn new() -> Self {
+        TeachFile {
+            version: Self::VERSION,
+            locations: BTreeMap::new(),
+            profiles: Vec::new(),
+            stations: Vec::new(),
+        }
+    }
+
+    /// Creates a file from the points captured during a teaching session
+    /// # Arguments
+    /// * `points` - Points captured during the session
+    /// * `first_id` - Location index of the first point, following points are numbered in order
+    pub fn from_points(points: &TaughtPoints, first_id: i32) -> Self {
+        let mut id = first_id;
+        let mut file = TeachFile::new();
+        for (name, pose) in &points.locations {
+            file.locations.insert(name.clone(), pose.to_waypoint(id));
+            id += 1;
+        }
+        for (name, pose) in &points.stations {
+            file.stations.push(pose.to_station(name, id));
+            id += 1;
+        }
+        file
+    }
+
+    /// Brings a file read from disk up to the current layout version
+    /// # Returns
+    /// * A RobotError if the file was written by a newer version of the crate
+    pub fn migrate(self) -> Result<Self, RobotError> {
+        match self.version {
+            0 => Err("Teach file has no valid version".to_string()),
+            version if version > Self::VERSION => Err(format!(
+                "Teach file version {} is newer than the supported version {}",
+                version,
+                Self::VERSION
+            )),
+            _ => Ok(self),
+        }
+    }
+
+    /// Stores every profile, location and station in the file on the robot
+    /// # Arguments
+    /// * `robot` - The robot to store the taught points on
+    pub fn upload(&self, robot: &mut PFlexRobot) -> Result<(), RobotError> {
+        info!("TeachFile::upload called");
+        for profile in &self.profiles {
+            robot.create_motion_profile(profile.clone())?;
+        }
+        for waypoint in self.locations.values() {
+            robot.create_waypoint(waypoint.clone())?;
+        }
+        for station in &self.stations {
+            robot.create_station(station)?;
+        }
+        Ok(())
+    }
+
+    /// Replaces every profile and location in the file with the copy stored on the robot, e.g.
+    /// after they were adjusted on the controller. Rail positions are kept since the controller
+    /// doesn't store them, and stations are left as they are since they're stored converted out
+    /// of their frame.
+    /// # Arguments
+    /// * `robot` - The robot to read the taught points from
+    pub fn download(&mut self, robot: &mut PFlexRobot) -> Result<(), RobotError> {
+        info!("TeachFile::download called");
+        for profile in self.profiles.iter_mut() {
+            *profile = robot.get_motion_profile(profile.id)?;
+        }
+        for waypoint in self.locations.values_mut() {
+            *waypoint = robot.get_waypoint(waypoint.id, waypoint.rail_position_mm)?;
+        }
+        Ok(())
+    }
+}
+
+impl Default for TeachFile {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// Free-mode teaching session
+///
+/// The selected joints are put into free mode when the session starts so the operator can move
+/// the arm by hand. Normal mode is restored when the session is finished or dropped, which
+/// includes unwinding from a panic.
+pub struct TeachSession<'a> {
+    robot: &'a mut PFlexRobot,
+    points: TaughtPoints,
+    active: bool,
+}
+
+impl<'a> TeachSession<'a> {
+    const PROMPT: &'static str = "teach> ";
+    const HELP: &'static str = "commands: loc <name> | station <name> | where | done";
+
+    /// Starts a teaching session
+    /// # Arguments
+    /// * `robot` - The robot to teach
+    /// * `joints` - Joints to put into free mode, every joint is released if empty
+    /// * `allow_gravity_loaded` - Whether joints that fall under gravity, i.e. Z, may be released
+    pub fn start(
+        robot: &'a mut PFlexRobot,
+        joints: &[Joint],
+        allow_gravity_loaded: bool,
+    ) -> Result<Self, RobotError> {
+        info!("TeachSession::start called");
+        robot.set_free_mode_joints(joints, allow_gravity_loaded)?;
+        Ok(TeachSession {
+            robot,
+            points: TaughtPoints::default(),
+            active: true,
+        })
+    }
+
+    /// Reads the current pose of the robot
+    pub fn current_pose(&mut self) -> Result<TaughtPose, RobotError> {
+        let cartesian = self.robot.get_endeffector_position()?;
+        let joints = self.robot.get_joint_positions()?;
+        Ok(TaughtPose { cartesian, joints })
+    }
+
+    /// Captures the current pose as a named location, replacing any previous capture
+    /// # Arguments
+    /// * `name` - Name of the location
+    pub fn capture_location(&mut self, name: &str) -> Result<TaughtPose, RobotError> {
+        let pose = self.current_pose()?;
+        debug!("captured location {}: {:?}", name, pose);
+        self.points.locations.insert(name.to_string(), pose.clone());
+        Ok(pose)
+    }
+
+    /// Captures the current pose as a named station, replacing any previous capture
+    /// # Arguments
+    /// * `name` - Name of the station
+    pub fn capture_station(&mut self, name: &str) -> Result<TaughtPose, RobotError> {
+        let pose = self.current_pose()?;
+        debug!("captured station {}: {:?}", name, pose);
+        self.points.stations.insert(name.to_string(), pose.clone());
+        Ok(pose)
+    }
+
+    /// Everything captured so far
+    pub fn points(&self) -> &TaughtPoints {
+        &self.points
+    }
+
+    /// Runs the session until the callback returns TeachAction::Finish
+    /// # Arguments
+    /// * `next_action` - Called with the points captured so far, returns the next action to take
+    pub fn run_with<F>(&mut self, mut next_action: F) -> Result<(), RobotError>
+    where
+        F: FnMut(&TaughtPoints) -> TeachAction,
+    {
+        loop {
+            match next_action(&self.points) {
+                TeachAction::Finish => return Ok(()),
+                action => {
+                    self.apply(&action)?;
+                }
+            }
+        }
+    }
+
+    /// Runs the session from operator input, one action per line, until `done` or end of input
+    /// # Arguments
+    /// * `input` - Where the operator's commands are read from, e.g. stdin
+    /// * `output` - Where prompts and captured poses are written to, e.g. stdout
+    pub fn run_interactive<R: BufRead, W: Write>(
+        &mut self,
+        input: R,
+        mut output: W,
+    ) -> Result<(), RobotError> {
+        let write_err = |e: std::io::Error| e.to_string();
+        writeln!(output, "{}", Self::HELP).map_err(write_err)?;
+        write!(output, "{}", Self::PROMPT).map_err(write_err)?;
+        output.flush().map_err(write_err)?;
+        for line in input.lines() {
+            let line = line.map_err(|e| e.to_string())?;
+            match TeachAction::parse(&line) {
+                Some(TeachAction::Finish) => return Ok(()),
+                Some(action) => match self.apply(&action) {
+                    Ok(pose) => writeln!(output, "{:?}", pose.cartesian).map_err(write_err)?,
+                    Err(e) => writeln!(output, "error: {}", e).map_err(write_err)?,
+                },
+                None if line.trim().is_empty() => {}
+                None => writeln!(output, "{}", Self::HELP).map_err(write_err)?,
+            }
+            write!(output, "{}", Self::PROMPT).map_err(write_err)?;
+            output.flush().map_err(write_err)?;
+        }
+        Ok(())
+    }
+
+    /// Restores normal mode and returns everything that was captured
+    pub fn finish(mut self) -> Result<TaughtPoints, RobotError> {
+        info!("TeachSession::finish called");
+        self.restore()?;
+        Ok(std::mem::take(&mut self.points))
+    }
+
+    fn apply(&mut self, action: &TeachAction) -> Result<TaughtPose, RobotError> {
+        match action {
+            TeachAction::CaptureLocation(name) => self.capture_location(name),
+            TeachAction::CaptureStation(name) => self.capture_station(name),
+            TeachAction::Where | TeachAction::Finish => self.current_pose(),
+        }
+    }
+
+    fn restore(&mut self) -> Result<(), RobotError> {
+        if !self.active {
+            return Ok(());
+        }
+        self.active = false;
+        self.robot.set_free_mode(false)
+    }
+}
+
+impl Drop for TeachSession<'_> {
+    fn drop(&mut self) {
+        if let Err(e) = self.restore() {
+            debug!("Failed to restore normal mode: {}", e);
+        }
+    }
+}
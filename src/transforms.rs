@@ -0,0 +1,219 @@
+use crate::error_codes::RobotError;
+use crate::structs::EndEffectorPosition;
+use crate::units::Degrees;
+use std::ops::Mul;
+
+/// A point in millimetres
+pub type Point = [f64; 3];
+
+/// Rigid body transform made up of a rotation matrix and a translation in millimetres
+///
+/// Orientations follow the controller's yaw, pitch, roll convention which are Z-Y-Z Euler angles:
+/// a rotation of yaw about Z, then pitch about the new Y axis, then roll about the new Z axis.
+#[derive(Debug, Clone, Copy, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct Transform {
+    pub rotation: [[f64; 3]; 3],
+    pub translation: Point,
+}
+
+impl Transform {
+    pub const IDENTITY: Transform = Transform {
+        rotation: [[1.0, 0.0, 0.0], [0.0, 1.0, 0.0], [0.0, 0.0, 1.0]],
+        translation: [0.0, 0.0, 0.0],
+    };
+
+    const EPSILON: f64 = 1e-9;
+
+    /// Creates a transform that only translates
+    pub fn from_translation(x_mm: f64, y_mm: f64, z_mm: f64) -> Self {
+        Transform {
+            translation: [x_mm, y_mm, z_mm],
+            ..Self::IDENTITY
+        }
+    }
+
+    /// Creates a transform from a translation and a rotation about the Z axis
+    pub fn from_xyz_yaw(x_mm: f64, y_mm: f64, z_mm: f64, yaw_deg: f64) -> Self {
+        Transform {
+            rotation: rot_z(yaw_deg),
+            translation: [x_mm, y_mm, z_mm],
+        }
+    }
+
+    /// Creates a transform from an end effector position
+    pub fn from_pose(pose: &EndEffectorPosition) -> Self {
+        let rotation = mat_mul(
+            &mat_mul(&rot_z(pose.yaw_deg.deg()), &rot_y(pose.pitch_deg.deg())),
+            &rot_z(pose.roll_deg.deg()),
+        );
+        Transform {
+            rotation,
+            translation: [pose.x_mm.mm(), pose.y_mm.mm(), pose.z_mm.mm()],
+        }
+    }
+
+    /// Converts the transform back into an end effector position
+    pub fn to_pose(&self) -> EndEffectorPosition {
+        let r = &self.rotation;
+        let sin_pitch = (r[2][0] * r[2][0] + r[2][1] * r[2][1]).sqrt();
+        let pitch = sin_pitch.atan2(r[2][2]);
+        let (yaw, roll) = if sin_pitch > Self::EPSILON {
+            (r[1][2].atan2(r[0][2]), r[2][1].atan2(-r[2][0]))
+        } else if r[2][2] > 0.0 {
+            // pitch is 0 so yaw and roll rotate about the same axis
+            (r[1][0].atan2(r[0][0]), 0.0)
+        } else {
+            // pitch is 180 so yaw and roll rotate about opposite axes
+            ((-r[1][0]).atan2(-r[0][0]), 0.0)
+        };
+        EndEffectorPosition {
+            x_mm: self.translation[0].into(),
+            y_mm: self.translation[1].into(),
+            z_mm: self.translation[2].into(),
+            yaw_deg: Degrees::from_radians(yaw),
+            pitch_deg: Degrees::from_radians(pitch),
+            roll_deg: Degrees::from_radians(roll),
+        }
+    }
+
+    /// Creates a frame from three measured points
+    /// # Arguments
+    /// * `origin` - Origin of the frame
+    /// * `x_axis_point` - Any point along the positive X axis of the frame
+    /// * `xy_plane_point` - Any point in the XY plane of the frame with a positive Y value
+    /// # Returns
+    /// * A RobotError if the points are coincident or collinear
+    pub fn from_three_points(
+        origin: Point,
+        x_axis_point: Point,
+        xy_plane_point: Point,
+    ) -> Result<Self, RobotError> {
+        let x_axis = normalise(sub(x_axis_point, origin))
+            .ok_or("Origin and X axis points are coincident")?;
+        let z_axis = normalise(cross(x_axis, sub(xy_plane_point, origin)))
+            .ok_or("Reference points are collinear")?;
+        let y_axis = cross(z_axis, x_axis);
+        Ok(Transform {
+            rotation: [
+                [x_axis[0], y_axis[0], z_axis[0]],
+                [x_axis[1], y_axis[1], z_axis[1]],
+                [x_axis[2], y_axis[2], z_axis[2]],
+            ],
+            translation: origin,
+        })
+    }
+
+    /// Calculates the correction between an instrument's original and re-measured position
+    ///
+    /// Applying the returned transform to anything taught against the original reference points
+    /// moves it to where the instrument is now.
+    /// # Arguments
+    /// * `original` - Origin, X axis and XY plane points measured when the instrument was taught
+    /// * `measured` - The same three points measured again
+    pub fn reteach(original: [Point; 3], measured: [Point; 3]) -> Result<Self, RobotError> {
+        let original = Self::from_three_points(original[0], original[1], original[2])?;
+        let measured = Self::from_three_points(measured[0], measured[1], measured[2])?;
+        Ok(measured * original.inverse())
+    }
+
+    /// Applies `other` in the frame of this transform
+    pub fn compose(&self, other: &Transform) -> Transform {
+        Transform {
+            rotation: mat_mul(&self.rotation, &other.rotation),
+            translation: self.transform_point(other.translation),
+        }
+    }
+
+    /// The transform that undoes this one
+    pub fn inverse(&self) -> Transform {
+        let r = &self.rotation;
+        let rotation = [
+            [r[0][0], r[1][0], r[2][0]],
+            [r[0][1], r[1][1], r[2][1]],
+            [r[0][2], r[1][2], r[2][2]],
+        ];
+        let t = mat_vec(&rotation, self.translation);
+        Transform {
+            rotation,
+            translation: [-t[0], -t[1], -t[2]],
+        }
+    }
+
+    /// Transforms a point expressed in this frame into the parent frame
+    pub fn transform_point(&self, point: Point) -> Point {
+        let p = mat_vec(&self.rotation, point);
+        [
+            p[0] + self.translation[0],
+            p[1] + self.translation[1],
+            p[2] + self.translation[2],
+        ]
+    }
+
+    /// Transforms a pose expressed in this frame into the parent frame
+    pub fn transform_pose(&self, pose: &EndEffectorPosition) -> EndEffectorPosition {
+        self.compose(&Transform::from_pose(pose)).to_pose()
+    }
+}
+
+impl Default for Transform {
+    fn default() -> Self {
+        Self::IDENTITY
+    }
+}
+
+impl Mul for Transform {
+    type Output = Transform;
+
+    fn mul(self, rhs: Transform) -> Transform {
+        self.compose(&rhs)
+    }
+}
+
+fn rot_z(angle_deg: f64) -> [[f64; 3]; 3] {
+    let (s, c) = angle_deg.to_radians().sin_cos();
+    [[c, -s, 0.0], [s, c, 0.0], [0.0, 0.0, 1.0]]
+}
+
+fn rot_y(angle_deg: f64) -> [[f64; 3]; 3] {
+    let (s, c) = angle_deg.to_radians().sin_cos();
+    [[c, 0.0, s], [0.0, 1.0, 0.0], [-s, 0.0, c]]
+}
+
+fn mat_mul(a: &[[f64; 3]; 3], b: &[[f64; 3]; 3]) -> [[f64; 3]; 3] {
+    let mut out = [[0.0; 3]; 3];
+    for (i, row) in out.iter_mut().enumerate() {
+        for (j, value) in row.iter_mut().enumerate() {
+            *value = (0..3).map(|k| a[i][k] * b[k][j]).sum();
+        }
+    }
+    out
+}
+
+fn mat_vec(m: &[[f64; 3]; 3], v: Point) -> Point {
+    [
+        m[0][0] * v[0] + m[0][1] * v[1] + m[0][2] * v[2],
+        m[1][0] * v[0] + m[1][1] * v[1] + m[1][2] * v[2],
+        m[2][0] * v[0] + m[2][1] * v[1] + m[2][2] * v[2],
+    ]
+}
+
+fn sub(a: Point, b: Point) -> Point {
+    [a[0] - b[0], a[1] - b[1], a[2] - b[2]]
+}
+
+fn cross(a: Point, b: Point) -> Point {
+    [
+        a[1] * b[2] - a[2] * b[1],
+        a[2] * b[0] - a[0] * b[2],
+        a[0] * b[1] - a[1] * b[0],
+    ]
+}
+
+fn normalise(v: Point) -> Option<Point> {
+    let length = (v[0] * v[0] + v[1] * v[1] + v[2] * v[2]).sqrt();
+    if length < Transform::EPSILON {
+        return None;
+    }
+    Some([v[0] / length, v[1] / length, v[2] / length])
+}
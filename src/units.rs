@@ -0,0 +1,321 @@
+use crate::error_codes::RobotError;
+use std::fmt;
+use std::ops::{Add, AddAssign, Neg, Sub};
+use std::time::Duration;
+
+/// Mass held by the robot, stored and serialized in kilograms
+#[derive(Debug, Clone, Copy, PartialEq, PartialOrd, Default)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(feature = "serde", serde(transparent))]
+pub struct Mass(f64);
+
+impl Mass {
+    pub fn from_kg(kg: f64) -> Self {
+        Mass(kg)
+    }
+
+    pub fn from_grams(grams: f64) -> Self {
+        Mass(grams / 1000.0)
+    }
+
+    /// Get the mass in kilograms
+    pub fn kg(&self) -> f64 {
+        self.0
+    }
+
+    /// Get the mass in grams
+    pub fn grams(&self) -> f64 {
+        self.0 * 1000.0
+    }
+}
+
+impl fmt::Display for Mass {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{} kg", self.0)
+    }
+}
+
+/// Distance in millimetres, used for Cartesian positions and linear joints
+#[derive(Debug, Clone, Copy, PartialEq, PartialOrd, Default)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(feature = "serde", serde(transparent))]
+pub struct Millimetres(f64);
+
+impl Millimetres {
+    pub const ZERO: Millimetres = Millimetres(0.0);
+
+    pub const fn from_mm(mm: f64) -> Self {
+        Millimetres(mm)
+    }
+
+    /// Get the distance in millimetres
+    pub fn mm(&self) -> f64 {
+        self.0
+    }
+
+    /// Get the absolute distance
+    pub fn abs(&self) -> Self {
+        Millimetres(self.0.abs())
+    }
+}
+
+impl From<f64> for Millimetres {
+    fn from(mm: f64) -> Self {
+        Millimetres(mm)
+    }
+}
+
+impl From<Millimetres> for f64 {
+    fn from(mm: Millimetres) -> Self {
+        mm.0
+    }
+}
+
+impl Add for Millimetres {
+    type Output = Millimetres;
+
+    fn add(self, rhs: Millimetres) -> Millimetres {
+        Millimetres(self.0 + rhs.0)
+    }
+}
+
+impl Sub for Millimetres {
+    type Output = Millimetres;
+
+    fn sub(self, rhs: Millimetres) -> Millimetres {
+        Millimetres(self.0 - rhs.0)
+    }
+}
+
+impl Neg for Millimetres {
+    type Output = Millimetres;
+
+    fn neg(self) -> Millimetres {
+        Millimetres(-self.0)
+    }
+}
+
+impl AddAssign for Millimetres {
+    fn add_assign(&mut self, rhs: Millimetres) {
+        self.0 += rhs.0;
+    }
+}
+
+impl fmt::Display for Millimetres {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{} mm", self.0)
+    }
+}
+
+/// Angle in degrees, used for orientations and rotary joints
+#[derive(Debug, Clone, Copy, PartialEq, PartialOrd, Default)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(feature = "serde", serde(transparent))]
+pub struct Degrees(f64);
+
+impl Degrees {
+    pub const ZERO: Degrees = Degrees(0.0);
+
+    pub const fn from_deg(deg: f64) -> Self {
+        Degrees(deg)
+    }
+
+    pub fn from_radians(radians: f64) -> Self {
+        Degrees(radians.to_degrees())
+    }
+
+    /// Get the angle in degrees
+    pub fn deg(&self) -> f64 {
+        self.0
+    }
+
+    /// Get the angle in radians
+    pub fn radians(&self) -> f64 {
+        self.0.to_radians()
+    }
+
+    /// Get the absolute angle
+    pub fn abs(&self) -> Self {
+        Degrees(self.0.abs())
+    }
+}
+
+impl From<f64> for Degrees {
+    fn from(deg: f64) -> Self {
+        Degrees(deg)
+    }
+}
+
+impl From<Degrees> for f64 {
+    fn from(deg: Degrees) -> Self {
+        deg.0
+    }
+}
+
+impl Add for Degrees {
+    type Output = Degrees;
+
+    fn add(self, rhs: Degrees) -> Degrees {
+        Degrees(self.0 + rhs.0)
+    }
+}
+
+impl Sub for Degrees {
+    type Output = Degrees;
+
+    fn sub(self, rhs: Degrees) -> Degrees {
+        Degrees(self.0 - rhs.0)
+    }
+}
+
+impl Neg for Degrees {
+    type Output = Degrees;
+
+    fn neg(self) -> Degrees {
+        Degrees(-self.0)
+    }
+}
+
+impl AddAssign for Degrees {
+    fn add_assign(&mut self, rhs: Degrees) {
+        self.0 += rhs.0;
+    }
+}
+
+impl fmt::Display for Degrees {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{} deg", self.0)
+    }
+}
+
+/// Percentage between 0 and 100, used for speeds, accelerations and forces
+#[derive(Debug, Clone, Copy, PartialEq, PartialOrd, Default)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(feature = "serde", serde(try_from = "f64", into = "f64"))]
+pub struct Percent(f64);
+
+impl Percent {
+    pub const MAX: Percent = Percent(100.0);
+
+    /// Creates a percentage known to be in range at compile time
+    pub(crate) const fn from_const(percent: f64) -> Self {
+        Percent(percent)
+    }
+
+    /// Creates a percentage
+    /// # Arguments
+    /// * `percent` - Value between 0 and 100
+    /// # Returns
+    /// * A RobotError if the value is out of range
+    pub fn new(percent: f64) -> Result<Self, RobotError> {
+        if !(0.0..=100.0).contains(&percent) {
+            return Err(format!("{} is not a percentage between 0 and 100", percent));
+        }
+        Ok(Percent(percent))
+    }
+
+    /// Creates a percentage from a fraction between 0 and 1
+    pub fn from_fraction(fraction: f64) -> Result<Self, RobotError> {
+        Self::new(fraction * 100.0)
+    }
+
+    /// Get the percentage between 0 and 100
+    pub fn value(&self) -> f64 {
+        self.0
+    }
+
+    /// Get the percentage as a fraction between 0 and 1
+    pub fn fraction(&self) -> f64 {
+        self.0 / 100.0
+    }
+}
+
+impl TryFrom<f64> for Percent {
+    type Error = RobotError;
+
+    fn try_from(percent: f64) -> Result<Self, RobotError> {
+        Percent::new(percent)
+    }
+}
+
+impl TryFrom<i32> for Percent {
+    type Error = RobotError;
+
+    fn try_from(percent: i32) -> Result<Self, RobotError> {
+        Percent::new(percent as f64)
+    }
+}
+
+impl From<Percent> for f64 {
+    fn from(percent: Percent) -> Self {
+        percent.0
+    }
+}
+
+impl fmt::Display for Percent {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}%", self.0)
+    }
+}
+
+/// Non-negative duration in seconds, used for ramps and timeouts
+#[derive(Debug, Clone, Copy, PartialEq, PartialOrd, Default)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(feature = "serde", serde(try_from = "f64", into = "f64"))]
+pub struct Seconds(f64);
+
+impl Seconds {
+    /// Creates a duration
+    /// # Arguments
+    /// * `seconds` - Finite, non-negative number of seconds
+    /// # Returns
+    /// * A RobotError if the value is negative or not finite
+    pub fn new(seconds: f64) -> Result<Self, RobotError> {
+        if !seconds.is_finite() || seconds < 0.0 {
+            return Err(format!("{} is not a valid number of seconds", seconds));
+        }
+        Ok(Seconds(seconds))
+    }
+
+    pub const fn from_millis(millis: u64) -> Self {
+        Seconds(millis as f64 / 1000.0)
+    }
+
+    /// Get the duration in seconds
+    pub fn secs(&self) -> f64 {
+        self.0
+    }
+
+    /// Get the duration as a std Duration
+    /// # Returns
+    /// * A RobotError if the duration is too long for a std Duration
+    pub fn to_duration(&self) -> Result<Duration, RobotError> {
+        Duration::try_from_secs_f64(self.0).map_err(|_| format!("{} is too long a duration", self))
+    }
+}
+
+impl TryFrom<f64> for Seconds {
+    type Error = RobotError;
+
+    fn try_from(seconds: f64) -> Result<Self, RobotError> {
+        Seconds::new(seconds)
+    }
+}
+
+impl From<Duration> for Seconds {
+    fn from(duration: Duration) -> Self {
+        Seconds(duration.as_secs_f64())
+    }
+}
+
+impl From<Seconds> for f64 {
+    fn from(seconds: Seconds) -> Self {
+        seconds.0
+    }
+}
+
+impl fmt::Display for Seconds {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{} s", self.0)
+    }
+}
@@ -0,0 +1,263 @@
+use crate::enums::Joint;
+use crate::error_codes::RobotError;
+use crate::structs::{EndEffectorPosition, JointPosition};
+use crate::transforms::Point;
+use std::fs;
+use std::path::Path;
+use std::str::FromStr;
+
+/// Allowed travel of a single joint
+#[derive(Debug, Clone, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct JointLimit {
+    pub joint: Joint,
+    pub min: f64,
+    pub max: f64,
+}
+
+/// Shape of a Cartesian zone, in millimetres in the world frame
+#[derive(Debug, Clone, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(feature = "serde", serde(rename_all = "lowercase"))]
+pub enum ZoneShape {
+    /// Axis aligned box between two corners
+    Box { min: Point, max: Point },
+    /// Vertical cylinder
+    Cylinder {
+        centre: [f64; 2],
+        radius_mm: f64,
+        z_min_mm: f64,
+        z_max_mm: f64,
+    },
+}
+
+impl ZoneShape {
+    /// Whether a point lies inside or on the boundary of the shape
+    pub fn contains(&self, point: Point) -> bool {
+        match self {
+            ZoneShape::Box { min, max } => (0..3).all(|i| min[i] <= point[i] && point[i] <= max[i]),
+            ZoneShape::Cylinder {
+                centre,
+                radius_mm,
+                z_min_mm,
+                z_max_mm,
+            } => {
+                let dx = point[0] - centre[0];
+                let dy = point[1] - centre[1];
+                (dx * dx + dy * dy).sqrt() <= *radius_mm
+                    && *z_min_mm <= point[2]
+                    && point[2] <= *z_max_mm
+            }
+        }
+    }
+}
+
+/// A named Cartesian zone
+#[derive(Debug, Clone, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct Zone {
+    pub name: String,
+    pub shape: ZoneShape,
+}
+
+/// Client-side model of where the robot is allowed to go
+///
+/// Poses must lie inside at least one of the `bounds` zones (if any are defined) and outside
+/// every `keep_out` zone, and joints must lie within their limits.
+///
+/// Workspaces can be loaded from a text file with one entry per line, blank lines and anything
+/// after a `#` are ignored:
+/// ```text
+/// joint <joint> <min> <max>
+/// bounds <name> box <x min> <y min> <z min> <x max> <y max> <z max>
+/// keepout <name> box <x min> <y min> <z min> <x max> <y max> <z max>
+/// keepout <name> cylinder <centre x> <centre y> <radius> <z min> <z max>
+/// ```
+#[derive(Debug, Clone, Default, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct Workspace {
+    pub joint_limits: Vec<JointLimit>,
+    pub bounds: Vec<Zone>,
+    pub keep_out: Vec<Zone>,
+}
+
+impl Workspace {
+    /// Loads a workspace from a file
+    /// # Arguments
+    /// * `path` - Path to the workspace file
+    pub fn load<P: AsRef<Path>>(path: P) -> Result<Self, RobotError> {
+        let contents = fs::read_to_string(path.as_ref())
+            .map_err(|e| format!("Failed to read {}: {}", path.as_ref().display(), e))?;
+        Self::parse(&contents)
+    }
+
+    /// Parses a workspace from the contents of a workspace file
+    /// # Arguments
+    /// * `contents` - The workspace definition
+    pub fn parse(contents: &str) -> Result<Self, RobotError> {
+        let mut workspace = Workspace::default();
+        for (index, line) in contents.lines().enumerate() {
+            let line = line.split('#').next().unwrap_or_default();
+            let parts = line.split_whitespace().collect::<Vec<&str>>();
+            if parts.is_empty() {
+                continue;
+            }
+            workspace
+                .parse_entry(&parts)
+                .map_err(|e| format!("line {}: {}", index + 1, e))?;
+        }
+        Ok(workspace)
+    }
+
+    fn parse_entry(&mut self, parts: &[&str]) -> Result<(), RobotError> {
+        match parts {
+            ["joint", joint, min, max] => {
+                let joint =
+                    Joint::from_str(joint).map_err(|_| format!("Unknown joint: {}", joint))?;
+                let (min, max) = (parse_number(min)?, parse_number(max)?);
+                if min > max {
+                    return Err(format!("Joint {:?} has min above max", joint));
+                }
+                self.joint_limits.push(JointLimit { joint, min, max });
+            }
+            ["bounds", name, shape @ ..] => self.bounds.push(parse_zone(name, shape)?),
+            ["keepout", name, shape @ ..] => self.keep_out.push(parse_zone(name, shape)?),
+            _ => return Err(format!("Unrecognised entry: {}", parts.join(" "))),
+        }
+        Ok(())
+    }
+
+    /// Finds a bounds or keep-out zone by name
+    /// # Arguments
+    /// * `name` - Name of the zone
+    pub fn zone(&self, name: &str) -> Option<&Zone> {
+        self.bounds
+            .iter()
+            .chain(self.keep_out.iter())
+            .find(|zone| zone.name == name)
+    }
+
+    /// Checks a point against the bounds and keep-out zones
+    /// # Arguments
+    /// * `point` - Point in the world frame
+    /// # Returns
+    /// * A RobotError naming the zone that was violated
+    pub fn check_point(&self, point: Point) -> Result<(), RobotError> {
+        if !self.bounds.is_empty() && !self.bounds.iter().any(|zone| zone.shape.contains(point)) {
+            let names = self
+                .bounds
+                .iter()
+                .map(|zone| zone.name.as_str())
+                .collect::<Vec<&str>>();
+            return Err(format!(
+                "Point {:?} is outside the workspace bounds {}",
+                point,
+                names.join(", ")
+            ));
+        }
+        match self.keep_out.iter().find(|zone| zone.shape.contains(point)) {
+            Some(zone) => Err(format!(
+                "Point {:?} is inside keep-out zone {}",
+                point, zone.name
+            )),
+            None => Ok(()),
+        }
+    }
+
+    /// Checks an end effector position against the bounds and keep-out zones
+    /// # Arguments
+    /// * `pose` - Pose in the world frame
+    pub fn check_pose(&self, pose: &EndEffectorPosition) -> Result<(), RobotError> {
+        self.check_point([pose.x_mm.mm(), pose.y_mm.mm(), pose.z_mm.mm()])
+    }
+
+    /// Whether an end effector position is clear of every zone
+    /// # Arguments
+    /// * `pose` - Pose in the world frame
+    pub fn is_pose_safe(&self, pose: &EndEffectorPosition) -> bool {
+        self.check_pose(pose).is_ok()
+    }
+
+    /// Checks a single joint value against its limit
+    /// # Arguments
+    /// * `joint` - The joint being moved
+    /// * `value` - Target value in millimetres or degrees
+    pub fn check_joint(&self, joint: Joint, value: f64) -> Result<(), RobotError> {
+        for limit in self
+            .joint_limits
+            .iter()
+            .filter(|limit| limit.joint == joint)
+        {
+            if value < limit.min || value > limit.max {
+                return Err(format!(
+                    "Joint {:?} target {} is outside its limit of {} to {}",
+                    joint, value, limit.min, limit.max
+                ));
+            }
+        }
+        Ok(())
+    }
+
+    /// Checks every joint of a joint position against its limit
+    /// # Arguments
+    /// * `joints` - Target joint position
+    pub fn check_joints(&self, joints: &JointPosition) -> Result<(), RobotError> {
+        self.check_joint(Joint::Z, joints.z_mm.mm())?;
+        self.check_joint(Joint::Shoulder, joints.shoulder_deg.deg())?;
+        self.check_joint(Joint::Elbow, joints.elbow_deg.deg())?;
+        self.check_joint(Joint::Wrist, joints.wrist_deg.deg())?;
+        self.check_joint(Joint::Gripper, joints.gripper_mm.mm())?;
+        if let Some(rail_mm) = joints.rail_mm {
+            self.check_joint(Joint::Rail, rail_mm.mm())?;
+        }
+        Ok(())
+    }
+}
+
+// NaN and infinite bounds would make every comparison against them pass or fail
+fn parse_number(value: &str) -> Result<f64, RobotError> {
+    value
+        .parse::<f64>()
+        .ok()
+        .filter(|number| number.is_finite())
+        .ok_or(format!("Invalid number: {}", value))
+}
+
+fn parse_zone(name: &str, shape: &[&str]) -> Result<Zone, RobotError> {
+    let shape = match shape {
+        ["box", values @ ..] if values.len() == 6 => {
+            let v = values
+                .iter()
+                .map(|value| parse_number(value))
+                .collect::<Result<Vec<f64>, RobotError>>()?;
+            let (min, max) = ([v[0], v[1], v[2]], [v[3], v[4], v[5]]);
+            if (0..3).any(|i| min[i] > max[i]) {
+                return Err(format!(
+                    "Box {} has a min corner above its max corner",
+                    name
+                ));
+            }
+            ZoneShape::Box { min, max }
+        }
+        ["cylinder", values @ ..] if values.len() == 5 => {
+            let v = values
+                .iter()
+                .map(|value| parse_number(value))
+                .collect::<Result<Vec<f64>, RobotError>>()?;
+            if v[2] < 0.0 || v[3] > v[4] {
+                return Err(format!("Cylinder {} has invalid dimensions", name));
+            }
+            ZoneShape::Cylinder {
+                centre: [v[0], v[1]],
+                radius_mm: v[2],
+                z_min_mm: v[3],
+                z_max_mm: v[4],
+            }
+        }
+        _ => return Err(format!("Invalid shape for zone {}", name)),
+    };
+    Ok(Zone {
+        name: name.to_string(),
+        shape,
+    })
+}
@@ -0,0 +1,187 @@
+use pflex_module_rs::cli::{CtlCommand, CtlOptions, CtlReport};
+use pflex_module_rs::enums::Joint;
+use pflex_module_rs::mock::MockRobot;
+use pflex_module_rs::units::{Millimetres, Percent};
+use std::collections::BTreeSet;
+use std::thread::sleep;
+use std::time::{Duration, Instant};
+
+fn args(line: &str) -> Vec<String> {
+    line.split_whitespace().map(str::to_string).collect()
+}
+
+fn options(mock: &MockRobot, line: &str) -> CtlOptions {
+    CtlOptions::from_args(args(&format!("--port {} {}", mock.port(), line)))
+        .expect("Command should parse")
+}
+
+fn run(mock: &MockRobot, line: &str) -> Result<CtlReport, String> {
+    let options = options(mock, line);
+    let mut robot = options.connect().expect("Failed to connect");
+    options.command.run(&mut robot)
+}
+
+// free mode isn't replied to, so it may be carried out after the command returns
+fn wait_for_free_mode(mock: &MockRobot) -> BTreeSet<i32> {
+    let deadline = Instant::now() + Duration::from_secs(1);
+    while !mock.state().free_mode && Instant::now() < deadline {
+        sleep(Duration::from_millis(10));
+    }
+    mock.state().free_joints.clone()
+}
+
+#[test]
+fn flags_and_commands_parse() {
+    let options = CtlOptions::from_args(args(
+        "--host 10.5.2.12 --robot 2 --no-rail --timeout 2.5 move pose 350 -20.5 200 90 --profile 3 --json",
+    ))
+    .expect("Command should parse");
+    assert_eq!(options.host, "10.5.2.12");
+    assert_eq!(options.robot, 2);
+    assert!(!options.rail && options.json);
+    assert_eq!(options.timeout, Some(2.5));
+    match options.command {
+        CtlCommand::MoveToPose {
+            pose,
+            motion_profile_id,
+        } => {
+            assert_eq!(pose.y_mm, Millimetres::from_mm(-20.5));
+            assert_eq!(motion_profile_id, 3);
+        }
+        command => panic!("Unexpected command: {:?}", command),
+    }
+
+    let parse = |line: &str| CtlCommand::parse(&line.split_whitespace().collect::<Vec<&str>>());
+    assert_eq!(parse("connect"), Ok(CtlCommand::Status));
+    assert_eq!(parse("power off"), Ok(CtlCommand::Power(false)));
+    assert_eq!(parse("where"), Ok(CtlCommand::WhereCartesian));
+    assert_eq!(parse("where joints"), Ok(CtlCommand::WhereJoints));
+    assert_eq!(
+        parse("gripper open"),
+        Ok(CtlCommand::GripperOpen {
+            width: CtlCommand::DEFAULT_OPEN_MM,
+            finger_speed: Percent::new(50.0).unwrap(),
+        })
+    );
+    assert_eq!(
+        parse("free on"),
+        Ok(CtlCommand::FreeMode {
+            free_on: true,
+            with_z: false
+        })
+    );
+    assert_eq!(
+        parse("free on --with-z"),
+        Ok(CtlCommand::FreeMode {
+            free_on: true,
+            with_z: true
+        })
+    );
+    assert_eq!(
+        parse("speed 80"),
+        Ok(CtlCommand::SetSpeed(Percent::new(80.0).unwrap()))
+    );
+    // robots are only taken to be on a rail when they are said to be
+    assert!(!CtlOptions::from_args(args("status")).unwrap().rail);
+    assert!(CtlOptions::from_args(args("--rail status")).unwrap().rail);
+    // flags after raw are part of the request
+    let options = CtlOptions::from_args(args("raw pd 2800 --json")).unwrap();
+    assert_eq!(
+        options.command,
+        CtlCommand::Raw("pd 2800 --json".to_string())
+    );
+    assert!(!options.json);
+
+    assert!(parse("").is_err());
+    assert!(parse("power maybe").is_err());
+    assert!(parse("speed 150").is_err());
+    assert!(parse("move loc").is_err());
+    assert!(parse("move loc 5 --force 10").is_err());
+    assert!(parse("gripper close").is_err());
+    assert!(parse("raw").is_err());
+    assert!(parse("fly").is_err());
+    assert!(CtlOptions::from_args(args("--port")).is_err());
+    for timeout in ["0", "-1", "NaN", "inf"] {
+        let flags = format!("--timeout {} status", timeout);
+        assert!(CtlOptions::from_args(args(&flags)).is_err());
+    }
+}
+
+#[test]
+fn commands_drive_the_robot() {
+    let mock = MockRobot::start().expect("Failed to start mock robot");
+    mock.state().time_scale = 0.01;
+
+    assert_eq!(run(&mock, "power on"), Ok(CtlReport::Done));
+    assert_eq!(run(&mock, "attach"), Ok(CtlReport::Done));
+    assert_eq!(run(&mock, "home"), Ok(CtlReport::Done));
+    let status = match run(&mock, "status") {
+        Ok(CtlReport::Status(status)) => status,
+        report => panic!("Unexpected report: {:?}", report),
+    };
+    // each run has its own connection, and the robot is detached when it closes
+    assert!(status.powered && !status.attached && status.homed);
+    assert_eq!(status.system_speed, Percent::new(50.0).unwrap());
+
+    assert_eq!(run(&mock, "speed 100"), Ok(CtlReport::Done));
+    assert_eq!(run(&mock, "speed"), Ok(CtlReport::Speed(Percent::MAX)));
+    assert_eq!(run(&mock, "move pose 350 0 200 0"), Ok(CtlReport::Done));
+    match run(&mock, "where") {
+        Ok(CtlReport::Pose(pose)) => assert_eq!(pose.x_mm, Millimetres::from_mm(350.0)),
+        report => panic!("Unexpected report: {:?}", report),
+    }
+    assert!(matches!(
+        run(&mock, "where joints"),
+        Ok(CtlReport::Joints(_))
+    ));
+
+    mock.state().plate_mm = Some(127.0);
+    assert_eq!(run(&mock, "gripper open"), Ok(CtlReport::Done));
+    assert_eq!(
+        run(&mock, "gripper close 127 --force 30"),
+        Ok(CtlReport::Grasped(true))
+    );
+    assert_eq!(
+        run(&mock, "raw pd 2800 1 0 1"),
+        Ok(CtlReport::Reply(vec!["1".to_string()]))
+    );
+    assert_eq!(run(&mock, "halt"), Ok(CtlReport::Done));
+
+    // Z stays locked unless it is asked for
+    let z = Joint::Z.value();
+    assert_eq!(run(&mock, "free on"), Ok(CtlReport::Done));
+    let free_joints = wait_for_free_mode(&mock);
+    assert!(!free_joints.is_empty() && !free_joints.contains(&z));
+    assert_eq!(run(&mock, "free off"), Ok(CtlReport::Done));
+    assert!(!mock.state().free_mode);
+    assert_eq!(run(&mock, "free on --with-z"), Ok(CtlReport::Done));
+    assert!(wait_for_free_mode(&mock).contains(&z));
+    assert_eq!(run(&mock, "free off"), Ok(CtlReport::Done));
+
+    mock.state().press_estop();
+    let error = run(&mock, "power on").unwrap_err();
+    assert!(error.contains("-1"), "{}", error);
+}
+
+#[test]
+fn reports_print_as_text_and_json() {
+    let report = CtlReport::Grasped(true);
+    assert_eq!(report.to_string(), "grasped: yes");
+    assert_eq!(report.to_json(), serde_json::json!({ "grasped": true }));
+    assert_eq!(CtlReport::Done.to_json(), serde_json::json!({ "ok": true }));
+    let speed = CtlReport::Speed(Percent::new(50.0).unwrap());
+    assert_eq!(speed.to_string(), "50%");
+    assert_eq!(speed.to_json(), serde_json::json!({ "system_speed": 50.0 }));
+
+    let mock = MockRobot::start().expect("Failed to start mock robot");
+    let json = run(&mock, "where")
+        .expect("Failed to read the pose")
+        .to_json();
+    assert_eq!(json["pitch_deg"], serde_json::json!(90.0));
+    let text = run(&mock, "where joints").unwrap().to_string();
+    assert!(
+        text.starts_with("z: ") && text.contains("\nrail: "),
+        "{}",
+        text
+    );
+}
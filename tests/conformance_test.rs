@@ -0,0 +1,406 @@
+// Wire format of every PFlexRobot method
+//
+// Each robot talks to the simulator through a proxy that keeps every byte the client sends, so
+// the tests can assert the exact requests on the wire alongside the parsed results. Everything
+// runs in-process, no hardware is needed.
+
+use pflex_module_rs::enums::{CartesianAxis, JogFrame, Joint};
+use pflex_module_rs::mock::MockRobot;
+use pflex_module_rs::pflex::PFlexRobot;
+use pflex_module_rs::structs::{
+    EndEffectorPosition, JogStep, JointPosition, MotionProfile, Station, Waypoint,
+};
+use pflex_module_rs::units::{Degrees, Mass, Millimetres, Percent, Seconds};
+use std::io::{Read, Write};
+use std::net::{Shutdown, TcpListener, TcpStream};
+use std::sync::{Arc, Mutex};
+use std::thread;
+use std::time::{Duration, Instant};
+
+/// Simulator with a proxy in front of it recording the bytes sent by the client
+struct Wire {
+    mock: MockRobot,
+    port: u16,
+    sent: Arc<Mutex<Vec<u8>>>,
+}
+
+impl Wire {
+    fn start() -> Self {
+        let mock = MockRobot::start().expect("Failed to start mock robot");
+        mock.state().time_scale = 0.01;
+        let listener = TcpListener::bind("127.0.0.1:0").expect("Failed to bind proxy");
+        let port = listener.local_addr().unwrap().port();
+        let upstream = mock.address();
+        let sent = Arc::new(Mutex::new(Vec::new()));
+        let log = sent.clone();
+        thread::spawn(move || {
+            let (mut client, _) = listener.accept().expect("Failed to accept client");
+            let mut server = TcpStream::connect(upstream).expect("Failed to reach mock robot");
+            let mut replies = server.try_clone().unwrap();
+            let mut reply_to = client.try_clone().unwrap();
+            thread::spawn(move || {
+                let _ = std::io::copy(&mut replies, &mut reply_to);
+                let _ = reply_to.shutdown(Shutdown::Both);
+            });
+            let buffer = &mut [0; 1024];
+            while let Ok(bytes_read @ 1..) = client.read(buffer) {
+                log.lock().unwrap().extend_from_slice(&buffer[..bytes_read]);
+                if server.write_all(&buffer[..bytes_read]).is_err() {
+                    break;
+                }
+            }
+            let _ = server.shutdown(Shutdown::Both);
+        });
+        Wire { mock, port, sent }
+    }
+
+    fn connect(&self) -> PFlexRobot {
+        PFlexRobot::new_with_port("127.0.0.1", self.port, true)
+    }
+
+    /// Starts a client with a robot that is powered, attached and homed
+    fn ready(&self) -> PFlexRobot {
+        {
+            let mut state = self.mock.state();
+            state.power = true;
+            state.attached = true;
+            state.homed = true;
+        }
+        self.connect()
+    }
+
+    /// Asserts the bytes sent since the last check, waiting for requests that aren't replied to
+    fn assert_sent(&self, expected: &str) {
+        let deadline = Instant::now() + Duration::from_secs(1);
+        while self.sent.lock().unwrap().len() < expected.len() && Instant::now() < deadline {
+            thread::sleep(Duration::from_millis(5));
+        }
+        let sent = self.sent.lock().unwrap().drain(..).collect::<Vec<u8>>();
+        assert_eq!(String::from_utf8_lossy(&sent), expected);
+    }
+
+    fn wait_for_motion(&self, robot: &mut PFlexRobot) {
+        robot
+            .wait_until_static(Seconds::new(5.0).unwrap())
+            .expect("Failed to wait for the move");
+        self.assert_sent("waitForEOM\n");
+    }
+}
+
+fn pose(x_mm: f64, y_mm: f64, z_mm: f64, yaw_deg: f64) -> EndEffectorPosition {
+    EndEffectorPosition {
+        x_mm: Millimetres::from_mm(x_mm),
+        y_mm: Millimetres::from_mm(y_mm),
+        z_mm: Millimetres::from_mm(z_mm),
+        yaw_deg: Degrees::from_deg(yaw_deg),
+        pitch_deg: PFlexRobot::DEFAULT_EE_PITCH,
+        roll_deg: PFlexRobot::DEFAULT_EE_ROLL,
+    }
+}
+
+#[test]
+fn bring_up() {
+    let wire = Wire::start();
+    let mut robot = wire.connect();
+    wire.assert_sent("");
+
+    assert!(robot.is_connection_alive());
+    wire.assert_sent("nop\n");
+    robot.set_mode(false).expect("Failed to set mode");
+    wire.assert_sent("mode 0\n");
+    robot.select_robot().expect("Failed to select robot");
+    wire.assert_sent("selectRobot 1\n");
+
+    robot.set_power(true);
+    wire.assert_sent("hp 1\n");
+    assert_eq!(robot.is_robot_attached(), Ok(false));
+    wire.assert_sent("attach\n");
+    robot.attach_robot().expect("Failed to attach");
+    wire.assert_sent("attach 1\n");
+    assert_eq!(robot.is_robot_attached(), Ok(true));
+    wire.assert_sent("attach\n");
+    assert_eq!(robot.is_robot_home(), Ok(false));
+    wire.assert_sent("pd 2800 1 0 1\n");
+    robot.home_robot().expect("Failed to home");
+    wire.assert_sent("home\n");
+    assert_eq!(robot.get_home(), Ok(vec!["1".to_string()]));
+    wire.assert_sent("pd 2800 1 0 1\n");
+
+    robot.set_power(false);
+    wire.assert_sent("hp 0\n");
+    robot.ensure_ready().expect("Failed to bring up robot");
+    wire.assert_sent("hp 1\nattach\npd 2800 1 0 1\n");
+    assert_eq!(robot.get_previous_error(), Ok(vec!["0".to_string()]));
+    wire.assert_sent("pd 320\n");
+
+    robot.disconnect();
+    wire.assert_sent("exit\n");
+}
+
+#[test]
+fn status_queries() {
+    let wire = Wire::start();
+    let mut robot = wire.ready();
+    let start = wire.mock.state().position;
+
+    let position = robot
+        .get_endeffector_position()
+        .expect("Failed to read the pose");
+    wire.assert_sent("wherec\n");
+    assert_eq!(
+        position,
+        pose(start[0], start[1], start[2], start[3]),
+        "wherec should parse as x y z yaw pitch roll"
+    );
+    assert_eq!(robot.get_endeffector_position_in_frame(None), Ok(position));
+    wire.assert_sent("wherec\n");
+    let location = robot.get_location().expect("Failed to read the location");
+    wire.assert_sent("loc\n");
+    assert_eq!(location.len(), 8);
+
+    let joints = robot.get_joint_positions().expect("Failed to read joints");
+    wire.assert_sent("wherej\n");
+    let expected = wire.mock.state().joints();
+    assert_eq!(joints, expected);
+    assert_eq!(robot.get_all_joints().map(|joints| joints.len()), Ok(6));
+    wire.assert_sent("wherej\n");
+
+    assert_eq!(robot.get_motion_state(), Ok(vec!["1".to_string()]));
+    wire.assert_sent("state\n");
+    assert_eq!(robot.is_moving(), Ok(false));
+    wire.assert_sent("state\n");
+    assert_eq!(robot.get_system_speed(), Percent::new(50.0));
+    wire.assert_sent("mspeed\n");
+}
+
+#[test]
+fn settings() {
+    let wire = Wire::start();
+    let mut robot = wire.ready();
+
+    robot
+        .set_system_speed(Percent::new(80.0).unwrap())
+        .expect("Failed to set speed");
+    wire.assert_sent("mspeed 80\n");
+    assert_eq!(wire.mock.state().system_speed, 80);
+
+    robot
+        .set_payload(Mass::from_kg(0.25))
+        .expect("Failed to set payload");
+    wire.assert_sent("payload 50\n");
+    assert_eq!(robot.get_payload(), Ok(Mass::from_kg(0.25)));
+    wire.assert_sent("payload\n");
+
+    robot
+        .set_zero_torque(&[Joint::Shoulder, Joint::Elbow], true)
+        .expect("Failed to enable zero torque");
+    wire.assert_sent("zeroTorque 1 6\n");
+    robot
+        .set_zero_torque(&[], false)
+        .expect("Failed to disable zero torque");
+    wire.assert_sent("zeroTorque 0 -1\n");
+
+    robot.set_signal(1, true).expect("Failed to set signal");
+    wire.assert_sent("sig 1 1\n");
+    assert_eq!(robot.get_signal(1), Ok(true));
+    wire.assert_sent("sig 1\n");
+}
+
+#[test]
+fn free_mode_is_not_replied_to() {
+    let wire = Wire::start();
+    let mut robot = wire.ready();
+
+    robot
+        .set_free_mode(true)
+        .expect("Failed to enable free mode");
+    wire.assert_sent("freemode 2\nfreemode 3\nfreemode 4\n");
+    robot
+        .set_free_mode_joints(&[], true)
+        .expect("Failed to free every joint");
+    wire.assert_sent("freemode 0\n");
+    robot
+        .set_free_mode_joints(&[Joint::Shoulder, Joint::Elbow], false)
+        .expect("Failed to free the joints");
+    wire.assert_sent("freemode 2\nfreemode 3\n");
+    // requests are carried out in order, so free mode is on once the next reply arrives
+    assert!(robot.is_connection_alive());
+    wire.assert_sent("nop\n");
+    assert!(wire.mock.state().free_mode);
+
+    // the reply to the next request must be its own, not one left over from free mode
+    robot
+        .set_free_mode(false)
+        .expect("Failed to disable free mode");
+    wire.assert_sent("freemode -1\n");
+    assert!(!wire.mock.state().free_mode);
+    assert_eq!(robot.get_motion_state(), Ok(vec!["1".to_string()]));
+    wire.assert_sent("state\n");
+}
+
+#[test]
+fn tables() {
+    let wire = Wire::start();
+    let mut robot = wire.ready();
+
+    let waypoint = Waypoint::new(5, 300.0, -25.5, 150.0, 90.0, None);
+    robot
+        .create_waypoint(waypoint.clone())
+        .expect("Failed to store the location");
+    wire.assert_sent("locXyz 5 300 -25.5 150 90 90 -180\n");
+    assert_eq!(robot.get_waypoint(5, None), Ok(waypoint.clone()));
+    wire.assert_sent("loc 5\n");
+
+    let station = Station::new("hotel", Waypoint::new(6, 250.0, 10.0, 100.0, 0.0, None));
+    robot
+        .create_station(&station)
+        .expect("Failed to store the station");
+    wire.assert_sent("locXyz 6 250 10 100 0 90 -180\n");
+
+    let profile = MotionProfile::builder(3)
+        .speed(Percent::new(40.0).unwrap())
+        .speed2(Percent::new(20.0).unwrap())
+        .accel_ramp(Seconds::new(0.2).unwrap())
+        .straight_line(true)
+        .build()
+        .unwrap();
+    robot
+        .create_motion_profile(profile.clone())
+        .expect("Failed to store the profile");
+    wire.assert_sent("profile 3 40 20 50 50 0.2 0.1 10 1\n");
+    assert_eq!(robot.get_motion_profile(3), Ok(profile));
+    wire.assert_sent("profile 3\n");
+}
+
+#[test]
+fn motion() {
+    let wire = Wire::start();
+    let mut robot = wire.ready();
+    robot
+        .create_motion_profile(MotionProfile::with_defaults(1))
+        .expect("Failed to store the profile");
+    wire.assert_sent("profile 1 50 0 50 50 0.1 0.1 10 0\n");
+
+    robot
+        .move_to_cartesian(pose(350.0, 50.0, 200.0, 45.0), 1)
+        .expect("Failed to move");
+    // the profile goes first, then x y z yaw pitch roll
+    wire.assert_sent("movec 1 350 50 200 45 90 -180\n");
+    wire.wait_for_motion(&mut robot);
+    robot
+        .move_to_cartesian_in_frame(pose(300.0, 0.0, 200.0, 0.0), None, 1)
+        .expect("Failed to move");
+    wire.assert_sent("movec 1 300 0 200 0 90 -180\n");
+    wire.wait_for_motion(&mut robot);
+
+    robot
+        .create_waypoint(Waypoint::new(7, 320.0, 0.0, 180.0, 0.0, None))
+        .expect("Failed to store the location");
+    wire.assert_sent("locXyz 7 320 0 180 0 90 -180\n");
+    robot.move_to_waypoint(7, 1).expect("Failed to move");
+    wire.assert_sent("move 7 1\n");
+    wire.wait_for_motion(&mut robot);
+
+    let mut joints = wire.mock.state().joints();
+    joints.z_mm = Millimetres::from_mm(250.0);
+    robot
+        .move_to_joint_position(&joints, 1)
+        .expect("Failed to move");
+    let mut expected = vec!["movej".to_string(), "1".to_string()];
+    expected.extend(joints.to_payload());
+    wire.assert_sent(&format!("{}\n", expected.join(" ")));
+    wire.wait_for_motion(&mut robot);
+
+    let raw = joints.to_payload();
+    let mut args = vec!["1"];
+    args.extend(raw.iter().map(String::as_str));
+    robot.move_to_joints(args).expect("Failed to move");
+    wire.assert_sent(&format!("movej 1 {}\n", raw.join(" ")));
+    // the reply to movej is read, so the next request gets its own reply
+    assert_eq!(robot.get_motion_state(), Ok(vec!["1".to_string()]));
+    wire.assert_sent("state\n");
+
+    robot
+        .move_rail(Millimetres::from_mm(40.0))
+        .expect("Failed to move rail");
+    wire.assert_sent("moveRail 1 1 40\n");
+    robot.halt().expect("Failed to halt");
+    wire.assert_sent("halt\n");
+    assert_eq!(robot.get_motion_state(), Ok(vec!["1".to_string()]));
+    wire.assert_sent("state\n");
+}
+
+#[test]
+fn jogging_reads_the_position_first() {
+    let wire = Wire::start();
+    let mut robot = wire.ready();
+    let start = wire.mock.state().position;
+
+    robot
+        .jog_cartesian(
+            CartesianAxis::Z,
+            JogStep::Linear(Millimetres::from_mm(5.0)),
+            &JogFrame::World,
+            1,
+        )
+        .expect("Failed to jog");
+    let target = pose(start[0], start[1], start[2] + 5.0, start[3]).to_payload();
+    wire.assert_sent(&format!("wherec\nmovec 1 {}\n", target.join(" ")));
+    wire.wait_for_motion(&mut robot);
+
+    let joints = wire.mock.state().joints();
+    robot
+        .jog_joint(Joint::Z, JogStep::Linear(Millimetres::from_mm(-5.0)), 1)
+        .expect("Failed to jog");
+    let target = JointPosition {
+        z_mm: joints.z_mm - Millimetres::from_mm(5.0),
+        ..joints
+    };
+    wire.assert_sent(&format!(
+        "wherej\nmovej 1 {}\n",
+        target.to_payload().join(" ")
+    ));
+    wire.wait_for_motion(&mut robot);
+}
+
+#[test]
+fn gripper() {
+    let wire = Wire::start();
+    let mut robot = wire.ready();
+
+    robot
+        .move_gripper(Millimetres::from_mm(130.0), 1)
+        .expect("Failed to open the gripper");
+    wire.assert_sent("moveoneaxis 5 130 1\n");
+    wire.wait_for_motion(&mut robot);
+
+    wire.mock.state().plate_mm = Some(127.0);
+    let grasped = robot
+        .grasp_plate(
+            Millimetres::from_mm(127.5),
+            Percent::new(50.0).unwrap(),
+            Percent::new(20.0).unwrap(),
+        )
+        .expect("Failed to grasp");
+    assert!(grasped);
+    wire.assert_sent("graspplate 127.5 50 20\n");
+    robot
+        .release_plate(Millimetres::from_mm(130.0), Percent::new(50.0).unwrap())
+        .expect("Failed to release");
+    wire.assert_sent("releaseplate 130 50\n");
+}
+
+#[test]
+fn shared_connection_selects_the_robot() {
+    let wire = Wire::start();
+    let mut first = wire.connect();
+    let mut second = first.connection().robot(2, false);
+
+    assert!(first.is_connection_alive());
+    wire.assert_sent("nop\n");
+    // the simulator only drives one robot, so selecting the second is refused
+    assert!(second.get_motion_state().is_err());
+    wire.assert_sent("selectRobot 2\n");
+    assert!(first.is_connection_alive());
+    wire.assert_sent("nop\n");
+}
@@ -0,0 +1,116 @@
+use pflex_module_rs::connection::SharedConnection;
+use pflex_module_rs::mock::{Fault, FaultRule, MockRobot};
+use pflex_module_rs::units::Percent;
+use std::thread;
+use std::time::Duration;
+
+#[test]
+fn robots_share_a_connection() {
+    let mock = MockRobot::start_with_robots(2).expect("Failed to start mock robot");
+    let connection = mock.connect_shared().expect("Failed to connect");
+    let mut first = connection.robot(1, false);
+    let mut second = connection.robot(2, false);
+    assert_eq!(second.robot_index(), 2);
+
+    second.set_power(true);
+    assert_eq!(connection.selected_robot(), 2);
+    assert!(mock.robot_state(2).power);
+    assert!(!mock.robot_state(1).power);
+
+    assert_eq!(first.is_robot_attached(), Ok(false));
+    assert_eq!(connection.selected_robot(), 1);
+    second.attach_robot().expect("Failed to attach robot");
+    assert!(mock.robot_state(2).attached);
+    assert!(!mock.robot_state(1).attached);
+
+    // dropping one robot leaves the connection open for the other
+    drop(second);
+    assert!(connection.is_connected());
+    first.get_all_joints().expect("Failed to read joints");
+    drop(first);
+    connection.disconnect().expect("Failed to disconnect");
+    assert!(!connection.is_connected());
+}
+
+#[test]
+fn robot_is_selected_again_after_a_reconnect() {
+    let mock = MockRobot::start_with_robots(2).expect("Failed to start mock robot");
+    let connection =
+        SharedConnection::connect("127.0.0.1", mock.port(), Some(0.3)).expect("Failed to connect");
+    let mut second = connection.robot(2, false);
+    assert_eq!(second.is_robot_attached(), Ok(false));
+    assert_eq!(connection.selected_robot(), 2);
+
+    // a reply that times out drops the connection, and with it the selection
+    mock.robot_state(2)
+        .inject_fault(FaultRule::new(Some("attach"), Fault::DropReply).times(1));
+    assert!(second.is_robot_attached().is_err());
+    assert_eq!(connection.selected_robot(), 1);
+    second.set_power(true);
+    assert!(mock.robot_state(2).power);
+    assert!(!mock.robot_state(1).power);
+    assert_eq!(connection.selected_robot(), 2);
+}
+
+#[test]
+fn robot_is_attached_again_after_a_reconnect() {
+    let mock = MockRobot::start_with_robots(2).expect("Failed to start mock robot");
+    let connection =
+        SharedConnection::connect("127.0.0.1", mock.port(), Some(0.3)).expect("Failed to connect");
+    let mut second = connection.robot(2, false);
+    second.ensure_ready().expect("Failed to bring up robot");
+    let joints = second.get_all_joints().expect("Failed to read joints");
+
+    // the old connection closes once the late reply is sent, detaching the robot it attached
+    mock.robot_state(2).inject_fault(
+        FaultRule::new(Some("wherej"), Fault::Latency(Duration::from_millis(500))).times(1),
+    );
+    assert!(second.get_all_joints().is_err());
+    thread::sleep(Duration::from_millis(800));
+    assert!(mock.robot_state(2).attached);
+    assert!(!mock.robot_state(1).attached);
+
+    let mut args = vec!["1"];
+    args.extend(joints.iter().map(String::as_str));
+    second
+        .move_to_joints(args)
+        .expect("Failed to move after a reconnect");
+    assert_eq!(connection.selected_robot(), 2);
+}
+
+#[test]
+fn commands_do_not_interleave() {
+    let mock = MockRobot::start_with_robots(2).expect("Failed to start mock robot");
+    let connection = mock.connect_shared().expect("Failed to connect");
+    let handles = [(1, 20.0), (2, 80.0)].map(|(robot_index, speed)| {
+        let mut robot = connection.robot(robot_index, false);
+        thread::spawn(move || {
+            for _ in 0..50 {
+                robot
+                    .set_system_speed(Percent::new(speed).unwrap())
+                    .expect("Failed to set speed");
+                assert_eq!(robot.get_system_speed(), Percent::new(speed));
+            }
+        })
+    });
+    for handle in handles {
+        handle.join().expect("Robot thread failed");
+    }
+    assert_eq!(mock.robot_state(1).system_speed, 20);
+    assert_eq!(mock.robot_state(2).system_speed, 80);
+}
+
+#[test]
+fn robot_index_out_of_range() {
+    let mock = MockRobot::start().expect("Failed to start mock robot");
+    assert_eq!(mock.robot_count(), 1);
+    let mut robot = mock.connect(false);
+    robot.set_robot_index(2);
+    assert!(robot.get_all_joints().is_err());
+    assert!(robot.select_robot().is_err());
+    robot.set_robot_index(1);
+    robot.select_robot().expect("Failed to select robot");
+    assert_eq!(robot.connection().selected_robot(), 1);
+    robot.get_all_joints().expect("Failed to read joints");
+    assert!(MockRobot::start_with_robots(0).is_err());
+}
@@ -4,14 +4,14 @@ use pflex_module_rs::error_codes::ResponseCodes;
 fn check_success() {
     let code = "0".to_string();
     let result = ResponseCodes::check_code(code);
-    assert_eq!(result.is_ok(), true);
+    assert!(result.is_ok());
 }
 
 #[test]
 fn check_warning() {
     let code = "1".to_string();
     let result = ResponseCodes::check_code(code);
-    assert_eq!(result.is_ok(), true);
+    assert!(result.is_ok());
     println!("{}", result.unwrap())
 }
 
@@ -19,7 +19,7 @@ fn check_warning() {
 fn check_failure() {
     let code = "-1046".to_string();
     let result = ResponseCodes::check_code(code);
-    assert_eq!(result.is_err(), true);
+    assert!(result.is_err());
     println!("{}", result.unwrap_err())
 }
 
@@ -27,8 +27,8 @@ fn check_failure() {
 fn check_catch_all() {
     let code = "-6942069".to_string();
     let result = ResponseCodes::check_code(code);
-    if result.is_err() {
-        assert_eq!(result.unwrap_err(), "-6942069");
+    if let Err(e) = result {
+        assert_eq!(e, "-6942069");
     }
 }
 
@@ -37,3 +37,16 @@ fn print_success_details() {
     println!("Success Description: {}", ResponseCodes::Success);
     println!("Success Code: {}", ResponseCodes::Success.value());
 }
+
+#[test]
+fn check_precondition_failures() {
+    let result = ResponseCodes::check_code("-1009".to_string());
+    assert_eq!(result, Err("PFError -1009: No robot attached.".to_string()));
+    let result = ResponseCodes::check_code("-1021".to_string());
+    assert_eq!(result, Err(ResponseCodes::RobotNotHomed.to_string()));
+    let result = ResponseCodes::check_code("-1012".to_string());
+    assert_eq!(
+        result,
+        Err("PFError -1012: Joint out-of-range.".to_string())
+    );
+}
@@ -0,0 +1,174 @@
+use pflex_module_rs::mock::{Fault, FaultRule, MockRobot};
+use pflex_module_rs::pflex::PFlexRobot;
+use pflex_module_rs::tcs_client::{TCSClient, TCSCommand};
+use pflex_module_rs::units::{Millimetres, Seconds};
+use std::num::NonZeroUsize;
+use std::time::{Duration, Instant};
+
+const SHORT_TIMEOUT: f64 = 0.5;
+
+fn connect_client(mock: &MockRobot) -> TCSClient {
+    let mut tcs = TCSClient::new();
+    tcs.connect_with_port("127.0.0.1", mock.port(), Some(SHORT_TIMEOUT))
+        .expect("Failed to connect to mock robot");
+    tcs
+}
+
+fn nop(tcs: &mut TCSClient) -> Result<Vec<String>, String> {
+    tcs.send_command(TCSCommand::NoOp, None, true, None)
+}
+
+fn ready_robot(mock: &MockRobot) -> PFlexRobot {
+    let mut robot = mock.connect(true);
+    robot.ensure_ready().expect("Failed to bring up robot");
+    robot
+}
+
+#[test]
+fn latency_delays_the_reply() {
+    let mock = MockRobot::start().expect("Failed to start mock robot");
+    let mut tcs = connect_client(&mock);
+    mock.inject_fault(
+        FaultRule::new(Some("nop"), Fault::Latency(Duration::from_millis(200))).times(1),
+    );
+    let started = Instant::now();
+    nop(&mut tcs).expect("Slow reply should still arrive");
+    assert!(started.elapsed() >= Duration::from_millis(200));
+
+    mock.inject_fault(FaultRule::new(
+        Some("nop"),
+        Fault::Latency(Duration::from_secs(1)),
+    ));
+    let error = nop(&mut tcs).unwrap_err();
+    assert!(error.contains("reconnected"), "{}", error);
+    // the late nop reply must not be taken for the reply to the next command
+    let joints = tcs
+        .send_command(TCSCommand::GetLocJoints, None, true, None)
+        .expect("Next command should get its own reply");
+    assert_eq!(joints.len(), 6, "{:?}", joints);
+    std::thread::sleep(Duration::from_secs(1));
+    mock.state().clear_faults();
+    assert_eq!(nop(&mut tcs), Ok(vec!["".to_string()]));
+}
+
+#[test]
+fn dropped_reply_times_out() {
+    let mock = MockRobot::start().expect("Failed to start mock robot");
+    let mut tcs = connect_client(&mock);
+    mock.inject_fault(FaultRule::new(Some("nop"), Fault::DropReply).times(1));
+    assert!(nop(&mut tcs).is_err());
+    assert!(mock.state().faults().is_empty());
+    nop(&mut tcs).expect("Next command should be answered");
+}
+
+#[test]
+fn closed_socket_is_an_error() {
+    let mock = MockRobot::start().expect("Failed to start mock robot");
+    let mut tcs = connect_client(&mock);
+    mock.inject_fault(FaultRule::new(Some("nop"), Fault::CloseSocket));
+    let error = nop(&mut tcs).unwrap_err();
+    assert!(error.contains("closed"), "{}", error);
+    assert!(nop(&mut tcs).is_err());
+    assert!(tcs.disconnect().is_ok());
+}
+
+#[test]
+fn fragmented_reply_is_reassembled() {
+    let mock = MockRobot::start().expect("Failed to start mock robot");
+    let mut robot = mock.connect(true);
+    let expected = robot.get_all_joints().expect("Failed to read joints");
+    mock.inject_fault(FaultRule::new(
+        Some("wherej"),
+        Fault::Fragment {
+            bytes: NonZeroUsize::new(3).unwrap(),
+            delay: Duration::from_millis(5),
+        },
+    ));
+    assert_eq!(robot.get_all_joints(), Ok(expected));
+}
+
+#[test]
+fn garbled_reply_is_an_error() {
+    let mock = MockRobot::start().expect("Failed to start mock robot");
+    let mut tcs = connect_client(&mock);
+    mock.inject_fault(FaultRule::new(Some("nop"), Fault::Garble).times(1));
+    assert!(nop(&mut tcs).is_err());
+    nop(&mut tcs).expect("Next reply should not be garbled");
+}
+
+#[test]
+fn injected_error_codes() {
+    let mock = MockRobot::start().expect("Failed to start mock robot");
+    let mut robot = mock.connect(true);
+    mock.inject_fault(FaultRule::new(Some("attach"), Fault::ErrorCode(-1046)).times(2));
+    robot.set_power(true);
+    let error = robot.attach_robot().unwrap_err();
+    assert!(error.contains("-1046"), "{}", error);
+    assert!(robot.attach_robot().is_err());
+    assert!(!mock.state().attached);
+    robot.attach_robot().expect("Failed to attach robot");
+    assert!(mock.state().attached);
+}
+
+#[test]
+fn estop_during_a_move() {
+    let mock = MockRobot::start().expect("Failed to start mock robot");
+    let mut robot = ready_robot(&mock);
+    mock.inject_fault(FaultRule::new(
+        Some("moveoneaxis"),
+        Fault::EStopAfter(Duration::from_millis(100)),
+    ));
+    robot
+        .move_gripper(Millimetres::from_mm(100.0), 1)
+        .expect("Move should start before the E-STOP");
+    let error = robot
+        .wait_until_static(Seconds::new(5.0).unwrap())
+        .unwrap_err();
+    assert!(error.contains("-1603"), "{}", error);
+
+    let state = mock.state();
+    assert!(state.estop && !state.power && !state.is_moving());
+    let stopped = state.joint_positions[4];
+    assert!(stopped > 0.0 && stopped < 100.0, "{}", stopped);
+}
+
+#[test]
+fn scenario_files() {
+    let rules = FaultRule::parse_scenario(
+        "# slow controller\n\
+         * latency 50\n\
+         \n\
+         hp error -1046 times 2\n\
+         wherec fragment 3 10 # split replies\n",
+    )
+    .expect("Scenario should parse");
+    assert_eq!(
+        rules,
+        vec![
+            FaultRule::new(None, Fault::Latency(Duration::from_millis(50))),
+            FaultRule::new(Some("hp"), Fault::ErrorCode(-1046)).times(2),
+            FaultRule::new(
+                Some("wherec"),
+                Fault::Fragment {
+                    bytes: NonZeroUsize::new(3).unwrap(),
+                    delay: Duration::from_millis(10)
+                }
+            ),
+        ]
+    );
+
+    let error = FaultRule::parse_scenario("nop drop\nnop explode\n").unwrap_err();
+    assert!(error.starts_with("line 2:"), "{}", error);
+    assert!(FaultRule::parse_scenario("nop latency soon").is_err());
+    assert!(FaultRule::parse_scenario("nop fragment 0 10").is_err());
+
+    let path = std::env::temp_dir().join(format!("pflex_scenario_{}.txt", std::process::id()));
+    std::fs::write(&path, "nop drop times 1\n").unwrap();
+    let mock = MockRobot::start().expect("Failed to start mock robot");
+    mock.load_scenario(&path).expect("Failed to load scenario");
+    std::fs::remove_file(&path).unwrap();
+    let mut tcs = connect_client(&mock);
+    assert!(nop(&mut tcs).is_err());
+    nop(&mut tcs).expect("Fault should only fire once");
+    assert!(mock.load_scenario("missing_scenario.txt").is_err());
+}
@@ -0,0 +1,131 @@
+use pflex_module_rs::enums::RobotModel;
+use pflex_module_rs::kinematics::{Handedness, Kinematics};
+use pflex_module_rs::mock::MockRobot;
+use pflex_module_rs::structs::{EndEffectorPosition, JointPosition};
+use pflex_module_rs::units::{Degrees, Millimetres};
+use pflex_module_rs::workspace::Workspace;
+
+const TOLERANCE: f64 = 1e-6;
+
+fn joints(shoulder_deg: f64, elbow_deg: f64, wrist_deg: f64) -> JointPosition {
+    JointPosition {
+        z_mm: Millimetres::from_mm(250.0),
+        shoulder_deg: Degrees::from_deg(shoulder_deg),
+        elbow_deg: Degrees::from_deg(elbow_deg),
+        wrist_deg: Degrees::from_deg(wrist_deg),
+        gripper_mm: Millimetres::from_mm(80.0),
+        rail_mm: None,
+    }
+}
+
+fn assert_joints_eq(a: &JointPosition, b: &JointPosition) {
+    let pairs = [
+        (a.z_mm.mm(), b.z_mm.mm()),
+        (a.shoulder_deg.deg(), b.shoulder_deg.deg()),
+        (a.elbow_deg.deg(), b.elbow_deg.deg()),
+        (a.wrist_deg.deg(), b.wrist_deg.deg()),
+        (a.gripper_mm.mm(), b.gripper_mm.mm()),
+    ];
+    for (left, right) in pairs {
+        assert!((left - right).abs() < TOLERANCE, "{:?} != {:?}", a, b);
+    }
+}
+
+#[test]
+fn forward_straight_arm() {
+    let kinematics = Kinematics::new(RobotModel::PF400, Handedness::Righty);
+    let pose = kinematics.forward(&joints(0.0, 0.0, 0.0));
+    let reach = kinematics.params.shoulder_to_elbow_mm
+        + kinematics.params.elbow_to_wrist_mm
+        + kinematics.params.wrist_to_tool_mm;
+    assert!((pose.x_mm.mm() - reach).abs() < TOLERANCE);
+    assert!(pose.y_mm.mm().abs() < TOLERANCE);
+    assert_eq!(pose.z_mm, Millimetres::from_mm(250.0));
+}
+
+#[test]
+fn inverse_round_trip() {
+    for (handedness, target) in [
+        (Handedness::Righty, joints(30.0, -60.0, 45.0)),
+        (Handedness::Lefty, joints(-30.0, 90.0, -120.0)),
+    ] {
+        let kinematics = Kinematics::new(RobotModel::PF3400, handedness);
+        let pose = kinematics.forward(&target);
+        let solved = kinematics
+            .inverse(&pose, None, target.gripper_mm)
+            .expect("Pose should be reachable");
+        assert_joints_eq(&solved, &target);
+        assert_eq!(Handedness::of(&solved), handedness);
+    }
+}
+
+#[test]
+fn rail_moves_the_base() {
+    let kinematics = Kinematics::new(RobotModel::PF400, Handedness::Righty);
+    let mut on_rail = joints(10.0, -20.0, 30.0);
+    let fixed = kinematics.forward(&on_rail);
+    on_rail.rail_mm = Some(Millimetres::from_mm(500.0));
+    let moved = kinematics.forward(&on_rail);
+    assert!(((moved.x_mm - fixed.x_mm).mm() - 500.0).abs() < TOLERANCE);
+    assert!((moved.y_mm - fixed.y_mm).mm().abs() < TOLERANCE);
+}
+
+#[test]
+fn unreachable_pose() {
+    let kinematics = Kinematics::new(RobotModel::PF400, Handedness::Righty);
+    let pose = EndEffectorPosition {
+        yaw_deg: Degrees::ZERO,
+        pitch_deg: Degrees::from_deg(90.0),
+        roll_deg: Degrees::from_deg(-180.0),
+        x_mm: Millimetres::from_mm(2000.0),
+        y_mm: Millimetres::ZERO,
+        z_mm: Millimetres::from_mm(100.0),
+    };
+    assert!(kinematics.inverse(&pose, None, Millimetres::ZERO).is_err());
+}
+
+#[test]
+fn robot_accepts_either_elbow_configuration() {
+    let mock = MockRobot::start().expect("Failed to start mock robot");
+    let mut robot = mock.connect(false);
+    robot.set_kinematics(Some(Kinematics::new(RobotModel::PF400, Handedness::Righty)));
+    let lefty = Kinematics::new(RobotModel::PF400, Handedness::Lefty);
+    let pose = lefty.forward(&joints(-30.0, 90.0, -60.0));
+
+    // the righty solution bends the elbow to -90, which is outside these limits
+    robot.set_workspace(Some(Workspace::parse("joint elbow 0 160").unwrap()));
+    assert_eq!(robot.check_pose(&pose, None), Ok(()));
+    robot.set_workspace(Some(Workspace::parse("joint elbow 100 160").unwrap()));
+    let error = robot.check_pose(&pose, None).unwrap_err();
+    assert!(error.contains("Elbow"), "{}", error);
+}
+
+#[test]
+fn robot_on_rail_checks_joints_from_the_rail() {
+    let mock = MockRobot::start().expect("Failed to start mock robot");
+    let mut robot = mock.connect(true);
+    robot.set_kinematics(Some(Kinematics::new(RobotModel::PF400, Handedness::Righty)));
+    robot.set_workspace(Some(Workspace::default()));
+    let pose = robot
+        .kinematics()
+        .unwrap()
+        .forward(&joints(0.0, -90.0, 90.0));
+
+    assert_eq!(robot.check_pose(&pose, Some(Millimetres::ZERO)), Ok(()));
+    let far = robot.check_pose(&pose, Some(Millimetres::from_mm(1000.0)));
+    assert!(far.unwrap_err().contains("out of reach"));
+
+    // moves are checked from where the rail is, the unpowered mock would refuse anything sent
+    let on_rail = |rail_mm: f64| JointPosition {
+        rail_mm: Some(Millimetres::from_mm(rail_mm)),
+        ..joints(0.0, -90.0, 90.0)
+    };
+    mock.state().set_joints(&on_rail(1000.0));
+    let error = robot.move_to_cartesian(pose.clone(), 1).unwrap_err();
+    assert!(error.contains("out of reach"), "{}", error);
+    mock.state().set_joints(&on_rail(0.0));
+    assert!(!robot
+        .move_to_cartesian(pose, 1)
+        .unwrap_err()
+        .contains("out of reach"));
+}
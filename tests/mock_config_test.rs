@@ -0,0 +1,135 @@
+use log::Level;
+use pflex_module_rs::enums::RobotModel;
+use pflex_module_rs::mock::{LogfmtLogger, MockConfig, MockRobot};
+use pflex_module_rs::units::{Millimetres, Seconds};
+use std::time::Instant;
+
+fn args(flags: &str) -> Vec<String> {
+    flags.split_whitespace().map(str::to_string).collect()
+}
+
+#[test]
+fn parse_config_file() {
+    let config = MockConfig::parse(
+        r#"
+        address = "127.0.0.1:0"
+        model = "PF3400"
+        rail = false
+        joints = [150.0, 10.0, 90.0, 0.0, 80.0, 0.0]
+        time_scale = 0.5
+        "#,
+    )
+    .expect("Config should parse");
+    assert_eq!(config.model, RobotModel::PF3400);
+    assert!(!config.rail);
+    assert_eq!(config.joints, Some([150.0, 10.0, 90.0, 0.0, 80.0, 0.0]));
+    assert_eq!(config.system_speed, MockConfig::default().system_speed);
+
+    assert!(MockConfig::parse("robots = 0").is_err());
+    assert!(MockConfig::parse("time_scale = -1.0").is_err());
+    assert!(MockConfig::parse("log_level = \"loud\"").is_err());
+    assert!(MockConfig::parse("colour = \"red\"").is_err());
+}
+
+#[test]
+fn flags_override_the_file() {
+    let path = std::env::temp_dir().join(format!("pflex_mock_{}.toml", std::process::id()));
+    std::fs::write(&path, "model = \"PF3400\"\nsystem_speed = 20\n").unwrap();
+    let config = MockConfig::from_args(args(&format!(
+        "--config {} --speed 80 --port 10200 --no-rail --power --robots 2 -q",
+        path.display()
+    )))
+    .expect("Flags should parse");
+    std::fs::remove_file(&path).unwrap();
+    assert_eq!(config.model, RobotModel::PF3400);
+    assert_eq!(config.system_speed, 80);
+    assert_eq!(config.address, "127.0.0.1:10200");
+    assert!(!config.rail && config.power);
+    assert_eq!(config.robots, 2);
+    assert_eq!(config.log_level, "error");
+
+    // flags given before the file still override it
+    std::fs::write(&path, "model = \"PF3400\"\nsystem_speed = 20\n").unwrap();
+    let config = MockConfig::from_args(args(&format!(
+        "--speed 80 --no-rail --config {}",
+        path.display()
+    )))
+    .expect("Flags should parse");
+    std::fs::remove_file(&path).unwrap();
+    assert_eq!(config.model, RobotModel::PF3400);
+    assert_eq!(config.system_speed, 80);
+    assert!(!config.rail);
+    assert!(MockConfig::from_args(args("--speed 80 --config")).is_err());
+
+    let config =
+        MockConfig::from_args(args("--model pf400 --joints 1,2,3,4,5,6 --replay s.jsonl")).unwrap();
+    assert_eq!(config.model, RobotModel::PF400);
+    assert_eq!(config.joints, Some([1.0, 2.0, 3.0, 4.0, 5.0, 6.0]));
+    assert_eq!(
+        config.replay.as_deref(),
+        Some(std::path::Path::new("s.jsonl"))
+    );
+
+    assert!(MockConfig::from_args(args("--speed")).is_err());
+    assert!(MockConfig::from_args(args("--speed fast")).is_err());
+    assert!(MockConfig::from_args(args("--joints 1,2,3")).is_err());
+    assert!(MockConfig::from_args(args("--model PF9000")).is_err());
+    assert!(MockConfig::from_args(args("--fly")).is_err());
+}
+
+#[test]
+fn simulator_follows_the_config() {
+    let config = MockConfig {
+        address: "127.0.0.1:0".to_string(),
+        robots: 2,
+        model: RobotModel::PF3400,
+        rail: false,
+        power: true,
+        joints: Some([150.0, 10.0, 90.0, 0.0, 80.0, 0.0]),
+        time_scale: 0.1,
+        ..MockConfig::default()
+    };
+    let mock = MockRobot::with_config(&config).expect("Failed to start mock robot");
+    assert_eq!(mock.robot_count(), 2);
+    {
+        let state = mock.robot_state(2);
+        assert!(state.power);
+        assert_eq!(state.rail_position, None);
+        assert_eq!(state.joint_positions[..5], [150.0, 10.0, 90.0, 0.0, 80.0]);
+    }
+
+    let mut robot = mock.connect(false);
+    robot.attach_robot().expect("Failed to attach robot");
+    robot.home_robot().expect("Failed to home robot");
+    let started = Instant::now();
+    robot
+        .move_gripper(Millimetres::ZERO, 1)
+        .expect("Failed to move gripper");
+    robot
+        .wait_until_static(Seconds::new(5.0).unwrap())
+        .expect("Failed to wait for the move");
+    // 80 mm takes over a second at the default speed without the time scale
+    assert!(
+        started.elapsed().as_secs_f64() < 0.5,
+        "{:?}",
+        started.elapsed()
+    );
+}
+
+#[test]
+fn log_lines_are_logfmt() {
+    let kvs = [("peer", "127.0.0.1:51234"), ("reply", "0 \"ok\"\r\n")];
+    let line = LogfmtLogger::format(
+        &log::Record::builder()
+            .args(format_args!("client connected"))
+            .level(Level::Info)
+            .target("pflex_module_rs::mock")
+            .key_values(&kvs)
+            .build(),
+    );
+    assert!(line.starts_with("ts="));
+    assert!(line.ends_with(
+        " level=INFO target=pflex_module_rs::mock msg=\"client connected\" \
+         peer=127.0.0.1:51234 reply=\"0 \\\"ok\\\"\\r\\n\""
+    ));
+}
@@ -0,0 +1,324 @@
+use pflex_module_rs::mock::{MockRobot, MockTables, RobotState};
+use pflex_module_rs::pflex::PFlexRobot;
+use pflex_module_rs::structs::{MotionProfile, Waypoint};
+use pflex_module_rs::units::{Millimetres, Percent, Seconds};
+use std::thread::sleep;
+use std::time::{Duration, Instant};
+
+const GRIPPER_PROFILE: i32 = 1;
+
+fn ready_robot(mock: &MockRobot) -> PFlexRobot {
+    let mut robot = mock.connect(true);
+    robot.ensure_ready().expect("Failed to bring up robot");
+    robot
+}
+
+fn timed_gripper_move(robot: &mut PFlexRobot, width_mm: f64) -> Duration {
+    let started = Instant::now();
+    robot
+        .move_gripper(Millimetres::from_mm(width_mm), GRIPPER_PROFILE)
+        .expect("Failed to move gripper");
+    robot
+        .wait_until_static(Seconds::new(5.0).unwrap())
+        .expect("Failed to wait for the move");
+    started.elapsed()
+}
+
+#[test]
+fn moves_take_time() {
+    let mock = MockRobot::start().expect("Failed to start mock robot");
+    let mut robot = ready_robot(&mock);
+    robot
+        .move_gripper(Millimetres::from_mm(80.0), GRIPPER_PROFILE)
+        .expect("Failed to move gripper");
+    assert!(mock.state().is_moving());
+    assert_eq!(robot.get_motion_state(), Ok(vec!["2".to_string()]));
+    std::thread::sleep(Duration::from_millis(200));
+    let halfway = robot.get_joint_positions().expect("Failed to read joints");
+    assert!(halfway.gripper_mm > Millimetres::ZERO);
+    assert!(halfway.gripper_mm < Millimetres::from_mm(80.0));
+
+    robot
+        .wait_until_static(Seconds::new(5.0).unwrap())
+        .expect("Failed to wait for the move");
+    assert!(!mock.state().is_moving());
+    assert_eq!(robot.get_motion_state(), Ok(vec!["1".to_string()]));
+    let joints = robot.get_joint_positions().expect("Failed to read joints");
+    assert_eq!(joints.gripper_mm, Millimetres::from_mm(80.0));
+}
+
+#[test]
+fn profile_and_system_speed_set_the_pace() {
+    let mock = MockRobot::start().expect("Failed to start mock robot");
+    let mut robot = ready_robot(&mock);
+    robot
+        .set_system_speed(Percent::MAX)
+        .expect("Failed to set speed");
+    robot
+        .create_motion_profile(MotionProfile::with_defaults(GRIPPER_PROFILE))
+        .expect("Failed to create profile");
+    let fast = timed_gripper_move(&mut robot, 20.0);
+
+    let slow = MotionProfile::builder(GRIPPER_PROFILE)
+        .speed(Percent::new(10.0).unwrap())
+        .build()
+        .unwrap();
+    robot
+        .create_motion_profile(slow)
+        .expect("Failed to create profile");
+    let slow = timed_gripper_move(&mut robot, 0.0);
+    assert!(slow > fast * 2, "{:?} is not slower than {:?}", slow, fast);
+}
+
+#[test]
+fn free_mode_drift_moves_the_joints_and_the_pose_together() {
+    let mock = MockRobot::start().expect("Failed to start mock robot");
+    let mut robot = ready_robot(&mock);
+    let start = robot.get_joint_positions().expect("Failed to read joints");
+    robot.set_free_mode(true).expect("Failed setting free mode");
+    // long enough for a few ticks of drift
+    sleep(Duration::from_millis(350));
+    robot
+        .set_free_mode(false)
+        .expect("Failed setting free mode");
+
+    let joints = robot.get_joint_positions().expect("Failed to read joints");
+    let pose = robot
+        .get_endeffector_position()
+        .expect("Failed to read position");
+    assert!(joints.shoulder_deg.deg() > start.shoulder_deg.deg());
+    // Z isn't released, so it stays where it was
+    assert_eq!(joints.z_mm, start.z_mm);
+    let expected = mock.state().kinematics.forward(&joints);
+    for (value, expected) in [
+        (pose.x_mm.mm(), expected.x_mm.mm()),
+        (pose.y_mm.mm(), expected.y_mm.mm()),
+        (pose.z_mm.mm(), expected.z_mm.mm()),
+        (pose.yaw_deg.deg(), expected.yaw_deg.deg()),
+    ] {
+        assert!((value - expected).abs() < 1e-6, "{:?} {:?}", pose, expected);
+    }
+}
+
+#[test]
+fn halt_stops_where_it_is() {
+    let mock = MockRobot::start().expect("Failed to start mock robot");
+    let mut robot = ready_robot(&mock);
+    robot
+        .move_gripper(Millimetres::from_mm(100.0), GRIPPER_PROFILE)
+        .expect("Failed to move gripper");
+    robot
+        .move_gripper(Millimetres::ZERO, GRIPPER_PROFILE)
+        .expect("Failed to move gripper");
+    std::thread::sleep(Duration::from_millis(100));
+    robot.halt().expect("Failed to halt");
+    robot.get_motion_state().expect("Failed to read state");
+
+    assert!(!mock.state().is_moving());
+    let stopped = mock.state().joint_positions[4];
+    assert!(stopped > 0.0 && stopped < 100.0, "{}", stopped);
+}
+
+#[test]
+fn bring_up_preconditions() {
+    let mock = MockRobot::start().expect("Failed to start mock robot");
+    let mut robot = mock.connect(true);
+    let gripper = Millimetres::from_mm(50.0);
+    let error = robot.move_gripper(gripper, GRIPPER_PROFILE).unwrap_err();
+    assert!(error.contains("-1046"), "{}", error);
+
+    robot.set_power(true);
+    let error = robot.move_gripper(gripper, GRIPPER_PROFILE).unwrap_err();
+    assert!(error.contains("-1009"), "{}", error);
+
+    robot.attach_robot().expect("Failed to attach robot");
+    assert_eq!(robot.is_robot_home(), Ok(false));
+    let error = robot.move_gripper(gripper, GRIPPER_PROFILE).unwrap_err();
+    assert!(error.contains("-1021"), "{}", error);
+
+    robot.home_robot().expect("Failed to home robot");
+    assert_eq!(robot.is_robot_home(), Ok(true));
+    robot
+        .move_gripper(gripper, GRIPPER_PROFILE)
+        .expect("Failed to move gripper");
+
+    mock.state().free_mode = true;
+    assert!(robot.move_gripper(gripper, GRIPPER_PROFILE).is_err());
+}
+
+#[test]
+fn invalid_location_and_profile() {
+    let mock = MockRobot::start().expect("Failed to start mock robot");
+    let mut robot = ready_robot(&mock);
+    assert!(robot.move_to_waypoint(5, GRIPPER_PROFILE).is_err());
+    robot
+        .create_waypoint(Waypoint::new(5, 300.0, 0.0, 200.0, 0.0, None))
+        .expect("Failed to create waypoint");
+    robot
+        .move_to_waypoint(5, GRIPPER_PROFILE)
+        .expect("Failed to move to waypoint");
+    assert!(robot.move_to_waypoint(5, 0).is_err());
+    assert!(robot
+        .move_gripper(Millimetres::from_mm(10.0), RobotState::MAX_PROFILE_ID + 1)
+        .is_err());
+    assert!(robot
+        .create_waypoint(Waypoint::new(0, 300.0, 0.0, 200.0, 0.0, None))
+        .is_err());
+}
+
+#[test]
+fn estop_stops_and_blocks_power() {
+    let mock = MockRobot::start().expect("Failed to start mock robot");
+    let mut robot = ready_robot(&mock);
+    robot
+        .move_gripper(Millimetres::from_mm(100.0), GRIPPER_PROFILE)
+        .expect("Failed to move gripper");
+    mock.state().press_estop();
+    assert!(!mock.state().is_moving());
+    assert!(!mock.state().power);
+
+    let error = robot.ensure_ready().unwrap_err();
+    assert!(error.contains("-1603"), "{}", error);
+    mock.state().release_estop();
+    robot.ensure_ready().expect("Failed to bring up robot");
+    assert!(mock.state().power);
+}
+
+#[test]
+fn stored_locations_and_profiles() {
+    let mock = MockRobot::start().expect("Failed to start mock robot");
+    let mut robot = ready_robot(&mock);
+    let waypoint = Waypoint::new(7, 350.0, -120.0, 220.0, 45.0, None);
+    robot
+        .create_waypoint(waypoint.clone())
+        .expect("Failed to create waypoint");
+    assert_eq!(robot.get_waypoint(7, None), Ok(waypoint.clone()));
+    assert!(robot.get_waypoint(8, None).is_err());
+
+    let profile = MotionProfile::builder(3)
+        .speed(Percent::new(25.0).unwrap())
+        .speed2(Percent::new(10.0).unwrap())
+        .straight_line(true)
+        .build()
+        .unwrap();
+    assert_eq!(
+        robot.get_motion_profile(3),
+        Ok(MotionProfile::with_defaults(3))
+    );
+    robot
+        .create_motion_profile(profile.clone())
+        .expect("Failed to create profile");
+    assert_eq!(robot.get_motion_profile(3), Ok(profile));
+
+    robot
+        .move_to_waypoint(7, 3)
+        .expect("Failed to move to waypoint");
+    robot
+        .wait_until_static(Seconds::new(5.0).unwrap())
+        .expect("Failed to wait for the move");
+    let reached = robot
+        .get_endeffector_position()
+        .expect("Failed to read position");
+    assert_eq!(reached, waypoint.to_endeffector_position());
+
+    // like the controller, the mock doesn't care about the case of a command
+    robot
+        .send_raw("LOCXYZ 8 300 0 180 0 90 -180")
+        .expect("Failed to store location");
+    assert_eq!(robot.send_raw("WhereJ").map(|v| v.len()), Ok(6));
+}
+
+#[test]
+fn tables_saved_as_json() {
+    let mock = MockRobot::start().expect("Failed to start mock robot");
+    let mut robot = ready_robot(&mock);
+    robot
+        .create_waypoint(Waypoint::new(2, 300.0, 0.0, 200.0, 0.0, None))
+        .expect("Failed to create waypoint");
+    robot
+        .create_motion_profile(MotionProfile::with_defaults(4))
+        .expect("Failed to create profile");
+    let path = std::env::temp_dir().join(format!("pflex_tables_{}.json", std::process::id()));
+    mock.save_tables(&path).expect("Failed to save tables");
+
+    let other = MockRobot::start().expect("Failed to start mock robot");
+    other.load_tables(&path).expect("Failed to load tables");
+    std::fs::remove_file(&path).unwrap();
+    assert_eq!(other.state().tables(), mock.state().tables());
+    assert_eq!(other.state().tables().locations.len(), 1);
+
+    let invalid = MockTables::from_json(r#"{"locations": {"0": [0, 0, 0, 0, 0, 0]}}"#)
+        .expect("Tables should parse");
+    assert!(other.state().set_tables(invalid).is_err());
+    assert!(MockTables::from_json(r#"{"locations": 1}"#).is_err());
+}
+
+#[test]
+fn grasp_needs_a_plate() {
+    let mock = MockRobot::start().expect("Failed to start mock robot");
+    let mut robot = ready_robot(&mock);
+    let speed = Percent::new(50.0).unwrap();
+    let wait = Seconds::new(5.0).unwrap();
+    mock.state().system_speed = 100;
+    mock.state().plate_mm = Some(80.0);
+    robot
+        .release_plate(Millimetres::from_mm(90.0), speed)
+        .expect("Failed to open gripper");
+    assert_eq!(
+        robot.grasp_plate(Millimetres::from_mm(78.0), speed, speed),
+        Ok(true)
+    );
+    robot.wait_until_static(wait).expect("Failed to wait");
+    assert!(mock.state().holding_plate);
+    assert_eq!(mock.state().joint_positions[4], 80.0);
+
+    robot
+        .release_plate(Millimetres::from_mm(90.0), speed)
+        .expect("Failed to release plate");
+    assert!(!mock.state().holding_plate);
+    mock.state().plate_mm = None;
+    assert_eq!(
+        robot.grasp_plate(Millimetres::from_mm(80.0), speed, speed),
+        Ok(false)
+    );
+    robot.wait_until_static(wait).expect("Failed to wait");
+    assert_eq!(mock.state().joint_positions[4], 0.0);
+}
+
+#[test]
+fn gripper_and_rail_limits() {
+    let mock = MockRobot::start().expect("Failed to start mock robot");
+    let mut robot = ready_robot(&mock);
+    let error = robot
+        .move_gripper(Millimetres::from_mm(200.0), GRIPPER_PROFILE)
+        .unwrap_err();
+    assert!(error.contains("-1012"), "{}", error);
+    let error = robot.move_rail(Millimetres::from_mm(2500.0)).unwrap_err();
+    assert!(error.contains("-1012"), "{}", error);
+    assert!(!mock.state().is_moving());
+
+    mock.state().system_speed = 100;
+    robot
+        .move_rail(Millimetres::from_mm(400.0))
+        .expect("Failed to move rail");
+    robot
+        .wait_until_static(Seconds::new(5.0).unwrap())
+        .expect("Failed to wait");
+    assert_eq!(mock.state().joint_positions[5], 400.0);
+}
+
+#[test]
+fn digital_io() {
+    let mock = MockRobot::start().expect("Failed to start mock robot");
+    let mut robot = mock.connect(true);
+    assert_eq!(robot.get_signal(97), Ok(false));
+    robot.set_signal(97, true).expect("Failed to set output");
+    assert_eq!(robot.get_signal(97), Ok(true));
+    assert!(mock.state().signal(97));
+
+    let input = RobotState::FIRST_INPUT_SIGNAL;
+    assert_eq!(robot.get_signal(input), Ok(false));
+    mock.state().set_input(input, true);
+    assert_eq!(robot.get_signal(input), Ok(true));
+    assert!(robot.set_signal(input, false).is_err());
+}
@@ -0,0 +1,243 @@
+use pflex_module_rs::mock::MockRobot;
+use pflex_module_rs::pendant::{JogMode, Pendant, PendantOptions};
+use pflex_module_rs::units::{Degrees, Millimetres, Percent, Seconds};
+use ratatui::backend::TestBackend;
+use ratatui::crossterm::event::{KeyCode, KeyEvent, KeyModifiers};
+use ratatui::Terminal;
+
+fn ready_mock() -> MockRobot {
+    let mock = MockRobot::start().expect("Failed to start mock robot");
+    {
+        let mut state = mock.state();
+        state.power = true;
+        state.attached = true;
+        state.homed = true;
+        state.time_scale = 0.01;
+    }
+    mock
+}
+
+fn press(pendant: &mut Pendant, keys: &str) {
+    for c in keys.chars() {
+        pendant.handle_key(KeyEvent::from(KeyCode::Char(c)));
+    }
+}
+
+fn settle(pendant: &mut Pendant) {
+    pendant
+        .robot()
+        .wait_until_static(Seconds::new(5.0).unwrap())
+        .expect("Robot didn't stop");
+    pendant.refresh().expect("Failed to read the robot");
+}
+
+fn screen(pendant: &Pendant) -> String {
+    let mut terminal = Terminal::new(TestBackend::new(120, 20)).unwrap();
+    terminal.draw(|frame| pendant.draw(frame)).unwrap();
+    let buffer = terminal.backend().buffer();
+    buffer
+        .content()
+        .chunks(buffer.area.width as usize)
+        .map(|row| row.iter().map(|cell| cell.symbol()).collect::<String>())
+        .collect::<Vec<String>>()
+        .join("\n")
+}
+
+#[test]
+fn keys_jog_the_robot() {
+    let mock = ready_mock();
+    let mut pendant = Pendant::new(mock.connect(true));
+    settle(&mut pendant);
+    let start = pendant.readings().pose.clone().expect("No pose read");
+    assert_eq!(pendant.mode(), JogMode::Cartesian);
+    assert_eq!(pendant.linear_step(), Millimetres::from_mm(1.0));
+
+    // x forwards twice, then y back once, each step is taken from where the robot is
+    press(&mut pendant, "q");
+    settle(&mut pendant);
+    press(&mut pendant, "q");
+    settle(&mut pendant);
+    press(&mut pendant, "s");
+    settle(&mut pendant);
+    let pose = pendant.readings().pose.clone().unwrap();
+    assert_eq!(pendant.message(), "Jogged Y -1 mm");
+    assert!(
+        (pose.x_mm.mm() - start.x_mm.mm() - 2.0).abs() < 1e-6,
+        "{:?}",
+        pose
+    );
+    assert!((pose.y_mm.mm() - start.y_mm.mm() + 1.0).abs() < 1e-6);
+
+    // the shoulder in joint mode with a bigger step
+    press(&mut pendant, "m++");
+    assert_eq!(pendant.mode(), JogMode::Joint);
+    assert_eq!(pendant.angular_step(), Degrees::from_deg(5.0));
+    let shoulder = pendant
+        .readings()
+        .joints
+        .clone()
+        .unwrap()
+        .shoulder_deg
+        .deg();
+    press(&mut pendant, "w");
+    settle(&mut pendant);
+    let joints = pendant.readings().joints.clone().unwrap();
+    assert!((joints.shoulder_deg.deg() - shoulder - 5.0).abs() < 1e-6);
+    // steps stop at the ends of the list
+    press(&mut pendant, "-----");
+    assert_eq!(pendant.linear_step(), Pendant::LINEAR_STEPS[0]);
+
+    press(&mut pendant, " ");
+    assert_eq!(pendant.message(), "Halted");
+
+    // a refused jog is reported rather than stopping the pendant
+    mock.state().power = false;
+    press(&mut pendant, "a");
+    assert!(
+        pendant.message().starts_with("error: "),
+        "{}",
+        pendant.message()
+    );
+
+    pendant.handle_key(KeyEvent::new(KeyCode::Char('c'), KeyModifiers::CONTROL));
+    assert!(pendant.is_finished());
+}
+
+#[test]
+fn status_gripper_and_free_mode() {
+    let mock = ready_mock();
+    mock.state().plate_mm = Some(127.0);
+    let mut pendant = Pendant::new(mock.connect(true));
+    pendant.refresh().unwrap();
+    assert!(pendant.readings().powered && pendant.readings().homed);
+    assert_eq!(pendant.readings().system_speed, Percent::new(50.0).unwrap());
+
+    press(&mut pendant, "c");
+    assert_eq!(pendant.message(), "Plate grasped");
+    assert!(mock.state().holding_plate);
+    press(&mut pendant, "o");
+    assert_eq!(pendant.message(), "Gripper opened");
+
+    press(&mut pendant, "F");
+    assert_eq!(pendant.message(), "Free mode on");
+    pendant.refresh().unwrap();
+    assert!(mock.state().free_mode);
+    assert!(screen(&pendant).contains("Free yes"));
+    press(&mut pendant, "F");
+    assert!(!mock.state().free_mode);
+
+    press(&mut pendant, "P");
+    assert_eq!(pendant.message(), "Power off");
+    assert!(!mock.state().power);
+    pendant.refresh().unwrap();
+    let screen = screen(&pendant);
+    assert!(screen.contains("Power no"), "{}", screen);
+    assert!(screen.contains("HALT"), "{}", screen);
+}
+
+#[test]
+fn locations_taught_on_the_rail_move_it_back() {
+    let mock = ready_mock();
+    let mut pendant = Pendant::new(mock.connect(true));
+    let rail_mm = Millimetres::from_mm(500.0);
+    pendant.robot().move_rail(rail_mm).unwrap();
+    settle(&mut pendant);
+    pendant.save_location("stacker").unwrap();
+    assert_eq!(
+        pendant.locations().locations["stacker"].rail_position_mm,
+        Some(rail_mm)
+    );
+
+    pendant
+        .robot()
+        .move_rail(Millimetres::from_mm(0.0))
+        .unwrap();
+    settle(&mut pendant);
+    pendant.handle_key(KeyEvent::from(KeyCode::Enter));
+    assert_eq!(pendant.message(), "Moving to stacker");
+    settle(&mut pendant);
+    let joints = pendant.readings().joints.clone().unwrap();
+    assert_eq!(joints.rail_mm, Some(rail_mm));
+}
+
+#[test]
+fn locations_are_taught_and_revisited() {
+    let path = std::env::temp_dir().join(format!("pflex_pendant_{}.toml", std::process::id()));
+    std::fs::remove_file(&path).ok();
+    let mock = ready_mock();
+    let mut pendant = Pendant::new(mock.connect(true));
+    pendant.open_file(&path).expect("Failed to open teach file");
+    settle(&mut pendant);
+
+    press(&mut pendant, "nreader");
+    pendant.handle_key(KeyEvent::from(KeyCode::Enter));
+    assert_eq!(pendant.message(), "Saved reader as location 1");
+    let taught = pendant.locations().locations["reader"].clone();
+
+    press(&mut pendant, "qqq");
+    settle(&mut pendant);
+    pendant.save_location("hotel").unwrap();
+    assert_eq!(pendant.locations().locations["hotel"].id, 2);
+    // Tab cancels naming
+    press(&mut pendant, "nx");
+    pendant.handle_key(KeyEvent::from(KeyCode::Tab));
+    assert_eq!(pendant.message(), "Cancelled");
+    assert_eq!(pendant.locations().locations.len(), 2);
+    assert!(screen(&pendant).contains("reader"));
+
+    // Esc halts a move even while a location is being named
+    press(&mut pendant, "++++q");
+    assert!(mock.state().is_moving());
+    press(&mut pendant, "ny");
+    pendant.handle_key(KeyEvent::from(KeyCode::Esc));
+    assert_eq!(pendant.message(), "Halted");
+    assert!(!mock.state().is_moving());
+    assert!(!screen(&pendant).contains("Location name"));
+    press(&mut pendant, "----");
+
+    // locations are listed by name, so hotel comes first
+    pendant.handle_key(KeyEvent::from(KeyCode::Down));
+    pendant.handle_key(KeyEvent::from(KeyCode::Enter));
+    assert_eq!(pendant.message(), "Moving to reader");
+    settle(&mut pendant);
+    let pose = pendant.readings().pose.clone().unwrap();
+    assert_eq!(pose.x_mm, taught.x_mm);
+
+    let mut reopened = Pendant::new(mock.connect(true));
+    reopened
+        .open_file(&path)
+        .expect("Failed to read teach file");
+    assert_eq!(reopened.locations(), pendant.locations());
+    // removing a location has to be confirmed
+    press(&mut reopened, "x");
+    assert_eq!(reopened.message(), "Remove hotel? [y/n]");
+    press(&mut reopened, "n");
+    assert_eq!(reopened.message(), "Kept hotel");
+    assert_eq!(reopened.locations().locations.len(), 2);
+    press(&mut reopened, "xy");
+    assert_eq!(reopened.message(), "Removed hotel");
+    assert_eq!(reopened.locations().locations.len(), 1);
+    std::fs::remove_file(&path).ok();
+
+    let options = PendantOptions::from_args(
+        "--host 10.5.2.12 --no-rail --profile 3 --plate 85.5 --file cell.toml"
+            .split_whitespace()
+            .map(str::to_string),
+    )
+    .expect("Options should parse");
+    assert!(!options.rail);
+    assert_eq!(options.profile, 3);
+    assert_eq!(options.plate_width, Millimetres::from_mm(85.5));
+    let defaults = PendantOptions::from_args(Vec::new()).expect("Options should parse");
+    assert!(!defaults.rail);
+    assert!(
+        PendantOptions::from_args(vec!["--rail".to_string()])
+            .unwrap()
+            .rail
+    );
+    assert!(PendantOptions::from_args(vec!["--plate".to_string()]).is_err());
+    for timeout in ["0", "-1", "NaN", "inf"] {
+        let flags = vec!["--timeout".to_string(), timeout.to_string()];
+        assert!(PendantOptions::from_args(flags).is_err());
+    }
+}
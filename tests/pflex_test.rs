@@ -1,121 +1,173 @@
-use pflex_module_rs::pflex::PFlexRobot;
+use pflex_module_rs::enums::Joint;
+use pflex_module_rs::mock::MockRobot;
 use pflex_module_rs::structs::EndEffectorPosition;
-use std::{env, thread::sleep, time::Duration};
+use pflex_module_rs::teach::TeachSession;
+use pflex_module_rs::units::{Degrees, Millimetres, Seconds};
+use std::collections::BTreeSet;
+use std::thread::sleep;
+use std::time::{Duration, Instant};
 
 #[test]
-#[ignore = "requires robot"]
 fn check_vitals() {
-    let robot_ip = env!("ROBOT_IP");
-    if robot_ip.contains("0.0.0.0") {
-        panic!("ROBOT_IP ENV VAR has not been overridden");
-    }
-    let mut pflex_robot = PFlexRobot::new(robot_ip, true);
-    println!("All joint states: {:?}", pflex_robot.get_all_joints());
+    let mock = MockRobot::start().expect("Failed to start mock robot");
+    let mut pflex_robot = mock.connect(true);
+    let joints = pflex_robot.get_all_joints().expect("Failed to read joints");
+    assert_eq!(joints.len(), 6);
 }
 
 #[test]
-#[ignore = "requires robot"]
 fn enable_freedmode() {
-    let robot_ip = env!("ROBOT_IP");
-    if !robot_ip.contains("0.0.0.0") {
-        let mut flex_robot = PFlexRobot::new(robot_ip, true);
-        // error -1046
-        // power not enabled
-        flex_robot.set_power(true);
-
-        // error -1009
-        // no robot attached
-        flex_robot.attach_robot().expect("Robot not attached");
-
-        flex_robot
-            .set_free_mode(true)
-            .expect("Failed setting free mode");
-
-        sleep(Duration::from_secs(5));
-
-        flex_robot
-            .set_free_mode(false)
-            .expect("Failed setting free mode")
+    let mock = MockRobot::start().expect("Failed to start mock robot");
+    let mut flex_robot = mock.connect(true);
+    flex_robot.set_power(true);
+    flex_robot.attach_robot().expect("Robot not attached");
+    assert!(mock.state().attached);
+
+    flex_robot
+        .set_free_mode(true)
+        .expect("Failed setting free mode");
+    flex_robot
+        .set_free_mode(false)
+        .expect("Failed setting free mode");
+    // enabling free mode doesn't wait for its reply, so the last command may still be in flight
+    let deadline = Instant::now() + Duration::from_secs(1);
+    while mock.state().free_mode && Instant::now() < deadline {
+        sleep(Duration::from_millis(10));
     }
+    assert!(!mock.state().free_mode);
+}
+
+#[test]
+fn free_mode_keeps_z_locked() {
+    let mock = MockRobot::start().expect("Failed to start mock robot");
+    let mut robot = mock.connect(true);
+    robot.set_power(true);
+    robot.attach_robot().expect("Robot not attached");
+
+    // releasing every joint or Z on its own is refused without sending anything
+    assert!(robot.set_free_mode_joints(&[], false).is_err());
+    assert!(robot
+        .set_free_mode_joints(&[Joint::Shoulder, Joint::Z], false)
+        .is_err());
+    assert!(TeachSession::start(&mut robot, &[], false).is_err());
+    assert!(robot.is_connection_alive());
+    assert!(!mock.state().free_mode);
+
+    // switching free mode on releases the horizontal joints only
+    let horizontal: BTreeSet<i32> = Joint::HORIZONTAL.iter().map(Joint::value).collect();
+    robot.set_free_mode(true).expect("Failed setting free mode");
+    // free mode isn't replied to, the next reply means it has been carried out
+    assert!(robot.is_connection_alive());
+    assert!(mock.state().free_mode);
+    assert_eq!(mock.state().free_joints, horizontal);
+    robot
+        .set_free_mode(false)
+        .expect("Failed setting free mode");
+    assert!(!mock.state().free_mode);
+    assert!(mock.state().free_joints.is_empty());
+
+    robot
+        .set_free_mode_joints(&Joint::HORIZONTAL, false)
+        .expect("Failed to free the horizontal joints");
+    assert!(robot.is_connection_alive());
+    assert_eq!(mock.state().free_joints, horizontal);
+    robot
+        .set_free_mode(false)
+        .expect("Failed setting free mode");
+
+    robot
+        .set_free_mode_joints(&[Joint::Z], true)
+        .expect("Failed to free z");
+    assert!(robot.is_connection_alive());
+    assert!(mock.state().free_mode);
+    assert!(mock.state().free_joints.contains(&Joint::Z.value()));
+    robot
+        .set_free_mode(false)
+        .expect("Failed setting free mode");
+
+    robot
+        .set_free_mode_joints(&[], true)
+        .expect("Failed to free every joint");
+    assert!(robot.is_connection_alive());
+    assert_eq!(mock.state().free_joints.len(), 6);
 }
 
 #[test]
-#[ignore = "requires robot"]
 fn open_close_gripper() {
-    let robot_ip = env!("ROBOT_IP");
-    if robot_ip.contains("0.0.0.0") {
-        panic!("Please set ROBOT_IP env variable...")
-    }
-    let mut pf_robot = PFlexRobot::new(robot_ip, true);
-    let closed_width = 0.0;
-    let open_width = 60.0;
-    pf_robot.move_gripper(open_width, 0);
-    sleep(Duration::from_secs(5));
-    pf_robot.move_gripper(closed_width, 0);
+    let mock = MockRobot::start().expect("Failed to start mock robot");
+    let mut pf_robot = mock.connect(true);
+    pf_robot.ensure_ready().expect("Failed to bring up robot");
+    let closed_width = Millimetres::ZERO;
+    let open_width = Millimetres::from_mm(60.0);
+    pf_robot
+        .move_gripper(open_width, 1)
+        .expect("Failed to open gripper");
+    pf_robot
+        .move_gripper(closed_width, 1)
+        .expect("Failed to close gripper");
 }
 
 #[test]
-#[ignore = "requires robot"]
 fn move_rail() {
-    let robot_ip = env!("ROBOT_IP");
-    if robot_ip.contains("0.0.0.0") {
-        panic!("Please set ROBOT_IP env variable")
-    }
-    let mut pf_robot = PFlexRobot::new(robot_ip, true);
-    let go_to_rail_position = 300.00;
+    let mock = MockRobot::start().expect("Failed to start mock robot");
+    let mut pf_robot = mock.connect(true);
+    let go_to_rail_position = Millimetres::from_mm(300.0);
 
-    pf_robot.is_robot_attached().expect("Failed to attaching");
-    let home_robot = pf_robot.get_home();
-    if home_robot.is_err() {
-        print!("Robot error: {}", home_robot.unwrap_err())
-    }
-    // error -1021
-    // robot not homed
-    let movement = pf_robot.move_rail(go_to_rail_position);
-    if movement.is_err() {
-        print!("Robot error: {}", movement.unwrap_err())
-    }
+    assert_eq!(pf_robot.is_robot_attached(), Ok(false));
+    assert_eq!(pf_robot.is_robot_home(), Ok(false));
+    pf_robot.ensure_ready().expect("Failed to bring up robot");
+    assert_eq!(pf_robot.is_robot_home(), Ok(true));
+    pf_robot
+        .move_rail(go_to_rail_position)
+        .expect("Failed to move rail");
+
+    mock.state().power = false;
+    let error = pf_robot.move_rail(go_to_rail_position).unwrap_err();
+    assert!(error.contains("-1046"), "{}", error);
 }
 
 #[test]
-#[ignore = "requires robot"]
 fn get_position() {
-    let robot_ip = env!("ROBOT_IP");
-    if robot_ip.contains("0.0.0.0") {
-        panic!("Please set ROBOT_IP env variable")
-    }
-    let mut pf_robot = PFlexRobot::new(robot_ip, true);
-    let current_position = pf_robot.get_location();
-    print!("Current position data: {:?}", current_position)
+    let mock = MockRobot::start().expect("Failed to start mock robot");
+    let mut pf_robot = mock.connect(true);
+    let current_position = pf_robot.get_location().expect("Failed to read location");
+    assert!(!current_position.is_empty());
 }
 
 #[test]
-#[ignore = "requires robot"]
 fn move_to_position() {
-    let robot_ip = env!("ROBOT_IP");
-    if robot_ip.contains("0.0.0.0") {
-        panic!("Please set ROBOT_IP env variable")
-    }
-    let mut pf_robot = PFlexRobot::new(robot_ip, true);
+    let mock = MockRobot::start().expect("Failed to start mock robot");
+    let mut pf_robot = mock.connect(true);
     pf_robot.set_power(true);
-    let attached = pf_robot.is_robot_attached();
-    if attached.is_err() {
-        panic!("Robot error: {}", attached.unwrap_err())
-    }
-    let homed = pf_robot.get_home();
-    if homed.is_err() {
-        panic!("Robot error: {}", homed.unwrap_err())
-    }
+    pf_robot.attach_robot().expect("Failed to attach robot");
+    pf_robot.home_robot().expect("Failed to home robot");
     let position = EndEffectorPosition {
-        yaw_mm: -94.612,
-        pitch_mm: 90.0,
-        roll_mm: 180.0,
-        x_mm: 403.49,
-        y_mm: -364.189,
-        z_mm: 815.161,
+        yaw_deg: Degrees::from_deg(-94.612),
+        pitch_deg: Degrees::from_deg(90.0),
+        roll_deg: Degrees::from_deg(180.0),
+        x_mm: Millimetres::from_mm(403.49),
+        y_mm: Millimetres::from_mm(-364.189),
+        z_mm: Millimetres::from_mm(815.161),
     };
-    let move_cmd = pf_robot.move_to_cartesian(position, 0);
-    if move_cmd.is_err() {
-        println!("Oops: {}", move_cmd.unwrap_err())
-    }
+    pf_robot
+        .move_to_cartesian(position.clone(), 1)
+        .expect("Failed to move");
+    pf_robot
+        .wait_until_static(Seconds::new(10.0).unwrap())
+        .expect("Failed to wait for the move");
+    let reached = pf_robot
+        .get_endeffector_position()
+        .expect("Failed to read position");
+    assert_eq!(reached, position);
+}
+
+#[test]
+fn mock_shuts_down() {
+    let mock = MockRobot::start().expect("Failed to start mock robot");
+    let address = mock.address();
+    let mut pf_robot = mock.connect(false);
+    pf_robot.get_all_joints().expect("Failed to read joints");
+    drop(pf_robot);
+    mock.shutdown();
+    assert!(std::net::TcpStream::connect(address).is_err());
 }
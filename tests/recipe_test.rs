@@ -0,0 +1,125 @@
+use pflex_module_rs::recipe::Recipe;
+use pflex_module_rs::sequence::MotionStep;
+use std::time::Duration;
+
+const RECIPE: &str = r#"
+name = "plate transfer"
+default_profile = 2
+
+[[profiles]]
+id = 2
+speed_percent = 40.0
+straight_line = true
+
+[[stations]]
+name = "reader"
+id = 10
+x_mm = 400.0
+y_mm = -250.0
+z_mm = 180.0
+yaw_deg = 90.0
+
+[[steps]]
+gripper_mm = 120.0
+
+[[steps]]
+move = "reader"
+
+[[steps]]
+wait_signal = 10001
+state = true
+timeout_s = 5.0
+"#;
+
+#[test]
+fn parse_recipe() {
+    let recipe = Recipe::parse(RECIPE).expect("recipe should parse");
+    assert_eq!(recipe.name, "plate transfer");
+    assert_eq!(recipe.profiles.len(), 1);
+    assert_eq!(recipe.profiles[0].max_speed_percent.value(), 40.0);
+    assert!(recipe.profiles[0].straight_line);
+    assert_eq!(recipe.stations[0].location.id, 10);
+
+    let steps = recipe.sequence.steps();
+    assert_eq!(steps.len(), 3);
+    assert_eq!(
+        steps[1],
+        MotionStep::MoveToWaypoint {
+            waypoint_id: 10,
+            motion_profile_id: 2
+        }
+    );
+    assert_eq!(
+        steps[2],
+        MotionStep::WaitForSignal {
+            signal: 10001,
+            state: true,
+            timeout: Duration::from_secs(5)
+        }
+    );
+    assert_eq!(recipe.step_line(1), Some(21));
+}
+
+#[test]
+fn errors_report_line() {
+    let unknown_field = "name = \"x\"\n\n[[steps]]\nmove = \"a\"\nspeeed = 3\n";
+    let error = Recipe::parse(unknown_field).unwrap_err();
+    assert!(error.starts_with("line 5:"), "{}", error);
+
+    let unknown_station = "name = \"x\"\n\n[[steps]]\nmove = \"nowhere\"\n";
+    let error = Recipe::parse(unknown_station).unwrap_err();
+    assert_eq!(error, "line 3: unknown station nowhere");
+
+    let same_id = r#"name = "x"
+
+[[stations]]
+name = "reader"
+id = 10
+x_mm = 400.0
+y_mm = 0.0
+z_mm = 180.0
+yaw_deg = 0.0
+
+[[stations]]
+name = "hotel"
+id = 10
+x_mm = 0.0
+y_mm = 400.0
+z_mm = 180.0
+yaw_deg = 0.0
+"#;
+    let error = Recipe::parse(same_id).unwrap_err();
+    assert_eq!(
+        error,
+        "line 11: station hotel has the same id 10 as station reader"
+    );
+
+    let slow_profile = "name = \"x\"\n\n[[profiles]]\nid = 3\nspeed_percent = 0.5\n";
+    let error = Recipe::parse(slow_profile).unwrap_err();
+    assert!(
+        error.starts_with("line 3: Motion profile 3: speed"),
+        "{}",
+        error
+    );
+
+    let two_actions = "name = \"x\"\n[[steps]]\ndelay_s = 1.0\nspeed = 10\n";
+    let error = Recipe::parse(two_actions).unwrap_err();
+    assert_eq!(error, "line 2: step has more than one action");
+
+    let long_delay = "name = \"x\"\n\n[[steps]]\ndelay_s = 1e30\n";
+    let error = Recipe::parse(long_delay).unwrap_err();
+    assert!(error.starts_with("line 3: 1000000"), "{}", error);
+    let long_timeout = "name = \"x\"\n[[steps]]\nwait_signal = 1\ntimeout_s = 1e30\n";
+    let error = Recipe::parse(long_timeout).unwrap_err();
+    assert!(error.ends_with("is too long a duration"), "{}", error);
+    assert!(error.starts_with("line 2:"), "{}", error);
+}
+
+#[test]
+fn dry_run_plan() {
+    let recipe = Recipe::parse(RECIPE).expect("recipe should parse");
+    let plan = recipe.dry_run(None).expect("dry run should pass");
+    assert_eq!(plan.len(), 5);
+    assert_eq!(plan[1], "create station reader at location 10");
+    assert!(plan[3].starts_with("step 1 (line 21)"));
+}
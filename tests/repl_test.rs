@@ -0,0 +1,253 @@
+use pflex_module_rs::commands::TCSCommand;
+use pflex_module_rs::mock::MockRobot;
+use pflex_module_rs::repl::{complete, describe_reply, hint, Repl, ReplOptions, ReplOutcome};
+use pflex_module_rs::tcs_client::TCSClient;
+use std::time::{Duration, Instant};
+
+fn shell(mock: &MockRobot) -> Repl {
+    let mut client = TCSClient::new();
+    client
+        .connect_with_port("127.0.0.1", mock.port(), Some(1.0))
+        .expect("Failed to connect to mock robot");
+    Repl::new(client)
+}
+
+fn output(outcome: ReplOutcome) -> String {
+    match outcome {
+        ReplOutcome::Continue(text) => text,
+        ReplOutcome::Quit(text) => panic!("Shell quit: {}", text),
+    }
+}
+
+fn reply(line: &str) -> Vec<String> {
+    line.split(' ').map(str::to_string).collect()
+}
+
+#[test]
+fn requests_print_decoded_replies() {
+    let mock = MockRobot::start().expect("Failed to start mock robot");
+    let mut repl = shell(&mock);
+
+    assert_eq!(output(repl.eval("nop")), "ok");
+    assert_eq!(
+        output(repl.eval("attach 1")),
+        "error -1046: Robot power not enabled."
+    );
+    assert_eq!(output(repl.eval("hp 1")), "ok");
+    assert_eq!(output(repl.eval("hp")), "power: 1");
+    let pose = output(repl.eval("wherec"));
+    assert!(
+        pose.starts_with("x: ") && pose.contains("\npitch: 90\nroll: "),
+        "{}",
+        pose
+    );
+    assert_eq!(output(repl.eval("mspeed")), "speed: 50");
+    assert_eq!(output(repl.eval("")), "");
+    assert!(output(repl.eval("fly")).starts_with("error -1: "));
+
+    assert_eq!(repl.eval(":quit"), ReplOutcome::Quit(String::new()));
+    repl.disconnect();
+}
+
+#[test]
+fn free_mode_is_not_waited_for() {
+    let mock = MockRobot::start().expect("Failed to start mock robot");
+    let mut repl = shell(&mock);
+    output(repl.eval("hp 1"));
+    output(repl.eval("attach 1"));
+
+    let started = Instant::now();
+    assert_eq!(
+        output(repl.eval("FreeMode 0")),
+        "sent, the controller doesn't reply"
+    );
+    assert!(mock.state().free_mode);
+    assert_eq!(output(repl.eval("nop")), "ok");
+    assert!(started.elapsed() < Duration::from_secs(1));
+    assert_eq!(output(repl.eval("freemode -1")), "ok");
+    assert!(!mock.state().free_mode);
+}
+
+#[test]
+fn wait_for_eom_waits_for_the_move() {
+    let mock = MockRobot::start().expect("Failed to start mock robot");
+    {
+        let mut state = mock.state();
+        state.power = true;
+        state.attached = true;
+        state.homed = true;
+        // a move that takes longer than the connection's timeout of a second
+        state.time_scale = 3.0;
+    }
+    let mut repl = shell(&mock);
+    let joints = mock.state().joints();
+    let request = format!(
+        "movej 1 {} {} {} {} {}",
+        joints.z_mm.mm(),
+        joints.shoulder_deg.deg() + 60.0,
+        joints.elbow_deg.deg(),
+        joints.wrist_deg.deg(),
+        joints.gripper_mm.mm()
+    );
+    assert_eq!(output(repl.eval(&request)), "ok");
+
+    let started = Instant::now();
+    assert_eq!(output(repl.eval("waitForEOM")), "ok");
+    assert!(started.elapsed() > Duration::from_secs(1));
+    assert!(!mock.state().is_moving());
+    assert_eq!(output(repl.eval("nop")), "ok");
+}
+
+#[test]
+fn replies_are_described() {
+    assert_eq!(
+        describe_reply(Some(&TCSCommand::Attach), &reply("-1603 ")),
+        "error -1603: Hard E-STOP."
+    );
+    assert_eq!(
+        describe_reply(None, &reply("-2800 Undefined location")),
+        "error -2800: Undefined location"
+    );
+    assert_eq!(
+        describe_reply(Some(&TCSCommand::GetLocJoints), &reply("0 100 10 20 30 50")),
+        "z: 100\nshoulder: 10\nelbow: 20\nwrist: 30\ngripper: 50"
+    );
+    assert_eq!(
+        describe_reply(Some(&TCSCommand::GetParam), &reply("0 1 2")),
+        "1 2"
+    );
+    assert_eq!(
+        describe_reply(None, &reply("1 Near limit")),
+        "warning: Near limit"
+    );
+    assert_eq!(describe_reply(None, &[]), "no reply");
+    assert_eq!(
+        describe_reply(None, &reply("huh ?")),
+        "unrecognised reply: huh ?"
+    );
+}
+
+#[test]
+fn macros_run_and_persist() {
+    let mock = MockRobot::start().expect("Failed to start mock robot");
+    mock.state().time_scale = 0.01;
+    let mut repl = shell(&mock);
+
+    assert_eq!(
+        output(repl.eval(":def ready hp 1; attach 1; home")),
+        "defined @ready"
+    );
+    assert_eq!(
+        output(repl.eval("@ready")),
+        "tcs> hp 1\nok\ntcs> attach 1\nok\ntcs> home\nok"
+    );
+    {
+        let state = mock.state();
+        assert!(state.power && state.attached && state.homed);
+    }
+    // a failing line stops the macro
+    repl.define_macro("broken", vec!["fly".to_string(), "hp 0".to_string()])
+        .unwrap();
+    let text = output(repl.eval("@broken"));
+    assert!(!text.contains("hp 0"), "{}", text);
+    assert!(mock.state().power);
+    // a macro running itself stops instead of recursing forever
+    repl.define_macro("again", vec!["@again".to_string()])
+        .unwrap();
+    assert!(output(repl.eval("@again")).ends_with("error: macros nested deeper than 8"));
+    assert!(output(repl.eval("@missing")).starts_with("error: no macro"));
+    assert!(repl
+        .define_macro("no spaces", vec!["nop".to_string()])
+        .is_err());
+
+    let path = std::env::temp_dir().join(format!("pflex_macros_{}.macros", std::process::id()));
+    assert_eq!(
+        output(repl.eval(&format!(":save {}", path.display()))),
+        format!("saved 3 macros to {}", path.display())
+    );
+    let mut other = shell(&mock);
+    assert_eq!(other.load_macros(&path), Ok(3));
+    std::fs::remove_file(&path).ok();
+    assert_eq!(other.macros(), repl.macros());
+    assert_eq!(output(other.eval(":undef again")), "removed @again");
+    assert!(output(other.eval(":macros")).starts_with("@broken = fly; hp 0\n@ready = "));
+
+    let macros = Repl::parse_macros("# cell 3\n\nopen = releaseplate 130 50 # wide\n")
+        .expect("Macros should parse");
+    assert_eq!(macros["open"], vec!["releaseplate 130 50".to_string()]);
+    let err = Repl::parse_macros("nop\n").unwrap_err();
+    assert!(err.starts_with("line 1:"), "{}", err);
+}
+
+#[test]
+fn help_lists_every_command() {
+    let mock = MockRobot::start().expect("Failed to start mock robot");
+    let mut repl = shell(&mock);
+    let help = output(repl.eval(":help"));
+    assert!(help.contains("wherec"), "{}", help);
+    assert!(help.contains(":def <name> <request>; ..."), "{}", help);
+    assert_eq!(
+        output(repl.eval(":help hp")),
+        "hp [0|1]\n  Reads or switches the robot power"
+    );
+    assert!(output(repl.eval(":help fly")).starts_with("error: "));
+    assert!(output(repl.eval(":bogus")).starts_with("error: "));
+}
+
+#[test]
+fn commands_complete_and_hint() {
+    let macros = vec!["ready".to_string(), "rest".to_string()];
+    assert_eq!(
+        complete("where", 5, &macros),
+        (0, vec!["wherec".to_string(), "wherej".to_string()])
+    );
+    assert_eq!(
+        complete("locx", 4, &macros),
+        (0, vec!["locXyz".to_string()])
+    );
+    assert_eq!(
+        complete("@re", 3, &macros),
+        (0, vec!["@ready".to_string(), "@rest".to_string()])
+    );
+    assert_eq!(complete(":he", 3, &macros), (0, vec![":help".to_string()]));
+    assert_eq!(complete(":help mo", 8, &macros).0, 6);
+    assert!(complete(":help mo", 8, &macros)
+        .1
+        .contains(&"movec".to_string()));
+    // arguments aren't completed
+    assert_eq!(complete("hp ", 3, &macros), (3, vec![]));
+    // every command can be completed
+    assert_eq!(complete("", 0, &macros).1.len(), 29);
+
+    assert_eq!(
+        hint("graspp").as_deref(),
+        Some("late <width> <finger speed> <force>")
+    );
+    assert_eq!(hint("hp").as_deref(), Some(" [0|1]"));
+    assert_eq!(hint("hp ").as_deref(), Some("[0|1]"));
+    assert_eq!(hint("hp 1"), None);
+    assert_eq!(hint("home"), None);
+    assert_eq!(hint("move"), Some(" <location> <profile>".to_string()));
+    assert_eq!(hint("mo"), None);
+    assert_eq!(hint(":he"), None);
+}
+
+#[test]
+fn options_parse() {
+    let options = ReplOptions::from_args(
+        "--host 10.5.2.12 --port 10200 --macros cell.macros --no-history"
+            .split_whitespace()
+            .map(str::to_string),
+    )
+    .expect("Options should parse");
+    assert_eq!(options.host, "10.5.2.12");
+    assert_eq!(options.port, 10200);
+    assert_eq!(options.macros.unwrap().to_str(), Some("cell.macros"));
+    assert_eq!(options.history, None);
+    assert!(ReplOptions::from_args(vec!["--port".to_string()]).is_err());
+    assert!(ReplOptions::from_args(vec!["wherec".to_string()]).is_err());
+    for timeout in ["0", "-1", "NaN", "inf"] {
+        let flags = vec!["--timeout".to_string(), timeout.to_string()];
+        assert!(ReplOptions::from_args(flags).is_err());
+    }
+}
@@ -0,0 +1,192 @@
+use pflex_module_rs::mock::MockRobot;
+use pflex_module_rs::sequence::{
+    MotionSequence, MotionStep, SequenceControl, SequenceError, SequenceProgress,
+};
+use pflex_module_rs::structs::JointPosition;
+use pflex_module_rs::units::{Degrees, Percent, Seconds};
+use std::sync::mpsc;
+use std::thread;
+use std::time::{Duration, Instant};
+
+#[test]
+fn build_sequence() {
+    let speed = Percent::new(50.0).unwrap();
+    let sequence = MotionSequence::new()
+        .set_speed(speed)
+        .move_to_waypoint(1, 1)
+        .gripper(80.0, 1)
+        .wait_for_signal(10001, true, Duration::from_secs(5))
+        .set_signal(97, false)
+        .delay(Duration::from_millis(250));
+    assert_eq!(sequence.steps().len(), 6);
+    assert_eq!(sequence.steps()[0], MotionStep::SetSpeed(speed));
+    assert!(sequence.steps()[1].is_motion());
+    assert!(sequence.steps()[2].is_motion());
+    assert!(!sequence.steps()[3].is_motion());
+}
+
+#[test]
+fn control_states() {
+    let control = SequenceControl::new();
+    assert!(!control.is_paused());
+    control.pause();
+    assert!(control.is_paused());
+    control.resume();
+    assert!(!control.is_paused());
+    control.abort();
+    assert!(control.is_aborted());
+    // aborted sequences stay aborted
+    control.resume();
+    assert!(control.is_aborted());
+}
+
+#[test]
+fn error_reports_step() {
+    let error = SequenceError {
+        step: 3,
+        error: "-1046".to_string(),
+    };
+    assert_eq!(error.to_string(), "step 3: -1046");
+}
+
+fn ready_mock(time_scale: f64) -> MockRobot {
+    let mock = MockRobot::start().expect("Failed to start mock robot");
+    {
+        let mut state = mock.state();
+        state.power = true;
+        state.attached = true;
+        state.homed = true;
+        state.time_scale = time_scale;
+    }
+    mock
+}
+
+fn shoulder_target(mock: &MockRobot) -> JointPosition {
+    let mut target = mock.state().joints();
+    target.shoulder_deg += Degrees::from_deg(60.0);
+    target
+}
+
+#[test]
+fn execute_runs_every_step() {
+    let mock = ready_mock(0.01);
+    let mut robot = mock.connect(true);
+    let target = shoulder_target(&mock);
+    let sequence = MotionSequence::new()
+        .set_speed(Percent::new(50.0).unwrap())
+        .move_to_joints(target.clone(), 1)
+        .set_signal(1, true)
+        .delay(Duration::from_millis(10));
+
+    let mut events = Vec::new();
+    sequence
+        .execute(&mut robot, &SequenceControl::new(), |event| {
+            events.push(event)
+        })
+        .expect("Sequence failed");
+    let expected = (0..4)
+        .flat_map(|step| {
+            [
+                SequenceProgress::Started(step),
+                SequenceProgress::Completed(step),
+            ]
+        })
+        .collect::<Vec<SequenceProgress>>();
+    assert_eq!(events, expected);
+    let state = mock.state();
+    assert!(!state.is_moving());
+    assert_eq!(state.joints().shoulder_deg, target.shoulder_deg);
+}
+
+#[test]
+fn failed_step_is_reported() {
+    let mock = ready_mock(0.01);
+    let mut robot = mock.connect(true);
+    let sequence = MotionSequence::new()
+        .set_signal(1, true)
+        .move_to_waypoint(999, 1)
+        .set_signal(1, false);
+    let error = sequence
+        .execute(&mut robot, &SequenceControl::new(), |_| {})
+        .unwrap_err();
+    assert_eq!(error.step, 1);
+    // nothing after the failed step is carried out
+    assert_eq!(robot.get_signal(1), Ok(true));
+}
+
+#[test]
+fn paused_sequence_waits_to_be_resumed() {
+    let mock = ready_mock(0.01);
+    let mut robot = mock.connect(true);
+    let start = mock.state().joints();
+    let sequence = MotionSequence::new().move_to_joints(shoulder_target(&mock), 1);
+    let control = SequenceControl::new();
+    control.pause();
+
+    let (sender, events) = mpsc::channel();
+    thread::scope(|scope| {
+        let running = scope
+            .spawn(|| sequence.execute(&mut robot, &control, |event| sender.send(event).unwrap()));
+        assert_eq!(events.recv(), Ok(SequenceProgress::Paused(0)));
+        thread::sleep(Duration::from_millis(100));
+        assert_eq!(mock.state().joints(), start);
+        control.resume();
+        running.join().unwrap().expect("Sequence failed");
+    });
+    assert_eq!(
+        events.try_iter().collect::<Vec<SequenceProgress>>(),
+        vec![
+            SequenceProgress::Resumed(0),
+            SequenceProgress::Started(0),
+            SequenceProgress::Completed(0)
+        ]
+    );
+}
+
+#[test]
+fn timed_out_move_is_halted() {
+    // slow enough that the move takes far longer than the timeout
+    let mock = ready_mock(50.0);
+    let mut robot = mock.connect(true);
+    let target = shoulder_target(&mock);
+    let sequence = MotionSequence::new()
+        .move_to_joints(target.clone(), 1)
+        .with_motion_timeout(Seconds::new(0.2).unwrap());
+
+    let error = sequence
+        .execute(&mut robot, &SequenceControl::new(), |_| {})
+        .unwrap_err();
+    assert_eq!(error.step, 0);
+    assert!(error.error.ends_with(", halted"), "{}", error.error);
+    assert!(!mock.state().is_moving());
+    assert_ne!(mock.state().joints().shoulder_deg, target.shoulder_deg);
+}
+
+#[test]
+fn abort_halts_a_move_under_way() {
+    // slow enough that the move takes far longer than the test
+    let mock = ready_mock(50.0);
+    let mut robot = mock.connect(true);
+    let target = shoulder_target(&mock);
+    let sequence = MotionSequence::new()
+        .set_signal(1, true)
+        .move_to_joints(target.clone(), 1)
+        .set_signal(1, false);
+    let control = SequenceControl::new();
+
+    let (result, aborted) = thread::scope(|scope| {
+        let running = scope.spawn(|| sequence.execute(&mut robot, &control, |_| {}));
+        while !mock.state().is_moving() {
+            thread::sleep(Duration::from_millis(10));
+        }
+        control.abort();
+        let aborted = Instant::now();
+        (running.join().unwrap(), aborted.elapsed())
+    });
+    let error = result.unwrap_err();
+    assert_eq!(error.step, 1);
+    assert_eq!(error.error, "Sequence aborted");
+    assert!(aborted < Duration::from_secs(1), "{:?}", aborted);
+    assert!(!mock.state().is_moving());
+    assert_ne!(mock.state().joints().shoulder_deg, target.shoulder_deg);
+}
@@ -0,0 +1,81 @@
+use pflex_module_rs::enums::Joint;
+use pflex_module_rs::structs::{EndEffectorPosition, MotionProfile, Station, Waypoint};
+use pflex_module_rs::teach::TeachFile;
+use pflex_module_rs::units::{Degrees, Mass, Millimetres, Percent};
+
+#[test]
+fn stable_field_names() {
+    let pose = EndEffectorPosition {
+        x_mm: Millimetres::from_mm(1.0),
+        y_mm: Millimetres::from_mm(2.0),
+        z_mm: Millimetres::from_mm(3.0),
+        yaw_deg: Degrees::from_deg(90.0),
+        pitch_deg: Degrees::from_deg(90.0),
+        roll_deg: Degrees::from_deg(-180.0),
+    };
+    let json = serde_json::to_value(&pose).expect("pose should serialize");
+    assert_eq!(json["yaw_deg"], 90.0);
+    assert_eq!(json["roll_deg"], -180.0);
+    assert_eq!(json["x_mm"], 1.0);
+    assert_eq!(
+        serde_json::to_string(&Joint::Shoulder).unwrap(),
+        "\"shoulder\""
+    );
+    assert_eq!(
+        serde_json::to_string(&Mass::from_grams(250.0)).unwrap(),
+        "0.25"
+    );
+}
+
+#[test]
+fn teach_file_round_trip() {
+    let mut file = TeachFile::new();
+    file.locations.insert(
+        "safe".to_string(),
+        Waypoint::new(1, 300.0, 0.0, 400.0, 0.0, None),
+    );
+    file.profiles.push(MotionProfile::with_defaults(2));
+    file.stations.push(Station::new(
+        "reader",
+        Waypoint::new(
+            10,
+            400.0,
+            -250.0,
+            180.0,
+            90.0,
+            Some(Millimetres::from_mm(500.0)),
+        ),
+    ));
+
+    let json = serde_json::to_string(&file).expect("file should serialize");
+    let read: TeachFile = serde_json::from_str(&json).expect("file should deserialize");
+    assert_eq!(read, file);
+
+    let toml = toml::to_string(&file).expect("file should serialize");
+    let read: TeachFile = toml::from_str(&toml).expect("file should deserialize");
+    assert_eq!(read.migrate(), Ok(file));
+}
+
+#[test]
+fn teach_file_versions() {
+    let file: TeachFile = serde_json::from_str(r#"{"version": 1}"#).unwrap();
+    assert!(file.locations.is_empty());
+    assert!(file.clone().migrate().is_ok());
+
+    let newer = TeachFile {
+        version: TeachFile::VERSION + 1,
+        ..file
+    };
+    assert!(newer.migrate().is_err());
+}
+
+#[test]
+fn checked_units_on_read() {
+    let percent: Percent = serde_json::from_str("40").unwrap();
+    assert_eq!(percent.value(), 40.0);
+    assert!(serde_json::from_str::<Percent>("150").is_err());
+
+    let json = serde_json::to_string(&MotionProfile::with_defaults(1)).unwrap();
+    let invalid = json.replace("\"accel_ramp_seconds\":0.1", "\"accel_ramp_seconds\":-1.0");
+    assert!(serde_json::from_str::<MotionProfile>(&invalid).is_err());
+}
@@ -0,0 +1,117 @@
+use pflex_module_rs::mock::{MockRobot, Replay};
+use pflex_module_rs::pflex::PFlexRobot;
+use pflex_module_rs::session::{Session, SessionRecorder};
+use pflex_module_rs::structs::JointPosition;
+use pflex_module_rs::tcs_client::{TCSClient, TCSCommand};
+use pflex_module_rs::units::Percent;
+use std::thread;
+use std::time::{Duration, Instant};
+
+fn run_script(robot: &mut PFlexRobot) -> (JointPosition, Percent) {
+    robot.ensure_ready().expect("Failed to bring up robot");
+    let joints = robot
+        .get_joint_positions()
+        .expect("Failed to read the joints");
+    let speed = robot.get_system_speed().expect("Failed to read the speed");
+    (joints, speed)
+}
+
+fn wait_until_finished(mock: &MockRobot) -> bool {
+    let deadline = Instant::now() + Duration::from_secs(1);
+    while !mock.replay_finished() && Instant::now() < deadline {
+        thread::sleep(Duration::from_millis(10));
+    }
+    mock.replay_finished()
+}
+
+#[test]
+fn recorded_session_replays_the_same_replies() {
+    let path = std::env::temp_dir().join(format!("pflex_session_{}.jsonl", std::process::id()));
+    let mock = MockRobot::start().expect("Failed to start mock robot");
+    let mut robot = mock.connect(true);
+    robot
+        .connection()
+        .start_recording(SessionRecorder::create(&path).expect("Failed to create recording"));
+    let recorded = run_script(&mut robot);
+    robot.disconnect();
+    mock.shutdown();
+
+    let session = Session::load(&path).expect("Failed to load recording");
+    std::fs::remove_file(&path).ok();
+    assert!(session.exchanges.len() > 3);
+    let first = &session.exchanges[0];
+    assert!(first.timestamp_s > 0.0 && first.elapsed_s >= 0.0);
+    assert!(first.reply.is_some() && first.error.is_none());
+    let last = session.exchanges.last().unwrap();
+    assert_eq!(last.request, "exit");
+    assert_eq!(last.reply, None);
+
+    let replay = MockRobot::start_replay(session).expect("Failed to start replay");
+    let mut robot = replay.connect(true);
+    assert_eq!(run_script(&mut robot), recorded);
+    robot.disconnect();
+    assert!(wait_until_finished(&replay));
+    assert!(replay.divergences().is_empty());
+}
+
+#[test]
+fn divergent_requests_are_flagged() {
+    let session = Session::parse(
+        r#"{"timestamp_s":1.0,"elapsed_s":0.001,"request":"hp 1","reply":"0 ","error":null}
+{"timestamp_s":1.5,"elapsed_s":0.001,"request":"attach 1","reply":"0 ","error":null}
+"#,
+    )
+    .expect("Failed to parse session");
+    let mock = MockRobot::start_replay(session).expect("Failed to start replay");
+    let mut tcs = TCSClient::new();
+    tcs.connect_with_port("127.0.0.1", mock.port(), Some(0.5))
+        .expect("Failed to connect to replay");
+
+    assert!(tcs
+        .send_command(TCSCommand::NoOp, None, true, None)
+        .is_err());
+    tcs.send_command(TCSCommand::Power, Some(vec!["1"]), true, None)
+        .expect("Recorded request should be answered");
+    assert!(!mock.replay_finished());
+    tcs.send_command(TCSCommand::Attach, Some(vec!["1"]), true, None)
+        .expect("Recorded request should be answered");
+    assert!(mock.replay_finished());
+    assert!(tcs
+        .send_command(TCSCommand::NoOp, None, true, None)
+        .is_err());
+
+    let divergences = mock.divergences();
+    assert_eq!(divergences.len(), 2);
+    assert_eq!(divergences[0].index, 0);
+    assert_eq!(divergences[0].expected.as_deref(), Some("hp 1"));
+    assert_eq!(divergences[0].received, "nop");
+    assert_eq!(divergences[1].index, 2);
+    assert_eq!(divergences[1].expected, None);
+}
+
+#[test]
+fn replay_serves_replies_in_order() {
+    let session = Session::parse(
+        r#"{"timestamp_s":1.0,"elapsed_s":0.0,"request":"nop","reply":"0 ","error":null}
+
+{"timestamp_s":2.0,"elapsed_s":0.0,"request":"nop","reply":"-1046 Power not enabled","error":null}
+"#,
+    )
+    .expect("Failed to parse session");
+    let mut replay = Replay::new(session);
+    assert_eq!(replay.answer("nop\n").as_deref(), Some("0 \r\n"));
+    assert_eq!(
+        replay.answer("nop").as_deref(),
+        Some("-1046 Power not enabled\r\n")
+    );
+    assert!(replay.is_finished());
+    assert_eq!(replay.served(), 2);
+    assert!(replay.divergences().is_empty());
+}
+
+#[test]
+fn invalid_sessions_are_rejected() {
+    let err = Session::parse("{\"request\":\"nop\"}\nnot json").unwrap_err();
+    assert!(err.starts_with("line 1:"), "{}", err);
+    assert!(Session::load("/nonexistent/session.jsonl").is_err());
+}
@@ -0,0 +1,87 @@
+use pflex_module_rs::structs::{JogLimits, JogStep, JointPosition, MotionProfile};
+use pflex_module_rs::units::{Degrees, Millimetres, Percent, Seconds};
+
+#[test]
+fn parse_joint_position() {
+    let response = ["150.5", "10", "20", "-30", "60", ""]
+        .iter()
+        .map(|s| s.to_string())
+        .collect::<Vec<String>>();
+    let joints = JointPosition::from_response(&response).expect("Failed to parse joints");
+    assert_eq!(joints.z_mm.mm(), 150.5);
+    assert_eq!(joints.wrist_deg.deg(), -30.0);
+    assert_eq!(joints.rail_mm, None);
+    assert_eq!(joints.to_payload(), vec!["150.5", "10", "20", "-30", "60"]);
+}
+
+#[test]
+fn parse_short_joint_position() {
+    let response = vec!["1".to_string(), "2".to_string()];
+    assert!(JointPosition::from_response(&response).is_err());
+}
+
+#[test]
+fn jog_limits() {
+    let limits = JogLimits::default();
+    let mm = |step: f64| JogStep::Linear(Millimetres::from_mm(step));
+    let deg = |step: f64| JogStep::Angular(Degrees::from_deg(step));
+    assert!(limits.check(mm(5.0)).is_ok());
+    assert!(limits.check(mm(-10.0)).is_ok());
+    assert!(limits.check(mm(10.5)).is_err());
+    assert!(limits.check(deg(-6.0)).is_err());
+    assert_eq!(
+        limits.check(deg(f64::NAN)),
+        Err("Jog step of NaN deg exceeds the limit of 5 deg".to_string())
+    );
+    assert_eq!(-mm(2.0), mm(-2.0));
+}
+
+#[test]
+fn build_motion_profile() {
+    let profile = MotionProfile::builder(3)
+        .speed(Percent::new(40.0).unwrap())
+        .speed2(Percent::new(20.0).unwrap())
+        .accel_ramp(Seconds::new(0.2).unwrap())
+        .in_range(MotionProfile::IN_RANGE_DISABLED)
+        .straight_line(true)
+        .build()
+        .expect("Profile should be valid");
+    assert_eq!(
+        profile.to_payload(),
+        Ok(vec!["3", "40", "20", "50", "50", "0.2", "0.1", "-1", "1"]
+            .into_iter()
+            .map(String::from)
+            .collect())
+    );
+    assert_eq!(
+        MotionProfile::with_defaults(1).to_payload().unwrap()[2],
+        "0"
+    );
+}
+
+#[test]
+fn motion_profile_limits() {
+    let builder = MotionProfile::builder(1);
+    assert!(builder
+        .clone()
+        .speed(Percent::new(0.5).unwrap())
+        .build()
+        .is_err());
+    assert!(builder
+        .clone()
+        .speed2(Percent::new(0.0).unwrap())
+        .build()
+        .is_err());
+    assert!(builder
+        .clone()
+        .decel_ramp(Seconds::new(30.0).unwrap())
+        .build()
+        .is_err());
+    assert!(builder.clone().in_range(-0.5).build().is_err());
+    assert!(builder.clone().in_range(f64::NAN).build().is_err());
+    assert!(builder.in_range(0.0).build().is_ok());
+
+    let mut profile = MotionProfile::with_defaults(1);
+    profile.max_accel_percent = Percent::new(0.0).unwrap();
+    assert!(profile.to_payload().is_err());
+}
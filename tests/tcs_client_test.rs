@@ -1,3 +1,4 @@
+use pflex_module_rs::mock::MockRobot;
 use pflex_module_rs::tcs_client::{TCSClient, TCSCommand};
 
 #[test]
@@ -13,33 +14,57 @@ fn fail_connect() {
 }
 
 #[test]
-#[ignore = "requires robot"]
+fn invalid_timeouts_are_errors() {
+    let mock = MockRobot::start().expect("Failed to start mock robot");
+    let mut tcs = TCSClient::new();
+    for timeout in [-1.0, 0.0, f64::NAN, f64::INFINITY] {
+        assert!(tcs
+            .connect_with_port("127.0.0.1", mock.port(), Some(timeout))
+            .is_err());
+    }
+    tcs.connect_with_port("127.0.0.1", mock.port(), Some(1.0))
+        .expect("Failed to connect to mock robot");
+    let result = tcs.send_command(TCSCommand::NoOp, None, true, Some(f64::NAN));
+    assert!(result.unwrap_err().contains("Failed to set read timeout"));
+    assert!(tcs.send_command(TCSCommand::NoOp, None, true, None).is_ok());
+}
+
+#[test]
 fn connect_power_disconnect() {
+    let mock = MockRobot::start().expect("Failed to start mock robot");
     let mut tcs = TCSClient::new();
-    tcs.connect("0.0.0.0", None)
+    tcs.connect_with_port("127.0.0.1", mock.port(), None)
         .expect("Failed to connect to TCS");
-    let _res_1 = tcs.send_command(TCSCommand::NoOp, None, true, None);
-    let _res_2 = tcs
-        .send_command(TCSCommand::Exit, None, false, None)
+    assert_eq!(
+        tcs.send_command(TCSCommand::NoOp, None, true, None),
+        Ok(vec!["".to_string()])
+    );
+    tcs.send_command(TCSCommand::Power, Some(vec!["1"]), true, None)
+        .expect("Failed to power on robot");
+    assert!(mock.state().power);
+    tcs.send_command(TCSCommand::Exit, None, false, None)
         .expect("Goodbye!");
+    tcs.disconnect().expect("Failed to disconnect");
 }
 
 #[test]
-#[ignore = "requires robot"]
 fn connect_home_disconnect() {
+    let mock = MockRobot::start().expect("Failed to start mock robot");
     let mut tcs = TCSClient::new();
-    tcs.connect("10.1.4.23", None)
+    tcs.connect_with_port("127.0.0.1", mock.port(), None)
         .expect("Failed to connect to TCS");
-    //tcs.connect("0.0.0.0", None, None).expect("Failed to connect to TCS");
-    // check if connected
+    // the robot has to be powered and attached before it can be homed
+    assert!(tcs
+        .send_command(TCSCommand::Home, None, true, None)
+        .is_err());
     tcs.send_command(TCSCommand::Power, Some(vec!["1"]), true, None)
         .expect("Failed to power on robot");
-    tcs.send_command(TCSCommand::NoOp, None, true, None)
-        .expect("Failed to connect to TCS");
-    // power should be either true or false
-    // attach selects a number of robot to connect to (make it zero)
-    tcs.send_command(TCSCommand::Attach, Some(vec!["0"]), true, None)
+    tcs.send_command(TCSCommand::Attach, Some(vec!["1"]), true, None)
         .expect("Failed to attach robot");
+    tcs.send_command(TCSCommand::Home, None, true, None)
+        .expect("Failed to home robot");
+    assert!(mock.state().homed);
     tcs.send_command(TCSCommand::Exit, None, false, None)
         .expect("Goodbye!");
+    tcs.disconnect().expect("Failed to disconnect");
 }
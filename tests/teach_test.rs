@@ -0,0 +1,75 @@
+use pflex_module_rs::mock::MockRobot;
+use pflex_module_rs::structs::{MotionProfile, Waypoint};
+use pflex_module_rs::teach::{TeachAction, TeachFile, TeachSession};
+use pflex_module_rs::units::Millimetres;
+
+#[test]
+fn parse_actions() {
+    assert_eq!(
+        TeachAction::parse("loc plate_nest"),
+        Some(TeachAction::CaptureLocation("plate_nest".to_string()))
+    );
+    assert_eq!(
+        TeachAction::parse("s reader"),
+        Some(TeachAction::CaptureStation("reader".to_string()))
+    );
+    assert_eq!(TeachAction::parse("where"), Some(TeachAction::Where));
+    assert_eq!(TeachAction::parse("done"), Some(TeachAction::Finish));
+}
+
+#[test]
+fn parse_invalid_actions() {
+    assert_eq!(TeachAction::parse(""), None);
+    assert_eq!(TeachAction::parse("loc"), None);
+    assert_eq!(TeachAction::parse("done now"), None);
+    assert_eq!(TeachAction::parse("jump"), None);
+}
+
+#[test]
+fn teach_from_keyboard() {
+    let mock = MockRobot::start().expect("Failed to start mock robot");
+    let mut pf_robot = mock.connect(true);
+    pf_robot.set_power(true);
+    pf_robot.attach_robot().expect("Robot not attached");
+    let mut session =
+        TeachSession::start(&mut pf_robot, &[], true).expect("Failed to start session");
+    assert!(mock.state().free_mode);
+    let keys = "loc plate_nest\nstation reader\njump\nwhere\ndone\n";
+    let mut output = Vec::new();
+    session
+        .run_interactive(keys.as_bytes(), &mut output)
+        .expect("Teaching failed");
+    let points = session.finish().expect("Failed to restore normal mode");
+    assert!(!mock.state().free_mode);
+    assert!(points.locations.contains_key("plate_nest"));
+    assert!(points.stations.contains_key("reader"));
+
+    let output = String::from_utf8(output).unwrap();
+    assert_eq!(output.matches("teach> ").count(), 5);
+    // an unknown command prints the help again
+    assert_eq!(output.matches("commands:").count(), 2);
+}
+
+#[test]
+fn teach_file_upload_and_download() {
+    let mock = MockRobot::start().expect("Failed to start mock robot");
+    let mut robot = mock.connect(true);
+    let mut file = TeachFile::new();
+    file.locations.insert(
+        "safe".to_string(),
+        Waypoint::new(1, 300.0, 0.0, 400.0, 0.0, Some(Millimetres::from_mm(250.0))),
+    );
+    file.profiles.push(MotionProfile::with_defaults(2));
+    file.upload(&mut robot)
+        .expect("Failed to upload teach file");
+
+    // adjust the location on the controller and read it back
+    mock.state().locations.get_mut(&1).unwrap()[2] = 410.0;
+    let uploaded = file.clone();
+    file.download(&mut robot)
+        .expect("Failed to download teach file");
+    assert_eq!(file.profiles, uploaded.profiles);
+    let safe = &file.locations["safe"];
+    assert_eq!(safe.z_mm, Millimetres::from_mm(410.0));
+    assert_eq!(safe.rail_position_mm, Some(Millimetres::from_mm(250.0)));
+}
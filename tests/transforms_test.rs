@@ -0,0 +1,79 @@
+use pflex_module_rs::structs::{EndEffectorPosition, Station, Waypoint};
+use pflex_module_rs::transforms::Transform;
+use pflex_module_rs::units::{Degrees, Millimetres};
+
+const TOLERANCE: f64 = 1e-6;
+
+fn assert_pose_eq(a: &EndEffectorPosition, b: &EndEffectorPosition) {
+    let pairs = [
+        (a.x_mm.mm(), b.x_mm.mm()),
+        (a.y_mm.mm(), b.y_mm.mm()),
+        (a.z_mm.mm(), b.z_mm.mm()),
+        (a.yaw_deg.deg(), b.yaw_deg.deg()),
+        (a.pitch_deg.deg(), b.pitch_deg.deg()),
+        (a.roll_deg.deg(), b.roll_deg.deg()),
+    ];
+    for (left, right) in pairs {
+        assert!((left - right).abs() < TOLERANCE, "{:?} != {:?}", a, b);
+    }
+}
+
+fn example_pose() -> EndEffectorPosition {
+    EndEffectorPosition {
+        yaw_deg: Degrees::from_deg(-94.612),
+        pitch_deg: Degrees::from_deg(90.0),
+        roll_deg: Degrees::from_deg(-180.0),
+        x_mm: Millimetres::from_mm(403.49),
+        y_mm: Millimetres::from_mm(-364.189),
+        z_mm: Millimetres::from_mm(815.161),
+    }
+}
+
+#[test]
+fn pose_round_trip() {
+    let pose = example_pose();
+    assert_pose_eq(&Transform::from_pose(&pose).to_pose(), &pose);
+}
+
+#[test]
+fn compose_with_inverse_is_identity() {
+    let transform = Transform::from_pose(&example_pose());
+    let identity = transform * transform.inverse();
+    assert_pose_eq(&identity.to_pose(), &Transform::IDENTITY.to_pose());
+}
+
+#[test]
+fn frame_from_three_points() {
+    let frame = Transform::from_three_points(
+        [100.0, 200.0, 50.0],
+        [100.0, 300.0, 50.0],
+        [0.0, 200.0, 50.0],
+    )
+    .expect("Points should define a frame");
+    // the frame's X axis points along world Y, so a point 10 mm along X lands 10 mm along Y
+    let point = frame.transform_point([10.0, 0.0, 0.0]);
+    assert!((point[0] - 100.0).abs() < TOLERANCE);
+    assert!((point[1] - 210.0).abs() < TOLERANCE);
+    assert!((point[2] - 50.0).abs() < TOLERANCE);
+}
+
+#[test]
+fn collinear_points_are_rejected() {
+    let frame = Transform::from_three_points([0.0, 0.0, 0.0], [1.0, 0.0, 0.0], [2.0, 0.0, 0.0]);
+    assert!(frame.is_err());
+}
+
+#[test]
+fn reteach_station() {
+    let original = [[0.0, 0.0, 0.0], [100.0, 0.0, 0.0], [0.0, 100.0, 0.0]];
+    // instrument moved 5 mm along X and rotated by 90 degrees
+    let measured = [[5.0, 0.0, 0.0], [5.0, 100.0, 0.0], [-95.0, 0.0, 0.0]];
+    let correction = Transform::reteach(original, measured).expect("Failed to reteach");
+    let station = Station::new("reader", Waypoint::new(1, 10.0, 0.0, 20.0, 0.0, None));
+    let moved = station.transformed(&correction);
+    assert!((moved.location.x_mm.mm() - 5.0).abs() < TOLERANCE);
+    assert!((moved.location.y_mm.mm() - 10.0).abs() < TOLERANCE);
+    assert!((moved.location.z_mm.mm() - 20.0).abs() < TOLERANCE);
+    assert!((moved.location.orientation_deg.deg() - 90.0).abs() < TOLERANCE);
+    assert_eq!(moved.location.id, 1);
+}
@@ -0,0 +1,66 @@
+use pflex_module_rs::enums::RobotModel;
+use pflex_module_rs::units::{Degrees, Mass, Millimetres, Percent, Seconds};
+use std::time::Duration;
+
+#[test]
+fn mass_conversions() {
+    let mass = Mass::from_grams(250.0);
+    assert_eq!(mass.kg(), 0.25);
+    assert_eq!(Mass::from_kg(0.25).grams(), 250.0);
+}
+
+#[test]
+fn payload_percent_of_rated() {
+    let percent = RobotModel::PF400.payload_percent(Mass::from_kg(0.25));
+    assert_eq!(percent, Ok(50.0));
+    let percent = RobotModel::PF3400.payload_percent(Mass::from_kg(0.5));
+    assert_eq!(percent, Ok(25.0));
+    assert_eq!(
+        RobotModel::PF400.payload_from_percent(50.0),
+        Mass::from_kg(0.25)
+    );
+}
+
+#[test]
+fn payload_out_of_range() {
+    assert!(RobotModel::PF400
+        .payload_percent(Mass::from_kg(0.6))
+        .is_err());
+    assert!(RobotModel::PF400
+        .payload_percent(Mass::from_kg(-0.1))
+        .is_err());
+    assert!(RobotModel::PF3400
+        .payload_percent(Mass::from_kg(0.6))
+        .is_ok());
+}
+
+#[test]
+fn distance_and_angle_arithmetic() {
+    let moved = Millimetres::from_mm(100.0) - Millimetres::from_mm(150.0);
+    assert_eq!(moved, Millimetres::from_mm(-50.0));
+    assert_eq!(moved.abs().mm(), 50.0);
+    assert_eq!(Degrees::from_radians(std::f64::consts::PI).deg(), 180.0);
+    assert_eq!(
+        (Degrees::from_deg(90.0) + Degrees::from_deg(45.0)).deg(),
+        135.0
+    );
+}
+
+#[test]
+fn percent_range() {
+    assert_eq!(Percent::new(40.0).map(|p| p.fraction()), Ok(0.4));
+    assert!(Percent::new(150.0).is_err());
+    assert!(Percent::new(-1.0).is_err());
+    assert!(Percent::new(f64::NAN).is_err());
+    assert_eq!(Percent::try_from(100), Ok(Percent::MAX));
+}
+
+#[test]
+fn seconds_to_duration() {
+    let seconds = Seconds::new(1.5).expect("Seconds should be valid");
+    assert_eq!(seconds.to_duration(), Ok(Duration::from_millis(1500)));
+    assert!(Seconds::new(1e30).unwrap().to_duration().is_err());
+    assert_eq!(Seconds::from(Duration::from_millis(250)).secs(), 0.25);
+    assert!(Seconds::new(-0.1).is_err());
+    assert!(Seconds::new(f64::INFINITY).is_err());
+}
@@ -0,0 +1,96 @@
+use pflex_module_rs::enums::Joint;
+use pflex_module_rs::mock::MockRobot;
+use pflex_module_rs::structs::{EndEffectorPosition, Waypoint};
+use pflex_module_rs::units::Degrees;
+use pflex_module_rs::workspace::Workspace;
+
+const CELL: &str = "
+# PF400 on the bench
+joint z 10 1000
+joint elbow -160 160
+bounds bench box -700 -700 0 700 700 1100
+keepout reader box 200 200 0 400 400 300   # plate reader
+keepout column cylinder 0 0 120 0 1100
+";
+
+fn pose(x_mm: f64, y_mm: f64, z_mm: f64) -> EndEffectorPosition {
+    EndEffectorPosition {
+        yaw_deg: Degrees::ZERO,
+        pitch_deg: Degrees::from_deg(90.0),
+        roll_deg: Degrees::from_deg(-180.0),
+        x_mm: x_mm.into(),
+        y_mm: y_mm.into(),
+        z_mm: z_mm.into(),
+    }
+}
+
+#[test]
+fn parse_workspace() {
+    let workspace = Workspace::parse(CELL).expect("Failed to parse workspace");
+    assert_eq!(workspace.joint_limits.len(), 2);
+    assert_eq!(workspace.bounds.len(), 1);
+    assert_eq!(workspace.keep_out.len(), 2);
+    assert!(workspace.zone("reader").is_some());
+    assert!(workspace.zone("fridge").is_none());
+}
+
+#[test]
+fn parse_errors_include_line() {
+    let result = Workspace::parse("joint z 0 100\nkeepout reader sphere 1 2 3");
+    assert!(result.unwrap_err().starts_with("line 2"));
+    assert!(Workspace::parse("joint knee 0 100").is_err());
+    // a bound that isn't finite would switch the check off
+    let result = Workspace::parse("joint z 0 100\nkeepout reader box 0 0 0 nan 10 10");
+    assert!(result.unwrap_err().starts_with("line 2"));
+    assert!(Workspace::parse("joint z -inf 100").is_err());
+}
+
+#[test]
+fn check_poses() {
+    let workspace = Workspace::parse(CELL).expect("Failed to parse workspace");
+    assert!(workspace.is_pose_safe(&pose(300.0, -300.0, 200.0)));
+    let reader = workspace.check_pose(&pose(300.0, 300.0, 200.0));
+    assert!(reader.unwrap_err().contains("reader"));
+    let column = workspace.check_pose(&pose(50.0, 50.0, 500.0));
+    assert!(column.unwrap_err().contains("column"));
+    let outside = workspace.check_pose(&pose(900.0, 0.0, 500.0));
+    assert!(outside.unwrap_err().contains("bench"));
+}
+
+#[test]
+fn check_joint_limits() {
+    let workspace = Workspace::parse(CELL).expect("Failed to parse workspace");
+    assert!(workspace.check_joint(Joint::Z, 500.0).is_ok());
+    assert!(workspace.check_joint(Joint::Z, 5.0).is_err());
+    assert!(workspace.check_joint(Joint::Elbow, -170.0).is_err());
+    assert!(workspace.check_joint(Joint::Wrist, 720.0).is_ok());
+}
+
+#[test]
+fn stored_locations_are_checked() {
+    let mock = MockRobot::start().expect("Failed to start mock robot");
+    {
+        let mut state = mock.state();
+        state.power = true;
+        state.attached = true;
+        state.homed = true;
+        state.time_scale = 0.01;
+    }
+    let mut robot = mock.connect(true);
+    robot
+        .create_waypoint(Waypoint::new(1, 300.0, 300.0, 200.0, 0.0, None))
+        .expect("Failed to store the reader location");
+    robot
+        .create_waypoint(Waypoint::new(2, 300.0, -300.0, 200.0, 0.0, None))
+        .expect("Failed to store the safe location");
+    robot.set_workspace(Some(
+        Workspace::parse(CELL).expect("Failed to parse workspace"),
+    ));
+
+    let reader = robot.move_to_waypoint(1, 1);
+    assert!(reader.unwrap_err().contains("reader"));
+    assert_eq!(robot.get_motion_state(), Ok(vec!["1".to_string()]));
+    robot
+        .move_to_waypoint(2, 1)
+        .expect("Failed to move to the safe location");
+}